@@ -252,37 +252,12 @@ configure_hooks() {
     RECURSOR_CMD="$INSTALL_DIR/$BINARY_NAME"
     [ "$OS" = "windows" ] && RECURSOR_CMD="$INSTALL_DIR/${BINARY_NAME}.exe"
     
-    if [ -f "$HOOKS_FILE" ]; then
-        if grep -q "recursor" "$HOOKS_FILE" 2>/dev/null; then
-            warn "Recursor already configured"
-            return
-        fi
-        cp "$HOOKS_FILE" "${HOOKS_FILE}.backup"
-        warn "Existing hooks.json backed up. Please merge manually:"
-        echo ""
-        echo "Add to $HOOKS_FILE:"
+    # Merges into any existing hooks.json, keeping other hooks
+    if "$RECURSOR_CMD" install-hooks --binary "$RECURSOR_CMD"; then
+        success "Configured $HOOKS_FILE"
+    else
+        warn "Could not update $HOOKS_FILE - run '$RECURSOR_CMD install-hooks' manually"
     fi
-    
-    cat > "$HOOKS_FILE" << EOF
-{
-  "version": 1,
-  "hooks": {
-    "beforeSubmitPrompt": [
-      { "command": "$RECURSOR_CMD save" }
-    ],
-    "beforeShellExecution": [
-      { "command": "$RECURSOR_CMD before-shell" }
-    ],
-    "afterShellExecution": [
-      { "command": "$RECURSOR_CMD after-shell" }
-    ],
-    "stop": [
-      { "command": "$RECURSOR_CMD restore" }
-    ]
-  }
-}
-EOF
-    success "Created $HOOKS_FILE"
 }
 
 # Success message
@@ -24,21 +24,9 @@ if %ERRORLEVEL% NEQ 0 (
     exit /b 1
 )
 
-:: Create hooks.json
-echo Creating hooks configuration...
-(
-echo {
-echo   "version": 1,
-echo   "hooks": {
-echo     "beforeSubmitPrompt": [
-echo       { "command": "%USERPROFILE%\.cursor\bin\recursor.exe save" }
-echo     ],
-echo     "stop": [
-echo       { "command": "%USERPROFILE%\.cursor\bin\recursor.exe restore" }
-echo     ]
-echo   }
-echo }
-) > "%USERPROFILE%\.cursor\hooks.json"
+:: Add Recursor to hooks.json, keeping any existing hooks
+echo Configuring Cursor hooks...
+"%USERPROFILE%\.cursor\bin\recursor.exe" install-hooks
 
 echo.
 echo   ========================================
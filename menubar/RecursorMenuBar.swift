@@ -93,7 +93,13 @@ class RecursorMenuBar: NSObject, NSApplicationDelegate {
     }
     
     func saveEnabledState() {
-        let json: [String: Any] = ["enabled": isEnabled]
+        // Merge into the existing config so other Recursor settings are preserved
+        var json: [String: Any] = [:]
+        if let data = try? Data(contentsOf: URL(fileURLWithPath: configFile)),
+           let existing = try? JSONSerialization.jsonObject(with: data) as? [String: Any] {
+            json = existing
+        }
+        json["enabled"] = isEnabled
         if let data = try? JSONSerialization.data(withJSONObject: json, options: .prettyPrinted) {
             try? data.write(to: URL(fileURLWithPath: configFile))
         }
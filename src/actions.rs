@@ -0,0 +1,493 @@
+//! User-defined action scripts
+//!
+//! Runs the user's own commands when a conversation's agent reaches certain
+//! points in its run, configured under `actions` in the config file:
+//!
+//! - `on_submit`: a prompt was submitted
+//! - `on_approval_needed`: a tool call has been pending long enough to need approval
+//! - `on_command_done`: a shell command or MCP tool call finished
+//! - `on_finished`: the agent stopped after completing or being aborted
+//! - `on_error`: the agent stopped with an error
+//!
+//! Each command runs through the shell, in the first workspace root, with an
+//! `ActionPayload` as JSON on stdin. Hooks never wait for actions: `dispatch`
+//! hands them to a detached `recursor run-actions` process, which runs them
+//! with a timeout and at most `max_concurrent` at a time (across all hooks),
+//! and appends failures to `~/.cursor/recursor_actions.log`.
+
+use crate::config::{Action, ActionsConfig};
+use crate::hooks::StopStatus;
+use crate::platform::WindowInfo;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Slack given to a runner between its command's timeout and its slot expiring
+const SLOT_GRACE_SECONDS: i64 = 10;
+
+/// How often to poll for a free slot or a finished command
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Point in an agent's run that can trigger actions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// A prompt was submitted
+    OnSubmit,
+    /// A tool call has been pending long enough to need approval
+    OnApprovalNeeded,
+    /// A shell command or MCP tool call finished
+    OnCommandDone,
+    /// The agent stopped after completing or being aborted
+    OnFinished,
+    /// The agent stopped with an error
+    OnError,
+}
+
+impl Trigger {
+    /// The trigger name as written in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OnSubmit => "on_submit",
+            Self::OnApprovalNeeded => "on_approval_needed",
+            Self::OnCommandDone => "on_command_done",
+            Self::OnFinished => "on_finished",
+            Self::OnError => "on_error",
+        }
+    }
+}
+
+/// What an action receives as JSON on stdin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionPayload {
+    /// What triggered the action
+    pub trigger: Trigger,
+    /// Conversation the event belongs to
+    pub conversation_id: String,
+    /// Workspace roots of the conversation
+    #[serde(default)]
+    pub workspace_roots: Vec<String>,
+    /// The shell command or MCP tool involved, if any
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Seconds the command (or, for stops, the whole run) took so far
+    #[serde(default)]
+    pub duration_seconds: Option<f64>,
+    /// The window Recursor returns the user to
+    #[serde(default)]
+    pub saved_window: Option<WindowInfo>,
+    /// How the agent stopped (for `on_finished` and `on_error`)
+    #[serde(default)]
+    pub status: Option<StopStatus>,
+}
+
+impl ActionPayload {
+    /// Create a payload with only the trigger and conversation set
+    pub fn new(trigger: Trigger, conversation_id: &str) -> Self {
+        Self {
+            trigger,
+            conversation_id: conversation_id.to_string(),
+            workspace_roots: Vec::new(),
+            command: None,
+            duration_seconds: None,
+            saved_window: None,
+            status: None,
+        }
+    }
+}
+
+/// Hand the actions configured for a payload's trigger to a background
+/// `run-actions` process of the given `recursor` binary. Never blocks on the
+/// actions and never fails; problems starting the runner are logged.
+pub fn dispatch(binary: &Path, config: &ActionsConfig, payload: &ActionPayload) {
+    if config.for_trigger(payload.trigger).is_empty() {
+        return;
+    }
+    if let Err(e) = spawn_runner(binary, payload) {
+        if let Ok(runner) = ActionRunner::new(config.clone()) {
+            runner.log_failure(payload.trigger, "run-actions", &format!("{:#}", e));
+        }
+    }
+}
+
+/// Spawn a detached runner process and write the payload to its stdin
+fn spawn_runner(binary: &Path, payload: &ActionPayload) -> Result<()> {
+    let json = serde_json::to_string(payload).context("Failed to serialize action payload")?;
+
+    let mut child = Command::new(binary)
+        .arg("run-actions")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to start action runner")?;
+    child
+        .stdin
+        .take()
+        .context("Action runner has no stdin")?
+        .write_all(json.as_bytes())
+        .context("Failed to send payload to action runner")?;
+    Ok(())
+}
+
+/// Runs configured actions, limiting concurrency with slot files
+pub struct ActionRunner {
+    config: ActionsConfig,
+    slots_dir: PathBuf,
+    log_path: PathBuf,
+}
+
+impl ActionRunner {
+    /// Create a runner using `~/.cursor` for its slots and log
+    pub fn new(config: ActionsConfig) -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self::in_dir(config, &home.join(".cursor")))
+    }
+
+    /// Create a runner keeping its slots and log in `dir`
+    pub fn in_dir(config: ActionsConfig, dir: &Path) -> Self {
+        Self {
+            config,
+            slots_dir: dir.join("recursor_actions"),
+            log_path: dir.join("recursor_actions.log"),
+        }
+    }
+
+    /// Path of the failure log
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Run every action for the payload's trigger, in parallel, and wait for them
+    pub fn run(&self, payload: &ActionPayload) -> Result<()> {
+        let input = serde_json::to_string(payload).context("Failed to serialize action payload")?;
+        let cwd = payload
+            .workspace_roots
+            .first()
+            .map(PathBuf::from)
+            .filter(|root| root.is_dir());
+
+        std::thread::scope(|scope| {
+            for action in self.config.for_trigger(payload.trigger) {
+                let (input, cwd) = (&input, cwd.as_deref());
+                scope.spawn(move || {
+                    if let Err(e) = self.run_one(action, input, cwd) {
+                        self.log_failure(payload.trigger, &action.command, &format!("{:#}", e));
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Wait for a free slot, then run one action to completion or its timeout
+    fn run_one(&self, action: &Action, input: &str, cwd: Option<&Path>) -> Result<()> {
+        let timeout = self.config.timeout_for(action);
+        let _slot = self.acquire_slot(timeout).ok_or_else(|| {
+            anyhow!(
+                "skipped: {} actions were still running after {}s",
+                self.config.max_concurrent,
+                timeout.as_secs()
+            )
+        })?;
+
+        let mut command = shell_command(&action.command);
+        if let Some(dir) = cwd {
+            command.current_dir(dir);
+        }
+        // Its own process group, so a timeout also stops whatever it started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start command")?;
+
+        // The command may exit without reading its input
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+        let stderr = child.stderr.take().map(|mut pipe| {
+            std::thread::spawn(move || {
+                let mut output = String::new();
+                let _ = pipe.read_to_string(&mut output);
+                output
+            })
+        });
+
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait().context("Failed to wait for command")? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                kill_process_tree(&mut child);
+                let _ = child.wait();
+                return Err(anyhow!("timed out after {}s", timeout.as_secs()));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        if status.success() {
+            return Ok(());
+        }
+        let stderr = stderr
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+        match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => Err(anyhow!("{}: {}", status, line.trim())),
+            None => Err(anyhow!("{}", status)),
+        }
+    }
+
+    /// Claim one of the `max_concurrent` slot files, waiting up to `timeout`.
+    /// A slot records when it expires, so slots left behind by a runner that
+    /// died are reclaimed.
+    fn acquire_slot(&self, timeout: Duration) -> Option<Slot> {
+        fs::create_dir_all(&self.slots_dir).ok()?;
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            for index in 0..self.config.max_concurrent.max(1) {
+                let path = self.slots_dir.join(format!("slot-{}", index));
+                if slot_expired(&path) {
+                    let _ = fs::remove_file(&path);
+                }
+                if let Ok(mut file) = OpenOptions::new().write(true).create_new(true).open(&path) {
+                    let expires = Utc::now()
+                        + chrono::Duration::seconds(timeout.as_secs() as i64 + SLOT_GRACE_SECONDS);
+                    let _ = file.write_all(expires.to_rfc3339().as_bytes());
+                    return Some(Slot { path });
+                }
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Append a failure to the log; logging is best-effort
+    fn log_failure(&self, trigger: Trigger, command: &str, message: &str) {
+        if let Some(parent) = self.log_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(mut file) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+        {
+            let _ = writeln!(
+                file,
+                "{} {} `{}`: {}",
+                Utc::now().to_rfc3339(),
+                trigger.as_str(),
+                command,
+                message
+            );
+        }
+    }
+}
+
+/// A claimed concurrency slot, released on drop
+struct Slot {
+    path: PathBuf,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Whether a slot file's expiry has passed.
+/// A slot that is still being written (no valid expiry yet) is only treated as
+/// expired once it is older than the grace period.
+fn slot_expired(path: &Path) -> bool {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return false;
+    };
+    match DateTime::parse_from_rfc3339(contents.trim()) {
+        Ok(expires) => Utc::now() > expires,
+        Err(_) => fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() as i64 > SLOT_GRACE_SECONDS),
+    }
+}
+
+/// Kill a command started by `run_one` along with everything it started
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: kill only sends a signal; the command leads its own process group
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+/// Build a command that runs `command` through the platform shell
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+    #[cfg(not(windows))]
+    {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::state::unique_test_dir;
+
+    fn action(command: String, timeout_seconds: Option<u64>) -> Action {
+        Action {
+            command,
+            timeout_seconds,
+        }
+    }
+
+    #[test]
+    fn actions_receive_the_payload_in_the_workspace() {
+        let dir = unique_test_dir("actions_payload");
+        let out = dir.join("payload.json");
+        let config = ActionsConfig {
+            on_finished: vec![action(
+                format!("cat > {0} && echo >> {0} && pwd >> {0}", out.display()),
+                None,
+            )],
+            ..Default::default()
+        };
+        let runner = ActionRunner::in_dir(config, &dir);
+
+        let mut payload = ActionPayload::new(Trigger::OnFinished, "c1");
+        payload.workspace_roots = vec![dir.display().to_string()];
+        payload.status = Some(StopStatus::Completed);
+        runner.run(&payload).expect("run");
+
+        let written = fs::read_to_string(&out).expect("action output");
+        let (json, cwd) = written.split_once('\n').expect("payload and cwd");
+        let received: ActionPayload = serde_json::from_str(json).expect("payload");
+        assert_eq!(received, payload);
+        assert_eq!(
+            fs::canonicalize(cwd.trim()).expect("cwd"),
+            fs::canonicalize(&dir).expect("dir")
+        );
+        assert!(!runner.log_path().exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failures_and_timeouts_are_logged() {
+        let dir = unique_test_dir("actions_failures");
+        let config = ActionsConfig {
+            on_error: vec![
+                action("echo 'light offline' >&2; exit 3".to_string(), None),
+                action("sleep 5".to_string(), Some(1)),
+            ],
+            ..Default::default()
+        };
+        let runner = ActionRunner::in_dir(config, &dir);
+
+        let started = Instant::now();
+        runner
+            .run(&ActionPayload::new(Trigger::OnError, "c1"))
+            .expect("run");
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let log = fs::read_to_string(runner.log_path()).expect("log");
+        assert!(log.contains(
+            "on_error `echo 'light offline' >&2; exit 3`: exit status: 3: light offline"
+        ));
+        assert!(log.contains("on_error `sleep 5`: timed out after 1s"));
+        assert_eq!(
+            fs::read_dir(dir.join("recursor_actions"))
+                .expect("slots dir")
+                .count(),
+            0
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn timeouts_kill_what_the_command_started() {
+        let dir = unique_test_dir("actions_process_group");
+        let pid_file = dir.join("sleep.pid");
+        let config = ActionsConfig {
+            on_error: vec![action(
+                format!("sleep 30 & echo $! > '{}'; wait", pid_file.display()),
+                Some(1),
+            )],
+            ..Default::default()
+        };
+        let runner = ActionRunner::in_dir(config, &dir);
+        runner
+            .run(&ActionPayload::new(Trigger::OnError, "c1"))
+            .expect("run");
+
+        let pid: libc::pid_t = fs::read_to_string(&pid_file)
+            .expect("pid file")
+            .trim()
+            .parse()
+            .expect("pid");
+        // Gone, or a zombie waiting for init to reap it
+        let gone = || {
+            // SAFETY: signal 0 only checks that the process exists
+            let exists = unsafe { libc::kill(pid, 0) } == 0;
+            let zombie = fs::read_to_string(format!("/proc/{}/stat", pid))
+                .map(|stat| stat.contains(") Z "))
+                .unwrap_or(false);
+            !exists || zombie
+        };
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !gone() && Instant::now() < deadline {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        assert!(gone(), "sleep {} outlived the action's timeout", pid);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn full_slots_are_waited_for_and_expired_slots_reclaimed() {
+        let dir = unique_test_dir("actions_slots");
+        let config = ActionsConfig {
+            max_concurrent: 1,
+            ..Default::default()
+        };
+        let runner = ActionRunner::in_dir(config, &dir);
+        let slot_path = dir.join("recursor_actions").join("slot-0");
+
+        let held = runner.acquire_slot(Duration::from_secs(1)).expect("slot");
+        assert!(runner.acquire_slot(Duration::from_millis(200)).is_none());
+        drop(held);
+        assert!(!slot_path.exists());
+
+        // A slot left behind by a runner that died
+        fs::write(
+            &slot_path,
+            (Utc::now() - chrono::Duration::seconds(1)).to_rfc3339(),
+        )
+        .expect("write expired slot");
+        assert!(runner.acquire_slot(Duration::from_millis(200)).is_some());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,133 @@
+//! Away-from-keyboard handling
+//!
+//! When the user hasn't touched the keyboard or mouse for `away.after_seconds`,
+//! pulling focus is pointless and notifications only pile up. Instead the engine
+//! records what happened as `AwayEvent`s in the state file and starts a
+//! background `recursor catch-up` process. Once the user is back, that process
+//! focuses the Cursor window of the most urgent conversation (a command waiting
+//! for approval before a finished run) and shows a digest of the rest.
+
+use crate::hooks::StopStatus;
+use crate::platform::WindowInfo;
+use crate::queue::PullKind;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A recorded event from while the user was away
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AwayEvent {
+    /// When it happened
+    pub at: DateTime<Utc>,
+    /// The conversation it happened in
+    pub conversation_id: String,
+    /// What needed the user
+    #[serde(flatten)]
+    pub kind: PullKind,
+    /// The Cursor window of the conversation, to focus on return
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor_window: Option<WindowInfo>,
+}
+
+impl AwayEvent {
+    /// Create an event that happened now
+    pub fn new(conversation_id: &str, kind: PullKind, cursor_window: Option<WindowInfo>) -> Self {
+        Self {
+            at: Utc::now(),
+            conversation_id: conversation_id.to_string(),
+            kind,
+            cursor_window,
+        }
+    }
+
+    /// Check if this event is stale (older than 12 hours)
+    pub fn is_stale(&self) -> bool {
+        (Utc::now() - self.at).num_hours() >= 12
+    }
+}
+
+/// The event to take the user to first: the most urgent, then the oldest
+pub fn most_urgent(events: &[AwayEvent]) -> Option<&AwayEvent> {
+    events.iter().min_by_key(|e| (e.kind.urgency(), e.at))
+}
+
+/// One-line summary of the events, e.g. "1 command waiting for approval, 2 runs finished"
+pub fn digest(events: &[AwayEvent]) -> String {
+    let count = |wanted: fn(PullKind) -> bool| events.iter().filter(|e| wanted(e.kind)).count();
+    let approvals = count(|k| k == PullKind::ApprovalNeeded);
+    let errors = count(|k| {
+        k == PullKind::Finished {
+            status: StopStatus::Error,
+        }
+    });
+    let finished = count(|k| matches!(k, PullKind::Finished { .. })) - errors;
+
+    let plural =
+        |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let mut parts = Vec::new();
+    if approvals > 0 {
+        parts.push(plural(
+            approvals,
+            "command waiting for approval",
+            "commands waiting for approval",
+        ));
+    }
+    if errors > 0 {
+        parts.push(plural(errors, "run failed", "runs failed"));
+    }
+    if finished > 0 {
+        parts.push(plural(finished, "run finished", "runs finished"));
+    }
+    if parts.is_empty() {
+        "nothing happened".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::Phase;
+    use chrono::Duration;
+
+    fn event(conversation_id: &str, kind: PullKind, minutes_ago: i64) -> AwayEvent {
+        AwayEvent {
+            at: Utc::now() - Duration::minutes(minutes_ago),
+            ..AwayEvent::new(conversation_id, kind, None)
+        }
+    }
+
+    #[test]
+    fn approvals_come_first_and_digest_counts_each_kind() {
+        let completed = PullKind::Finished {
+            status: StopStatus::Completed,
+        };
+        let failed = PullKind::Finished {
+            status: StopStatus::Error,
+        };
+        let events = vec![
+            event("done", completed, 30),
+            event("broken", failed, 20),
+            event("waiting", PullKind::ApprovalNeeded, 10),
+            event("also-done", completed, 5),
+        ];
+
+        assert_eq!(
+            most_urgent(&events).map(|e| e.conversation_id.as_str()),
+            Some("waiting")
+        );
+        assert_eq!(
+            most_urgent(&events[..2]).map(|e| e.conversation_id.as_str()),
+            Some("broken")
+        );
+        assert_eq!(
+            digest(&events),
+            "1 command waiting for approval, 1 run failed, 2 runs finished"
+        );
+
+        // An approval that was given, or a run the user already continued, is dropped
+        assert!(!PullKind::ApprovalNeeded.still_relevant(Phase::Working));
+        assert!(!completed.still_relevant(Phase::Working));
+        assert!(completed.still_relevant(Phase::Idle));
+    }
+}
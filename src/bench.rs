@@ -0,0 +1,315 @@
+//! Hook latency benchmark for `recursor bench`
+//!
+//! Runs a synthetic prompt (submit, a shell command, its result, stop) through
+//! the engine a number of times and times each hook, broken down into stages:
+//! parsing the payload, the fast-path answer (see `worker`), and within the
+//! handler, state file loads and saves, window queries, and focus, media and
+//! menu bar changes. Whatever is left of the handler (rules, the lifecycle,
+//! the engine's own waits) is reported as `other`.
+//!
+//! The benchmark uses a scratch state file and queue, starts no background
+//! processes and records nothing in the history, audit log or debug log.
+
+use crate::config::Config;
+use crate::engine::{self, Engine};
+use crate::hooks::HookEvent;
+use crate::platform::timing::TimingWindowManager;
+use crate::platform::WindowManager;
+use crate::predict::quantile;
+use crate::state::StateManager;
+use crate::worker::{HookJob, JobQueue};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Hooks of one synthetic prompt, in the order Cursor sends them
+pub const HOOK_EVENTS: &[&str] = &[
+    "beforeSubmitPrompt",
+    "beforeShellExecution",
+    "afterShellExecution",
+    "stop",
+];
+
+/// Conversation the synthetic prompt belongs to
+const CONVERSATION_ID: &str = "recursor-bench";
+
+/// Part of a hook's work that is timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Parsing the payload
+    Parse,
+    /// Working out the fast-path answer and queueing the event
+    Answer,
+    /// Reading the state file
+    StateLoad,
+    /// Writing the state file
+    StateSave,
+    /// Asking the window manager about windows, input and media
+    WindowQueries,
+    /// Focus, media and menu bar changes
+    Focus,
+}
+
+impl Stage {
+    /// Every stage, in report order
+    pub const ALL: [Stage; 6] = [
+        Stage::Parse,
+        Stage::Answer,
+        Stage::StateLoad,
+        Stage::StateSave,
+        Stage::WindowQueries,
+        Stage::Focus,
+    ];
+
+    /// Name for text output
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Answer => "fast path answer",
+            Self::StateLoad => "state load",
+            Self::StateSave => "state save",
+            Self::WindowQueries => "window queries",
+            Self::Focus => "focus and media",
+        }
+    }
+
+    /// Whether the stage is part of the engine handling the hook
+    pub fn in_handler(self) -> bool {
+        !matches!(self, Self::Parse | Self::Answer)
+    }
+}
+
+/// Shared handle that adds up time spent per stage. Thread-safe, so a
+/// `StateManager` timed with it can still be sent between threads.
+#[derive(Debug, Clone, Default)]
+pub struct Stopwatch(Arc<Mutex<BTreeMap<Stage, Duration>>>);
+
+impl Stopwatch {
+    /// Run `f`, adding the time it took to `stage`
+    pub fn time<T>(&self, stage: Stage, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        *self.times().entry(stage).or_default() += started.elapsed();
+        result
+    }
+
+    /// Take the time added up so far, resetting every stage
+    pub fn take(&self) -> BTreeMap<Stage, Duration> {
+        std::mem::take(&mut *self.times())
+    }
+
+    fn times(&self) -> std::sync::MutexGuard<'_, BTreeMap<Stage, Duration>> {
+        // A panic while timing leaves the totals usable
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Percentiles of one measurement, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Timing {
+    /// Median
+    pub p50_ms: f64,
+    /// 95th percentile
+    pub p95_ms: f64,
+    /// Slowest run
+    pub max_ms: f64,
+}
+
+impl Timing {
+    fn of(durations: &[Duration]) -> Self {
+        // Whole microseconds are plenty, and keep the JSON readable
+        let mut ms: Vec<f64> = durations
+            .iter()
+            .map(|d| d.as_micros() as f64 / 1000.0)
+            .collect();
+        ms.sort_by(f64::total_cmp);
+        if ms.is_empty() {
+            return Self {
+                p50_ms: 0.0,
+                p95_ms: 0.0,
+                max_ms: 0.0,
+            };
+        }
+        Self {
+            p50_ms: quantile(&ms, 0.5),
+            p95_ms: quantile(&ms, 0.95),
+            max_ms: ms[ms.len() - 1],
+        }
+    }
+}
+
+/// Timings of one hook
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookBench {
+    /// Hook event name
+    pub event: String,
+    /// Number of runs
+    pub runs: usize,
+    /// How long Cursor waits: parse plus the fast-path answer, or plus the
+    /// whole handler when the fast path is off
+    pub blocking: Timing,
+    /// The engine handling the hook
+    pub handler: Timing,
+    /// Time per stage
+    pub stages: BTreeMap<Stage, Timing>,
+    /// Handler time not accounted for by its stages
+    pub other: Timing,
+}
+
+/// One run of one hook
+#[derive(Debug, Default)]
+struct Run {
+    stages: BTreeMap<Stage, Duration>,
+    handler: Duration,
+}
+
+/// Run every hook of the synthetic prompt `iterations` times against a window
+/// manager, keeping state and queued events under `scratch_dir`
+pub fn run(
+    wm: Box<dyn WindowManager>,
+    config: &Config,
+    iterations: usize,
+    scratch_dir: &Path,
+) -> Result<Vec<HookBench>> {
+    let stopwatch = Stopwatch::default();
+    let state = StateManager::with_state_path(scratch_dir.join("recursor_state.json"))
+        .with_stopwatch(stopwatch.clone());
+    // Answering reads the state too, but that is part of the answer, not the handler
+    let answer_state = StateManager::with_state_path(scratch_dir.join("recursor_state.json"));
+    let queue = JobQueue::with_path(scratch_dir.join("recursor_jobs.jsonl"));
+    let engine = Engine::new(
+        Box::new(TimingWindowManager::new(wm, stopwatch.clone())),
+        state,
+        config.clone(),
+    )
+    .with_failsafe_timers(false)
+    .with_actions(false);
+
+    let mut runs: BTreeMap<&str, Vec<Run>> = BTreeMap::new();
+    for _ in 0..iterations {
+        for &event_name in HOOK_EVENTS {
+            let raw = synthetic_payload(event_name);
+            stopwatch.take();
+            let event = stopwatch.time(Stage::Parse, || HookEvent::parse(&raw))?;
+            stopwatch.time(Stage::Answer, || -> Result<()> {
+                let answer = engine::respond(config, &answer_state, &event)?.to_json();
+                queue.push(&HookJob {
+                    at: Utc::now(),
+                    command: "hook".to_string(),
+                    raw: raw.clone(),
+                    answer,
+                })?;
+                queue.take_all().map(|_| ())
+            })?;
+            let started = Instant::now();
+            engine
+                .handle(event)
+                .with_context(|| format!("{} failed", event_name))?;
+            let handler = started.elapsed();
+            runs.entry(event_name).or_default().push(Run {
+                stages: stopwatch.take(),
+                handler,
+            });
+        }
+    }
+
+    Ok(HOOK_EVENTS
+        .iter()
+        .map(|event| summarize(event, runs.get(event).map_or(&[], Vec::as_slice), config))
+        .collect())
+}
+
+/// Percentiles of the runs of one hook
+fn summarize(event: &str, runs: &[Run], config: &Config) -> HookBench {
+    let stage_time = |run: &Run, stage: Stage| run.stages.get(&stage).copied().unwrap_or_default();
+    let timing = |f: &dyn Fn(&Run) -> Duration| Timing::of(&runs.iter().map(f).collect::<Vec<_>>());
+
+    HookBench {
+        event: event.to_string(),
+        runs: runs.len(),
+        blocking: timing(&|run| {
+            let answer = if config.fast_path.enabled {
+                stage_time(run, Stage::Answer)
+            } else {
+                run.handler
+            };
+            stage_time(run, Stage::Parse) + answer
+        }),
+        handler: timing(&|run| run.handler),
+        stages: Stage::ALL
+            .iter()
+            .map(|&stage| (stage, timing(&|run| stage_time(run, stage))))
+            .collect(),
+        other: timing(&|run| {
+            let staged: Duration = Stage::ALL
+                .iter()
+                .filter(|stage| stage.in_handler())
+                .map(|&stage| stage_time(run, stage))
+                .sum();
+            run.handler.saturating_sub(staged)
+        }),
+    }
+}
+
+/// A payload like the one Cursor sends for a hook
+pub fn synthetic_payload(event: &str) -> String {
+    let mut payload = json!({
+        "hook_event_name": event,
+        "conversation_id": CONVERSATION_ID,
+        "generation_id": "g1",
+        "model": "bench-model",
+        "workspace_roots": ["/tmp/recursor-bench"],
+    });
+    let extra = match event {
+        "beforeSubmitPrompt" => json!({ "prompt": "Fix the failing tests in the parser" }),
+        "beforeShellExecution" => json!({ "command": "cargo test" }),
+        "afterShellExecution" => json!({
+            "command": "cargo test",
+            "output": "test result: ok. 42 passed; 0 failed",
+            "duration": 1200.0,
+        }),
+        "stop" => json!({ "status": "completed", "loop_count": 0 }),
+        _ => json!({}),
+    };
+    if let (Some(payload), Some(extra)) = (payload.as_object_mut(), extra.as_object()) {
+        payload.extend(extra.clone());
+    }
+    payload.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::null::NullWindowManager;
+    use std::fs;
+
+    #[test]
+    fn every_hook_is_timed_and_broken_down_into_stages() {
+        let dir = std::env::temp_dir().join(format!("recursor_bench_{}", std::process::id()));
+        let results = run(Box::new(NullWindowManager), &Config::default(), 5, &dir).expect("bench");
+
+        let events: Vec<&str> = results.iter().map(|r| r.event.as_str()).collect();
+        assert_eq!(events, HOOK_EVENTS);
+        for result in &results {
+            assert_eq!(result.runs, 5);
+            assert!(result.handler.p50_ms <= result.handler.p95_ms);
+            assert!(result.handler.p95_ms <= result.handler.max_ms);
+            assert_eq!(result.stages.len(), Stage::ALL.len());
+        }
+        // Every hook reads the state, and the submit saves it
+        assert!(results
+            .iter()
+            .all(|r| r.stages[&Stage::StateLoad].max_ms > 0.0));
+        assert!(results[0].stages[&Stage::StateSave].max_ms > 0.0);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,199 @@
+//! Capture of hook invocations for debugging
+//!
+//! When `capture.enabled` is set in the config, every hook invocation appends
+//! what Cursor sent, how it parsed, Recursor's response and the window manager
+//! calls it made to `~/.cursor/recursor_captures/<conversation_id>.jsonl`.
+//! Only the most recently written `capture.max_sessions` files are kept.
+//! `recursor replay <file>` feeds a capture back through the handlers.
+
+use crate::platform::recording::RecordedCall;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One captured invocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureEntry {
+    /// When the invocation finished
+    pub at: DateTime<Utc>,
+    /// Subcommand line (e.g. "hook", "save --no-focus", "check-idle")
+    pub command: String,
+    /// Raw stdin payload (the conversation ID for check-idle)
+    pub raw: String,
+    /// Hook event the payload was handled as
+    #[serde(default)]
+    pub event: Option<String>,
+    /// Parsed input, as Recursor understood it
+    #[serde(default)]
+    pub parsed: Option<serde_json::Value>,
+    /// Why the payload failed to parse, if it did
+    #[serde(default)]
+    pub parse_error: Option<String>,
+    /// JSON written back to Cursor
+    #[serde(default)]
+    pub response: Option<serde_json::Value>,
+    /// Error returned by the handler
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Window manager calls made while handling the invocation
+    #[serde(default)]
+    pub calls: Vec<RecordedCall>,
+}
+
+/// Rotating directory of capture files, one per conversation
+pub struct CaptureDir {
+    dir: PathBuf,
+    max_sessions: usize,
+}
+
+impl CaptureDir {
+    /// Open the capture directory at the default location
+    pub fn new(max_sessions: usize) -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self {
+            dir: home.join(".cursor").join("recursor_captures"),
+            max_sessions,
+        })
+    }
+
+    #[cfg(test)]
+    fn with_dir(dir: PathBuf, max_sessions: usize) -> Self {
+        Self { dir, max_sessions }
+    }
+
+    /// Append an entry to a conversation's capture file, then drop the oldest files
+    pub fn append(&self, conversation_id: &str, entry: &CaptureEntry) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir).context("Failed to create capture directory")?;
+        let path = self
+            .dir
+            .join(format!("{}.jsonl", file_stem(conversation_id)));
+
+        let line = serde_json::to_string(entry).context("Failed to serialize capture entry")?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context("Failed to open capture file")?;
+        writeln!(file, "{}", line).context("Failed to write capture entry")?;
+
+        self.rotate()?;
+        Ok(path)
+    }
+
+    /// Remove all but the `max_sessions` most recently modified capture files
+    fn rotate(&self) -> Result<()> {
+        let mut files: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(&self.dir)
+            .context("Failed to read capture directory")?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((modified, path))
+            })
+            .collect();
+
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in files.into_iter().skip(self.max_sessions.max(1)) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(())
+    }
+}
+
+/// Read a capture file, skipping lines that fail to parse
+pub fn read_session(path: &Path) -> Result<Vec<CaptureEntry>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read capture file {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Make a conversation ID safe to use as a file name
+fn file_stem(conversation_id: &str) -> String {
+    conversation_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::unique_test_dir;
+
+    fn entry(command: &str) -> CaptureEntry {
+        CaptureEntry {
+            at: Utc::now(),
+            command: command.to_string(),
+            raw: "{not json".to_string(),
+            event: Some("beforeSubmitPrompt".to_string()),
+            parsed: None,
+            parse_error: Some("Failed to parse JSON input".to_string()),
+            response: Some(serde_json::json!({"continue": true})),
+            error: None,
+            calls: vec![],
+        }
+    }
+
+    #[test]
+    fn entries_round_trip_per_conversation() {
+        let dir = unique_test_dir("capture_round_trip");
+        let captures = CaptureDir::with_dir(dir.clone(), 10);
+
+        let path = captures.append("conv/1", &entry("save")).expect("append");
+        captures
+            .append("conv/1", &entry("restore"))
+            .expect("append");
+        assert_eq!(
+            path.file_name().and_then(|n| n.to_str()),
+            Some("conv_1.jsonl")
+        );
+
+        let entries = read_session(&path).expect("read");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "save");
+        assert_eq!(entries[1].command, "restore");
+        assert_eq!(
+            entries[0].parse_error.as_deref(),
+            Some("Failed to parse JSON input")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn oldest_sessions_are_rotated_out() {
+        let dir = unique_test_dir("capture_rotate");
+        let captures = CaptureDir::with_dir(dir.clone(), 2);
+
+        for conversation in ["a", "b", "c"] {
+            captures
+                .append(conversation, &entry("hook"))
+                .expect("append");
+            // Make modification times distinct
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        let mut remaining: Vec<String> = fs::read_dir(&dir)
+            .expect("read dir")
+            .filter_map(Result::ok)
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["b.jsonl", "c.jsonl"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
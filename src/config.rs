@@ -0,0 +1,898 @@
+//! User configuration for Recursor
+//!
+//! Reads `~/.cursor/recursor_config.json`. The menu bar app toggles the
+//! `enabled` flag in the same file; every other setting is optional and falls
+//! back to its default, so a missing or partial file behaves like the defaults.
+
+use crate::actions::Trigger;
+use crate::hooks::StopStatus;
+use crate::logging::Level;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What to do when the agent stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopAction {
+    /// Bring the user back to Cursor
+    Pull,
+    /// Bring the user back to Cursor and show a desktop notification
+    PullAndNotify,
+    /// Leave focus alone but show a desktop notification
+    Notify,
+    /// Leave focus alone
+    None,
+}
+
+impl StopAction {
+    /// The action name as written in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pull => "pull",
+            Self::PullAndNotify => "pull_and_notify",
+            Self::Notify => "notify",
+            Self::None => "none",
+        }
+    }
+
+    /// Whether this action moves focus to Cursor
+    pub fn pulls(self) -> bool {
+        matches!(self, Self::Pull | Self::PullAndNotify)
+    }
+
+    /// Whether this action shows a notification
+    pub fn notifies(self) -> bool {
+        matches!(self, Self::PullAndNotify | Self::Notify)
+    }
+}
+
+/// Per-status behavior for the stop hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StopBehavior {
+    /// When the agent finished its work
+    pub completed: StopAction,
+    /// When the user stopped the agent
+    pub aborted: StopAction,
+    /// When the agent stopped because of an error
+    pub error: StopAction,
+}
+
+impl Default for StopBehavior {
+    fn default() -> Self {
+        Self {
+            completed: StopAction::Pull,
+            // An aborted run was cancelled by the user, who is already in Cursor
+            aborted: StopAction::None,
+            error: StopAction::PullAndNotify,
+        }
+    }
+}
+
+impl StopBehavior {
+    /// Get the configured action for a stop status
+    pub fn action_for(&self, status: StopStatus) -> StopAction {
+        match status {
+            StopStatus::Completed => self.completed,
+            StopStatus::Aborted => self.aborted,
+            StopStatus::Error => self.error,
+        }
+    }
+}
+
+/// Follow-up prompt override for a single workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceFollowup {
+    /// Prompt to send instead of the global one
+    pub message: Option<String>,
+    /// Retry budget instead of the global one
+    pub max_retries: Option<u32>,
+}
+
+/// Opt-in policy for automatically retrying after an agent error
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorFollowup {
+    /// Whether to send a follow-up prompt when the agent stops with an error
+    pub enabled: bool,
+    /// How many follow-ups to send before pulling the user back
+    pub max_retries: u32,
+    /// Prompt sent to the agent
+    pub message: String,
+    /// Per-workspace overrides, keyed by workspace root path
+    pub workspaces: HashMap<String, WorkspaceFollowup>,
+}
+
+impl Default for ErrorFollowup {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_retries: 1,
+            message:
+                "The previous attempt stopped with an error. Please investigate and try again."
+                    .to_string(),
+            workspaces: HashMap::new(),
+        }
+    }
+}
+
+impl ErrorFollowup {
+    /// Get the follow-up prompt to send for a stop, if the policy allows another retry.
+    /// `loop_count` is the number of follow-ups already sent in this run.
+    pub fn followup_for(
+        &self,
+        status: StopStatus,
+        loop_count: u32,
+        workspace_roots: &[String],
+    ) -> Option<String> {
+        if !self.enabled || status != StopStatus::Error {
+            return None;
+        }
+
+        let workspace = self.workspace_override(workspace_roots);
+        let max_retries = workspace
+            .and_then(|w| w.max_retries)
+            .unwrap_or(self.max_retries);
+        if loop_count >= max_retries {
+            return None;
+        }
+
+        Some(
+            workspace
+                .and_then(|w| w.message.clone())
+                .unwrap_or_else(|| self.message.clone()),
+        )
+    }
+
+    /// Find the override for the first workspace root that is (or is inside) a configured path
+    fn workspace_override(&self, workspace_roots: &[String]) -> Option<&WorkspaceFollowup> {
+        workspace_roots.iter().find_map(|root| {
+            self.workspaces
+                .iter()
+                .filter(|(path, _)| Path::new(root).starts_with(path))
+                // Prefer the most specific configured path
+                .max_by_key(|(path, _)| path.len())
+                .map(|(_, followup)| followup)
+        })
+    }
+}
+
+/// Capture of hook payloads for debugging (see `recursor replay`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// Whether to write every hook invocation to `~/.cursor/recursor_captures`
+    pub enabled: bool,
+    /// How many conversation capture files to keep
+    pub max_sessions: usize,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_sessions: 20,
+        }
+    }
+}
+
+/// A user command run when an agent reaches some point in its run.
+/// Written either as a plain command string or as
+/// `{"command": "...", "timeout_seconds": 10}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ActionSpec")]
+pub struct Action {
+    /// Shell command to run
+    pub command: String,
+    /// Timeout for this command instead of the global one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// The accepted spellings of an `Action`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ActionSpec {
+    Command(String),
+    Full {
+        command: String,
+        #[serde(default)]
+        timeout_seconds: Option<u64>,
+    },
+}
+
+impl From<ActionSpec> for Action {
+    fn from(spec: ActionSpec) -> Self {
+        match spec {
+            ActionSpec::Command(command) => Self {
+                command,
+                timeout_seconds: None,
+            },
+            ActionSpec::Full {
+                command,
+                timeout_seconds,
+            } => Self {
+                command,
+                timeout_seconds,
+            },
+        }
+    }
+}
+
+/// User commands to run on lifecycle transitions (see `actions`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionsConfig {
+    /// When a prompt is submitted
+    pub on_submit: Vec<Action>,
+    /// When a tool call has been pending long enough to need approval
+    pub on_approval_needed: Vec<Action>,
+    /// When a shell command or MCP tool call finishes
+    pub on_command_done: Vec<Action>,
+    /// When the agent stops after completing or being aborted
+    pub on_finished: Vec<Action>,
+    /// When the agent stops with an error
+    pub on_error: Vec<Action>,
+    /// Seconds a command may run before it is killed
+    pub timeout_seconds: u64,
+    /// How many commands may run at once, across all hooks
+    pub max_concurrent: usize,
+}
+
+impl Default for ActionsConfig {
+    fn default() -> Self {
+        Self {
+            on_submit: Vec::new(),
+            on_approval_needed: Vec::new(),
+            on_command_done: Vec::new(),
+            on_finished: Vec::new(),
+            on_error: Vec::new(),
+            timeout_seconds: 30,
+            max_concurrent: 4,
+        }
+    }
+}
+
+impl ActionsConfig {
+    /// Get the commands configured for a trigger
+    pub fn for_trigger(&self, trigger: Trigger) -> &[Action] {
+        match trigger {
+            Trigger::OnSubmit => &self.on_submit,
+            Trigger::OnApprovalNeeded => &self.on_approval_needed,
+            Trigger::OnCommandDone => &self.on_command_done,
+            Trigger::OnFinished => &self.on_finished,
+            Trigger::OnError => &self.on_error,
+        }
+    }
+
+    /// How long an action may run
+    pub fn timeout_for(&self, action: &Action) -> Duration {
+        Duration::from_secs(action.timeout_seconds.unwrap_or(self.timeout_seconds))
+    }
+}
+
+/// What a per-app rule does to matching windows (see `rules`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Never bounce back to the window
+    NeverReturn,
+    /// Bounce back to the app whenever one of its windows is open
+    Prefer,
+    /// Never pull the user out of the window
+    DoNotInterrupt,
+}
+
+impl RuleAction {
+    /// The action name as written in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NeverReturn => "never_return",
+            Self::Prefer => "prefer",
+            Self::DoNotInterrupt => "do_not_interrupt",
+        }
+    }
+}
+
+/// Which windows a rule applies to; every condition given must hold
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowMatch {
+    /// App name, compared case-insensitively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    /// Window class (WM_CLASS on X11), compared case-insensitively
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Case-insensitive regex searched for in the window title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A per-app rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppRule {
+    /// Windows the rule applies to
+    #[serde(rename = "match")]
+    pub window: WindowMatch,
+    /// What to do with them
+    pub action: RuleAction,
+}
+
+/// What to do instead of pulling the user to Cursor while they must not be disturbed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DndMode {
+    /// Send a notification and leave focus alone
+    #[default]
+    Notify,
+    /// Wait, and pull the user once the condition ends
+    Defer,
+}
+
+impl DndMode {
+    /// The mode name as written in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Notify => "notify",
+            Self::Defer => "defer",
+        }
+    }
+}
+
+/// Do-not-disturb detection (see `dnd`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DoNotDisturbConfig {
+    /// Whether the built-in checks run; `do_not_interrupt` rules always apply
+    pub enabled: bool,
+    /// Don't pull the user out of a fullscreen window
+    pub fullscreen: bool,
+    /// Don't pull the user to Cursor while the screen is locked
+    pub screen_locked: bool,
+    /// Don't pull the user out of known call and presentation apps
+    pub call_apps: bool,
+    /// What to do instead of pulling
+    pub mode: DndMode,
+    /// Seconds between checks while a pull is deferred
+    pub poll_seconds: u64,
+}
+
+impl Default for DoNotDisturbConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fullscreen: true,
+            screen_locked: true,
+            call_apps: true,
+            mode: DndMode::default(),
+            poll_seconds: 10,
+        }
+    }
+}
+
+/// Waiting for the user to stop typing before pulling them to Cursor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypingConfig {
+    /// How long the user must have been idle; 0 pulls without waiting
+    pub idle_ms: u64,
+    /// The longest a pull waits for the user to stop typing
+    pub max_wait_ms: u64,
+}
+
+impl Default for TypingConfig {
+    fn default() -> Self {
+        Self {
+            idle_ms: 750,
+            max_wait_ms: 3000,
+        }
+    }
+}
+
+/// Bouncing back to the previous window after a prompt is submitted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BounceConfig {
+    /// Stay in Cursor until the agent has been working this long, so quick runs
+    /// don't bounce away and straight back; 0 bounces immediately
+    pub delay_seconds: u64,
+    /// Predict the run time from the history and stay in Cursor for short runs
+    pub predict: bool,
+    /// Runs predicted to finish within this many seconds don't bounce
+    pub short_run_seconds: u64,
+    /// Similar past runs needed before a prediction is trusted
+    pub min_samples: usize,
+}
+
+impl Default for BounceConfig {
+    fn default() -> Self {
+        Self {
+            delay_seconds: 0,
+            predict: false,
+            short_run_seconds: 10,
+            min_samples: 5,
+        }
+    }
+}
+
+/// Coordinating pulls across conversations (see `queue`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PullQueueConfig {
+    /// Hold pulls for other conversations while the user handles one
+    pub enabled: bool,
+    /// Seconds between checks for whether the user is done with a pull
+    pub poll_seconds: u64,
+}
+
+impl Default for PullQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_seconds: 3,
+        }
+    }
+}
+
+/// Choosing the approval failsafe delay per command (see `failsafe`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailsafeConfig {
+    /// Shortest delay, used for commands that usually wait for approval
+    pub min_seconds: u64,
+    /// Longest delay, however long a command usually runs
+    pub max_seconds: u64,
+    /// Past calls of a command needed before its delay is learned
+    pub min_samples: usize,
+}
+
+impl Default for FailsafeConfig {
+    fn default() -> Self {
+        Self {
+            min_seconds: 2,
+            max_seconds: 60,
+            min_samples: 3,
+        }
+    }
+}
+
+/// Audit log of agent shell commands (see `audit`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Whether to record shell commands to `~/.cursor/recursor_audit.jsonl`
+    pub enabled: bool,
+    /// Longest command output kept per entry, in characters
+    pub max_output_chars: usize,
+    /// Size at which the log is rotated, in bytes
+    pub max_file_bytes: u64,
+    /// How many rotated files to keep
+    pub keep_files: usize,
+    /// Extra regexes for secrets to redact, on top of the built-in ones
+    pub redact: Vec<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_output_chars: 2000,
+            max_file_bytes: 5_000_000,
+            keep_files: 5,
+            redact: Vec::new(),
+        }
+    }
+}
+
+/// The structured debug log (see `logging`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Most detailed level written: `error`, `warn`, `info` or `debug`
+    pub level: Level,
+    /// Size at which the log is rotated, in bytes
+    pub max_file_bytes: u64,
+    /// How many rotated files to keep
+    pub keep_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::Info,
+            max_file_bytes: 2_000_000,
+            keep_files: 3,
+        }
+    }
+}
+
+/// Answering hooks before acting on them (see `worker`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FastPathConfig {
+    /// Answer Cursor straight away and leave window and media work to a worker
+    pub enabled: bool,
+    /// Milliseconds a hook may take to answer before `recursor doctor` flags it
+    pub budget_ms: u64,
+    /// Budgets for specific hooks by event name, e.g. `{"stop": 100}`
+    pub event_budgets_ms: HashMap<String, u64>,
+}
+
+impl FastPathConfig {
+    /// The latency budget for a hook event
+    pub fn budget_for(&self, event: &str) -> u64 {
+        self.event_budgets_ms
+            .get(event)
+            .copied()
+            .unwrap_or(self.budget_ms)
+    }
+}
+
+impl Default for FastPathConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget_ms: 50,
+            event_budgets_ms: HashMap::new(),
+        }
+    }
+}
+
+/// Away-from-keyboard detection (see `away`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AwayConfig {
+    /// Seconds without input after which the user counts as away; 0 never does
+    pub after_seconds: u64,
+    /// Seconds between checks for the user's return
+    pub poll_seconds: u64,
+}
+
+impl Default for AwayConfig {
+    fn default() -> Self {
+        Self {
+            after_seconds: 300,
+            poll_seconds: 5,
+        }
+    }
+}
+
+/// Window manager backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// The real window manager for this platform
+    #[default]
+    Platform,
+    /// Does nothing: no windows, every action succeeds
+    Null,
+    /// Simulated windows from a JSON script file
+    Scripted,
+    /// The platform backend (or the scripted one, if a script is set) with
+    /// every call appended to a log file
+    Recording,
+}
+
+impl BackendKind {
+    fn parse(name: &str) -> Result<Self> {
+        match name {
+            "platform" => Ok(Self::Platform),
+            "null" => Ok(Self::Null),
+            "scripted" => Ok(Self::Scripted),
+            "recording" => Ok(Self::Recording),
+            other => Err(anyhow!(
+                "Unknown backend \"{}\" (expected platform, null, scripted or recording)",
+                other
+            )),
+        }
+    }
+}
+
+/// Window manager backend selection.
+/// `RECURSOR_BACKEND`, `RECURSOR_BACKEND_SCRIPT` and `RECURSOR_BACKEND_LOG`
+/// override the corresponding fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    /// Which backend to use
+    pub kind: BackendKind,
+    /// Script file for the scripted backend
+    pub script: Option<PathBuf>,
+    /// Log file for the recording backend (default `~/.cursor/recursor_calls.jsonl`)
+    pub log: Option<PathBuf>,
+}
+
+impl BackendConfig {
+    /// Apply the environment variable overrides
+    pub fn resolve(&self) -> Result<Self> {
+        let mut resolved = self.clone();
+        if let Ok(kind) = std::env::var("RECURSOR_BACKEND") {
+            resolved.kind = BackendKind::parse(kind.trim())?;
+        }
+        if let Some(script) = std::env::var_os("RECURSOR_BACKEND_SCRIPT") {
+            resolved.script = Some(PathBuf::from(script));
+        }
+        if let Some(log) = std::env::var_os("RECURSOR_BACKEND_LOG") {
+            resolved.log = Some(PathBuf::from(log));
+        }
+        Ok(resolved)
+    }
+
+    /// Whether this backend acts on real windows (and so needs failsafe timers)
+    pub fn drives_real_windows(&self) -> bool {
+        match self.kind {
+            BackendKind::Platform => true,
+            BackendKind::Recording => self.script.is_none(),
+            BackendKind::Null | BackendKind::Scripted => false,
+        }
+    }
+}
+
+/// Recursor configuration file format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Whether Recursor manages focus at all (toggled from the menu bar)
+    pub enabled: bool,
+    /// Decide and log as usual but never move focus or touch media playback
+    pub dry_run: bool,
+    /// What to do when the agent stops, per stop status
+    pub on_stop: StopBehavior,
+    /// Automatic retry after agent errors
+    pub error_followup: ErrorFollowup,
+    /// Hook payload capture for debugging
+    pub capture: CaptureConfig,
+    /// Window manager backend
+    pub backend: BackendConfig,
+    /// User commands run on lifecycle transitions
+    pub actions: ActionsConfig,
+    /// Per-app rules, evaluated in order
+    pub rules: Vec<AppRule>,
+    /// When not to pull the user to Cursor
+    pub do_not_disturb: DoNotDisturbConfig,
+    /// Waiting for typing to stop before a pull
+    pub typing: TypingConfig,
+    /// Holding focus changes while the user is away
+    pub away: AwayConfig,
+    /// When to bounce back after a prompt is submitted
+    pub bounce: BounceConfig,
+    /// How long a tool call may be pending before the user is pulled to Cursor
+    pub failsafe: FailsafeConfig,
+    /// Taking turns when several conversations need the user
+    pub pull_queue: PullQueueConfig,
+    /// Recording the commands agents run
+    pub audit: AuditConfig,
+    /// What goes into the debug log
+    pub logging: LoggingConfig,
+    /// Answering hooks before doing the window work
+    pub fast_path: FastPathConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dry_run: false,
+            on_stop: StopBehavior::default(),
+            error_followup: ErrorFollowup::default(),
+            capture: CaptureConfig::default(),
+            backend: BackendConfig::default(),
+            actions: ActionsConfig::default(),
+            rules: Vec::new(),
+            do_not_disturb: DoNotDisturbConfig::default(),
+            typing: TypingConfig::default(),
+            away: AwayConfig::default(),
+            bounce: BounceConfig::default(),
+            failsafe: FailsafeConfig::default(),
+            pull_queue: PullQueueConfig::default(),
+            audit: AuditConfig::default(),
+            logging: LoggingConfig::default(),
+            fast_path: FastPathConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults if it is missing; see `parse`
+    pub fn load() -> Self {
+        Self::load_with_errors().0
+    }
+
+    /// Load the config file, also returning the problems found in it
+    pub fn load_with_errors() -> (Self, Vec<String>) {
+        let path = config_path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(e) => (
+                Self::default(),
+                vec![format!("can't read {}: {}", path.display(), e)],
+            ),
+        }
+    }
+
+    /// Parse a config file. A section with an error (an unknown value, a wrong
+    /// type) falls back to its defaults on its own and is reported, so one typo
+    /// doesn't reset everything else. `enabled` and `dry_run` are always taken
+    /// from the file when they are booleans, so a broken section never turns
+    /// Recursor back on or a dry run off. A file that isn't JSON at all is
+    /// ignored entirely, as before.
+    pub fn parse(contents: &str) -> (Self, Vec<String>) {
+        let raw: serde_json::Value = match serde_json::from_str(contents) {
+            Ok(raw) => raw,
+            Err(e) => return (Self::default(), vec![format!("not valid JSON: {}", e)]),
+        };
+        let Some(sections) = raw.as_object() else {
+            return (Self::default(), vec!["not a JSON object".to_string()]);
+        };
+
+        let mut errors = Vec::new();
+        let mut valid = serde_json::Map::new();
+        for (key, value) in sections {
+            let section =
+                serde_json::Value::Object([(key.clone(), value.clone())].into_iter().collect());
+            match serde_json::from_value::<Self>(section) {
+                Ok(_) => {
+                    valid.insert(key.clone(), value.clone());
+                }
+                Err(e) => errors.push(format!("{}: {}", key, e)),
+            }
+        }
+        let mut config =
+            serde_json::from_value(serde_json::Value::Object(valid)).unwrap_or_else(|e| {
+                errors.push(e.to_string());
+                Self::default()
+            });
+        if let Some(enabled) = raw.get("enabled").and_then(|v| v.as_bool()) {
+            config.enabled = enabled;
+        }
+        if let Some(dry_run) = raw.get("dry_run").and_then(|v| v.as_bool()) {
+            config.dry_run = dry_run;
+        }
+        (config, errors)
+    }
+}
+
+/// Get the path to the config file
+pub fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".cursor")
+        .join("recursor_config.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_bar_config_uses_defaults_for_everything_else() {
+        let config: Config = serde_json::from_str(r#"{"enabled": false}"#).expect("parse");
+        assert!(!config.enabled);
+        assert_eq!(config.on_stop, StopBehavior::default());
+    }
+
+    #[test]
+    fn invalid_sections_fall_back_on_their_own() {
+        let (config, errors) = Config::parse(
+            r#"{
+                "enabled": false,
+                "dry_run": true,
+                "on_stop": {"completed": "teleport"},
+                "bounce": {"delay_seconds": 5},
+                "failsafe": "soon"
+            }"#,
+        );
+        assert!(!config.enabled);
+        assert!(config.dry_run);
+        assert_eq!(config.on_stop, StopBehavior::default());
+        assert_eq!(config.bounce.delay_seconds, 5);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("on_stop: unknown variant `teleport`"));
+        assert!(errors[1].starts_with("failsafe: "));
+
+        let (config, errors) = Config::parse("{\"enabled\": false,");
+        assert!(config.enabled);
+        assert!(errors[0].starts_with("not valid JSON"));
+    }
+
+    #[test]
+    fn partial_stop_behavior_keeps_other_defaults() {
+        let config: Config =
+            serde_json::from_str(r#"{"on_stop": {"aborted": "pull"}}"#).expect("parse");
+        assert!(config.enabled);
+        assert_eq!(config.on_stop.aborted, StopAction::Pull);
+        assert_eq!(config.on_stop.error, StopAction::PullAndNotify);
+    }
+
+    #[test]
+    fn default_stop_actions() {
+        let behavior = StopBehavior::default();
+        assert!(behavior.action_for(StopStatus::Completed).pulls());
+        assert!(!behavior.action_for(StopStatus::Completed).notifies());
+        assert!(!behavior.action_for(StopStatus::Aborted).pulls());
+        assert!(behavior.action_for(StopStatus::Error).pulls());
+        assert!(behavior.action_for(StopStatus::Error).notifies());
+    }
+
+    #[test]
+    fn error_followup_is_opt_in() {
+        let followup = ErrorFollowup::default();
+        assert_eq!(followup.followup_for(StopStatus::Error, 0, &[]), None);
+    }
+
+    #[test]
+    fn error_followup_respects_retry_budget_and_status() {
+        let config: Config = serde_json::from_str(
+            r#"{"error_followup": {"enabled": true, "max_retries": 2, "message": "retry"}}"#,
+        )
+        .expect("parse");
+        let followup = &config.error_followup;
+
+        assert_eq!(
+            followup.followup_for(StopStatus::Error, 0, &[]),
+            Some("retry".to_string())
+        );
+        assert_eq!(
+            followup.followup_for(StopStatus::Error, 1, &[]),
+            Some("retry".to_string())
+        );
+        assert_eq!(followup.followup_for(StopStatus::Error, 2, &[]), None);
+        assert_eq!(followup.followup_for(StopStatus::Completed, 0, &[]), None);
+        assert_eq!(followup.followup_for(StopStatus::Aborted, 0, &[]), None);
+    }
+
+    #[test]
+    fn error_followup_uses_most_specific_workspace_override() {
+        let config: Config = serde_json::from_str(
+            r#"{"error_followup": {
+                "enabled": true,
+                "message": "global",
+                "workspaces": {
+                    "/work": {"message": "work"},
+                    "/work/api": {"message": "api", "max_retries": 0}
+                }
+            }}"#,
+        )
+        .expect("parse");
+        let followup = &config.error_followup;
+
+        let web = vec!["/work/web".to_string()];
+        assert_eq!(
+            followup.followup_for(StopStatus::Error, 0, &web),
+            Some("work".to_string())
+        );
+
+        let api = vec!["/work/api".to_string()];
+        assert_eq!(followup.followup_for(StopStatus::Error, 0, &api), None);
+
+        let other = vec!["/elsewhere".to_string()];
+        assert_eq!(
+            followup.followup_for(StopStatus::Error, 0, &other),
+            Some("global".to_string())
+        );
+    }
+
+    #[test]
+    fn actions_accept_plain_commands_and_timeouts() {
+        let config: Config = serde_json::from_str(
+            r#"{"actions": {
+                "on_finished": ["cargo test", {"command": "notify.sh", "timeout_seconds": 5}]
+            }}"#,
+        )
+        .expect("parse");
+        let actions = &config.actions;
+
+        let finished = actions.for_trigger(Trigger::OnFinished);
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0].command, "cargo test");
+        assert_eq!(actions.timeout_for(&finished[0]), Duration::from_secs(30));
+        assert_eq!(actions.timeout_for(&finished[1]), Duration::from_secs(5));
+        assert!(actions.for_trigger(Trigger::OnSubmit).is_empty());
+    }
+}
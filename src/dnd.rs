@@ -0,0 +1,174 @@
+//! Do-not-disturb detection
+//!
+//! Before pulling the user to Cursor (or moving focus at all), the engine asks
+//! whether they must not be disturbed right now:
+//!
+//! - the screen is locked
+//! - the active window matches a `do_not_interrupt` rule
+//! - the active window is a known call or presentation app
+//! - the active window is fullscreen (a screen share, slides, a video), unless
+//!   it is Cursor itself
+//!
+//! Depending on `do_not_disturb.mode`, the pull is then downgraded to a
+//! notification or deferred until the condition ends.
+
+use crate::config::{AppRule, DoNotDisturbConfig, RuleAction, WindowMatch};
+use crate::platform::recording::describe_window;
+use crate::platform::WindowManager;
+use crate::rules::RuleSet;
+
+/// Title patterns of call and presentation windows, matched case-insensitively
+const CALL_WINDOW_TITLES: &[&str] = &[
+    r"^zoom (meeting|webinar)",
+    r"^meet - |google meet",
+    r"(meeting|call) .*\| microsoft teams",
+    r"^webex meeting",
+    r"huddle",
+    r"^powerpoint slide show|presenter view",
+];
+
+/// Apps whose every window is a call
+const CALL_APPS: &[&str] = &["FaceTime"];
+
+/// Built-in `do_not_interrupt` rules for known call and presentation apps
+pub fn builtin_rules() -> Vec<AppRule> {
+    let titles = CALL_WINDOW_TITLES.iter().map(|title| WindowMatch {
+        title: Some(title.to_string()),
+        ..WindowMatch::default()
+    });
+    let apps = CALL_APPS.iter().map(|app| WindowMatch {
+        app: Some(app.to_string()),
+        ..WindowMatch::default()
+    });
+    titles
+        .chain(apps)
+        .map(|window| AppRule {
+            window,
+            action: RuleAction::DoNotInterrupt,
+        })
+        .collect()
+}
+
+/// Decides whether the user may be disturbed
+pub struct DoNotDisturb {
+    config: DoNotDisturbConfig,
+    call_apps: RuleSet,
+}
+
+impl DoNotDisturb {
+    /// Create a detector for the given settings
+    pub fn new(config: &DoNotDisturbConfig) -> Self {
+        Self {
+            config: config.clone(),
+            call_apps: RuleSet::builtin(&builtin_rules()),
+        }
+    }
+
+    /// The settings this detector was created with
+    pub fn config(&self) -> &DoNotDisturbConfig {
+        &self.config
+    }
+
+    /// Whether the user must not be disturbed, with the reason added to `why`.
+    /// Only asks the window manager what the enabled checks need.
+    pub fn active(&self, wm: &dyn WindowManager, rules: &RuleSet, why: &mut Vec<String>) -> bool {
+        let builtin = self.config.enabled;
+        if builtin && self.config.screen_locked && wm.is_screen_locked() {
+            why.push("the screen is locked".to_string());
+            return true;
+        }
+
+        let needs_window = rules.has(RuleAction::DoNotInterrupt)
+            || (builtin && (self.config.call_apps || self.config.fullscreen));
+        if !needs_window {
+            return false;
+        }
+        let Ok(active) = wm.get_active_window() else {
+            return false;
+        };
+
+        if rules.protects(&active, why) {
+            return true;
+        }
+        if builtin && self.config.call_apps && self.call_apps.protects(&active, why) {
+            return true;
+        }
+        if builtin && self.config.fullscreen && !active.is_cursor() && wm.is_fullscreen(&active) {
+            why.push(format!("{} is fullscreen", describe_window(&active)));
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::scripted::{Script, ScriptedWindowManager};
+    use crate::platform::WindowInfo;
+
+    fn window(id: &str, app_name: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            pid: 1,
+            window_id: id.to_string(),
+            app_name: app_name.to_string(),
+            title: title.to_string(),
+            class: None,
+        }
+    }
+
+    fn check(script: Script, config: &DoNotDisturbConfig) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!("recursor_dnd_{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&script).expect("serialize")).expect("write");
+        let wm = ScriptedWindowManager::load(&path).expect("load");
+        let _ = std::fs::remove_file(&path);
+
+        let mut why = Vec::new();
+        let active = DoNotDisturb::new(config).active(&wm, &RuleSet::new(&[]), &mut why);
+        assert_eq!(active, !why.is_empty());
+        why
+    }
+
+    #[test]
+    fn detects_calls_fullscreen_windows_and_locked_screens() {
+        let config = DoNotDisturbConfig::default();
+        let script = |active: WindowInfo| Script {
+            focus: vec![active.window_id.clone()],
+            windows: vec![active],
+            ..Script::default()
+        };
+
+        let meet = window("meet", "Google Chrome", "Meet - abc-defg-hij");
+        assert_eq!(
+            check(script(meet), &config),
+            vec!["built-in rule (do_not_interrupt title=/^meet - |google meet/) kept you in Google Chrome \"Meet - abc-defg-hij\" (pid 1)"]
+        );
+
+        let video = window("vlc", "vlc", "movie.mkv");
+        let mut fullscreen = script(video.clone());
+        fullscreen.fullscreen = vec!["vlc".to_string()];
+        assert_eq!(
+            check(fullscreen.clone(), &config),
+            vec!["vlc \"movie.mkv\" (pid 1) is fullscreen"]
+        );
+        assert!(check(script(video), &config).is_empty());
+
+        // A fullscreen Cursor window is no reason not to go to Cursor
+        let cursor = window("cursor", "Cursor", "main.rs - app - Cursor");
+        let mut fullscreen_cursor = script(cursor);
+        fullscreen_cursor.fullscreen = vec!["cursor".to_string()];
+        assert!(check(fullscreen_cursor, &config).is_empty());
+
+        let locked = Script {
+            screen_locked: true,
+            ..Script::default()
+        };
+        assert_eq!(check(locked, &config), vec!["the screen is locked"]);
+
+        let disabled = DoNotDisturbConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(check(fullscreen, &disabled).is_empty());
+    }
+}
@@ -0,0 +1,502 @@
+//! End-to-end diagnostics for `recursor doctor`
+//!
+//! Each check reports pass/warn/fail with a short detail and, when something
+//! is wrong, a hint on how to fix it. Platform-specific checks (X11, Wayland,
+//! helper tools, Cursor window discovery, media control) come from the window
+//! manager's `diagnostics()`; everything else is checked here.
+
+use crate::config::{config_path, BackendConfig, BackendKind, Config, FastPathConfig};
+use crate::install;
+use crate::platform::WindowManager;
+use crate::state::{RecursorState, StateManager};
+use crate::worker::{latency_report, LatencyLog, LatencyReport};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    /// Everything is in order
+    Pass,
+    /// Recursor works, but something is degraded
+    Warn,
+    /// Recursor can't work as intended until this is fixed
+    Fail,
+}
+
+impl CheckStatus {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Pass => "PASS",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+        }
+    }
+}
+
+/// Result of a single diagnostic check
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    /// Short name of what was checked
+    pub name: String,
+    /// Whether the check passed
+    pub status: CheckStatus,
+    /// What was found
+    pub detail: String,
+    /// How to fix a warning or failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Check {
+    /// A passing check
+    pub fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    /// A warning, with a hint on how to fix it
+    pub fn warn(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    /// A failure, with a hint on how to fix it
+    pub fn fail(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Run every check
+pub fn run_checks(wm: &dyn WindowManager, backend: &BackendConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if backend.kind != BackendKind::Platform {
+        checks.push(Check::warn(
+            "backend",
+            format!(
+                "using the {:?} backend instead of the platform one",
+                backend.kind
+            )
+            .to_lowercase(),
+            "Unset RECURSOR_BACKEND (or remove \"backend\" from the config) to manage real windows",
+        ));
+    }
+
+    checks.push(match wm.get_active_window() {
+        Ok(window) => Check::pass(
+            "active window",
+            format!("{} ({})", window.app_name, window.title),
+        ),
+        Err(e) => Check::fail(
+            "active window",
+            format!("{:#}", e),
+            "Recursor can't see the focused window; see the platform checks below",
+        ),
+    });
+
+    checks.extend(wm.diagnostics());
+
+    match install::user_hooks_path() {
+        Ok(path) => checks.extend(check_hooks_file(&path, false)),
+        Err(e) => checks.push(Check::fail(
+            "hooks.json",
+            format!("{:#}", e),
+            "Set HOME so ~/.cursor/hooks.json can be found",
+        )),
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        let project_path = install::project_hooks_path(&cwd);
+        if project_path.exists() {
+            checks.extend(check_hooks_file(&project_path, true));
+        }
+    }
+
+    match StateManager::new() {
+        Ok(state) => checks.push(check_json_file::<RecursorState>(
+            "state file",
+            state.state_path(),
+            "no saved state yet",
+        )),
+        Err(e) => checks.push(Check::fail(
+            "state file",
+            format!("{:#}", e),
+            "Make sure ~/.cursor exists and is writable",
+        )),
+    }
+    checks.push(check_config_file(&config_path()));
+
+    let fast_path = Config::load().fast_path;
+    let samples = LatencyLog::new()
+        .and_then(|log| log.samples())
+        .unwrap_or_default();
+    checks.extend(check_latency(
+        &latency_report(&samples, &fast_path),
+        &fast_path,
+    ));
+
+    checks.push(check_allowlist_db());
+    checks
+}
+
+/// Number of failed checks
+pub fn failures(checks: &[Check]) -> usize {
+    checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .count()
+}
+
+/// Human-readable report
+pub fn render_text(checks: &[Check]) -> String {
+    let mut out = String::from("Recursor Doctor\n===============\n\n");
+    for check in checks {
+        out.push_str(&format!(
+            "[{}] {}: {}\n",
+            check.status.label(),
+            check.name,
+            check.detail
+        ));
+        if let Some(hint) = &check.hint {
+            out.push_str(&format!("       -> {}\n", hint));
+        }
+    }
+
+    let warnings = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Warn)
+        .count();
+    out.push_str(&format!(
+        "\n{} passed, {} warnings, {} failed\n",
+        checks.len() - warnings - failures(checks),
+        warnings,
+        failures(checks)
+    ));
+    out
+}
+
+/// Check that a hooks file exists, registers Recursor and points at a real binary
+fn check_hooks_file(path: &Path, project: bool) -> Vec<Check> {
+    let name = if project {
+        "project hooks.json"
+    } else {
+        "hooks.json"
+    };
+    let install_hint = if project {
+        "Run `recursor install-hooks --project` in this directory"
+    } else {
+        "Run `recursor install-hooks`"
+    };
+
+    if !path.exists() {
+        return vec![Check::fail(
+            name,
+            format!("{} not found", path.display()),
+            install_hint,
+        )];
+    }
+    match install::load_hooks_file(path) {
+        Ok(hooks_file) => check_hooks(path, &hooks_file, name, install_hint),
+        Err(e) => vec![Check::fail(
+            name,
+            format!("{:#}", e),
+            format!(
+                "Fix the JSON in {}, then run `recursor install-hooks`",
+                path.display()
+            ),
+        )],
+    }
+}
+
+fn check_hooks(
+    path: &Path,
+    hooks_file: &serde_json::Value,
+    name: &str,
+    install_hint: &str,
+) -> Vec<Check> {
+    let registered = install::registered_commands(hooks_file);
+    let missing: Vec<&str> = install::RECURSOR_EVENTS
+        .iter()
+        .copied()
+        .filter(|event| !registered.iter().any(|(e, _)| e == event))
+        .collect();
+
+    let registration = if registered.is_empty() {
+        Check::fail(
+            name,
+            format!("Recursor is not registered in {}", path.display()),
+            install_hint,
+        )
+    } else if !missing.is_empty() {
+        Check::warn(
+            name,
+            format!("not registered for {}", missing.join(", ")),
+            install_hint,
+        )
+    } else {
+        Check::pass(name, format!("registered in {}", path.display()))
+    };
+    let mut checks = vec![registration];
+
+    let mut binaries: Vec<&str> = registered
+        .iter()
+        .filter_map(|(_, command)| install::split_command(command).map(|(program, _)| program))
+        .collect();
+    binaries.sort_unstable();
+    binaries.dedup();
+    for binary in binaries {
+        checks.push(check_hook_binary(binary, install_hint));
+    }
+    checks
+}
+
+fn check_hook_binary(binary: &str, install_hint: &str) -> Check {
+    let path = if Path::new(binary).components().count() > 1 {
+        Some(PathBuf::from(binary))
+    } else {
+        find_in_path(binary)
+    };
+    let Some(path) = path.filter(|p| p.is_file()) else {
+        return Check::fail(
+            "hook binary",
+            format!("{} does not exist", binary),
+            install_hint,
+        );
+    };
+    if !is_executable(&path) {
+        return Check::fail(
+            "hook binary",
+            format!("{} is not executable", path.display()),
+            format!("Run `chmod +x {}`", path.display()),
+        );
+    }
+    Check::pass("hook binary", path.display().to_string())
+}
+
+/// Check that a JSON file Recursor reads and writes parses and is writable
+fn check_json_file<T: serde::de::DeserializeOwned>(
+    name: &str,
+    path: &Path,
+    missing: &str,
+) -> Check {
+    if !path.exists() {
+        return Check::pass(name, missing);
+    }
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            return Check::fail(
+                name,
+                format!("can't read {}: {}", path.display(), e),
+                format!("Check the permissions of {}", path.display()),
+            )
+        }
+    };
+    if let Err(e) = serde_json::from_str::<T>(&contents) {
+        return Check::warn(
+            name,
+            format!("{} is invalid ({}); it will be ignored", path.display(), e),
+            format!("Fix or delete {}", path.display()),
+        );
+    }
+    let readonly = fs::metadata(path)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(false);
+    if readonly {
+        return Check::warn(
+            name,
+            format!("{} is read-only", path.display()),
+            format!("Make {} writable", path.display()),
+        );
+    }
+    Check::pass(name, path.display().to_string())
+}
+
+/// Check the config file: readable, writable, and every section valid
+fn check_config_file(path: &Path) -> Check {
+    const NAME: &str = "config file";
+    let check = check_json_file::<serde_json::Value>(NAME, path, "not present, using defaults");
+    if check.status != CheckStatus::Pass || !path.exists() {
+        return check;
+    }
+    let (_, errors) = Config::parse(&fs::read_to_string(path).unwrap_or_default());
+    if errors.is_empty() {
+        return check;
+    }
+    Check::warn(
+        NAME,
+        format!(
+            "{} has settings that are ignored: {}",
+            path.display(),
+            errors.join("; ")
+        ),
+        format!(
+            "Fix them in {}; until then those sections use their defaults",
+            path.display()
+        ),
+    )
+}
+
+/// Check how long hooks take to answer against their budgets, one check per event
+fn check_latency(reports: &[LatencyReport], fast_path: &FastPathConfig) -> Vec<Check> {
+    if reports.is_empty() {
+        return vec![Check::pass("hook latency", "no hooks recorded yet")];
+    }
+    reports
+        .iter()
+        .map(|report| {
+            let name = format!("{} latency", report.event);
+            let detail = format!(
+                "p50 {}ms, p90 {}ms over {} hooks (budget {}ms, {} over)",
+                report.p50_ms, report.p90_ms, report.samples, report.budget_ms, report.over_budget
+            );
+            if report.within_budget() {
+                Check::pass(&name, detail)
+            } else if !fast_path.enabled {
+                Check::warn(
+                    &name,
+                    detail,
+                    "Set \"fast_path\": {\"enabled\": true} so hooks answer before the window work",
+                )
+            } else {
+                Check::warn(
+                    &name,
+                    detail,
+                    format!(
+                        "Look for slow hooks with `recursor logs`, or raise fast_path.event_budgets_ms.{}",
+                        report.event
+                    ),
+                )
+            }
+        })
+        .collect()
+}
+
+/// Check that Cursor's settings database (which holds the command allowlist) can be read
+fn check_allowlist_db() -> Check {
+    const NAME: &str = "allowlist database";
+    let Some(path) =
+        dirs::config_dir().map(|dir| dir.join("Cursor/User/globalStorage/state.vscdb"))
+    else {
+        return Check::warn(
+            NAME,
+            "could not determine the config directory",
+            "Set HOME so Cursor's settings can be found",
+        );
+    };
+    if !path.exists() {
+        return Check::warn(
+            NAME,
+            format!("{} not found", path.display()),
+            "Open Cursor once so it creates its settings database",
+        );
+    }
+
+    let result = rusqlite::Connection::open_with_flags(
+        &path,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .and_then(|conn| {
+        conn.query_row("SELECT COUNT(*) FROM ItemTable", [], |row| {
+            row.get::<_, i64>(0)
+        })
+    });
+    match result {
+        Ok(_) => Check::pass(NAME, path.display().to_string()),
+        Err(e) => Check::warn(
+            NAME,
+            format!("can't read {}: {}", path.display(), e),
+            "Shell commands on Cursor's allowlist may be treated as needing approval",
+        ),
+    }
+}
+
+/// Find an executable on PATH
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let plain = dir.join(name);
+            let exe = dir.join(format!("{}.exe", name));
+            [plain, exe]
+        })
+        .find(|candidate| candidate.is_file() && is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn hooks_check_reports_missing_events_and_binaries() {
+        let hooks_file = json!({
+            "version": 1,
+            "hooks": {
+                "beforeSubmitPrompt": [{ "command": "/nonexistent/recursor hook" }],
+                "stop": [{ "command": "/nonexistent/recursor hook" }, { "command": "other" }]
+            }
+        });
+        let checks = check_hooks(
+            Path::new("hooks.json"),
+            &hooks_file,
+            "hooks.json",
+            "Run `recursor install-hooks`",
+        );
+
+        assert_eq!(checks.len(), 2);
+        assert_eq!(checks[0].status, CheckStatus::Warn);
+        assert!(checks[0].detail.contains("beforeShellExecution"));
+        assert_eq!(checks[1].name, "hook binary");
+        assert_eq!(checks[1].status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn hooks_check_fails_when_not_registered() {
+        let hooks_file = json!({ "version": 1, "hooks": { "stop": [{ "command": "other" }] } });
+        let checks = check_hooks(Path::new("hooks.json"), &hooks_file, "hooks.json", "hint");
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn report_serializes_with_snake_case_status() {
+        let checks = vec![
+            Check::pass("config file", "ok"),
+            Check::fail("hooks.json", "missing", "Run `recursor install-hooks`"),
+        ];
+        let value = serde_json::to_value(&checks).expect("serialize");
+        assert_eq!(value[0]["status"], "pass");
+        assert!(value[0].get("hint").is_none());
+        assert_eq!(value[1]["status"], "fail");
+        assert_eq!(failures(&checks), 1);
+        assert!(render_text(&checks).contains("1 passed, 0 warnings, 1 failed"));
+    }
+}
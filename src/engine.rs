@@ -0,0 +1,1602 @@
+//! Hook handling engine
+//!
+//! Turns parsed hook events into window management actions and hook responses.
+//! The CLI builds one `Engine` per invocation around the platform window
+//! manager; replay builds one around a playback window manager and a scratch
+//! state file.
+//!
+//! Every handler drives the conversation's lifecycle (see `lifecycle`) and only
+//! acts when its transition is valid. Valid transitions also fire the user's
+//! configured actions (see `actions`), and where focus moves is subject to the
+//! per-app rules (see `rules`), whose reasoning is stored for `recursor status`.
+//! Focus never moves while the user must not be disturbed (see `dnd`) or is
+//! away from the keyboard (see `away`), and conversations take turns pulling
+//! the user to Cursor (see `queue`).
+
+use crate::actions::{self, ActionPayload, Trigger};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::away::{self, AwayEvent};
+use crate::config::{Config, DndMode};
+use crate::dnd::DoNotDisturb;
+use crate::failsafe::{self, Failsafe, FailsafeDelay};
+use crate::history::{FocusTarget, History, HistoryEvent, RunRecord};
+use crate::hooks::{self, HookEvent, HookOutput, StopStatus};
+use crate::lifecycle::{Lifecycle, LifecycleEvent, Phase};
+use crate::logging::{Level, Logger};
+use crate::platform::dry_run::SuppressedCalls;
+use crate::platform::recording::describe_window;
+use crate::platform::{WindowInfo, WindowManager};
+use crate::predict::{Prediction, Predictor};
+use crate::queue::{PullKind, PullQueue, QueuedPull};
+use crate::rules::RuleSet;
+use crate::state::{ConversationState, StateManager};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Seconds a tool call may stay pending before the failsafe pulls the user to
+/// Cursor, unless past calls of the same command suggest otherwise (see `failsafe`)
+pub const SHELL_FAILSAFE_DELAY_SECONDS: u64 = 5;
+
+/// Handles hook events against a window manager and the state file
+pub struct Engine {
+    wm: Box<dyn WindowManager>,
+    state: StateManager,
+    config: Config,
+    rules: RuleSet,
+    dnd: DoNotDisturb,
+    history: Option<History>,
+    audit: Option<AuditLog>,
+    logger: Option<Logger>,
+    /// Focus and media calls the window manager held back, in dry-run mode
+    dry_run: Option<SuppressedCalls>,
+    /// The latest decision, for calls held back after it was explained
+    last_decision: RefCell<Option<Decision>>,
+    /// Whether beforeSubmitPrompt switches focus back to the saved window
+    bounce_on_submit: bool,
+    /// Whether to spawn background processes: failsafe timers for pending tool
+    /// calls and deferred pulls
+    spawn_failsafe_timers: bool,
+    /// Whether to run the configured actions
+    run_actions: bool,
+    /// The `recursor` binary that background processes and actions run with
+    recursor_binary: Option<PathBuf>,
+}
+
+impl Engine {
+    /// Create an engine with default behavior and no history. It starts no
+    /// other processes until given a binary with `with_recursor_binary`.
+    pub fn new(wm: Box<dyn WindowManager>, state: StateManager, config: Config) -> Self {
+        Self {
+            wm,
+            state,
+            rules: RuleSet::new(&config.rules),
+            dnd: DoNotDisturb::new(&config.do_not_disturb),
+            config,
+            history: None,
+            audit: None,
+            logger: None,
+            dry_run: None,
+            last_decision: RefCell::new(None),
+            bounce_on_submit: true,
+            spawn_failsafe_timers: true,
+            run_actions: true,
+            recursor_binary: None,
+        }
+    }
+
+    /// Record run events to the given history
+    #[doc(hidden)]
+    pub fn with_history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Record the shell commands agents run to the given audit log
+    #[doc(hidden)]
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Log decisions, focus attempts and failures to the given logger
+    #[doc(hidden)]
+    pub fn with_logger(mut self, logger: Logger) -> Self {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// Report the calls a `DryRunWindowManager` held back along with the
+    /// reasons for each decision, in the log and in `recursor status`
+    pub fn with_dry_run(mut self, suppressed: SuppressedCalls) -> Self {
+        self.dry_run = Some(suppressed);
+        self
+    }
+
+    /// Set whether beforeSubmitPrompt switches focus back to the saved window
+    pub fn with_bounce_on_submit(mut self, bounce: bool) -> Self {
+        self.bounce_on_submit = bounce;
+        self
+    }
+
+    /// Set whether pending tool calls spawn a failsafe timer process (and
+    /// deferred pulls a waiting process)
+    pub fn with_failsafe_timers(mut self, spawn: bool) -> Self {
+        self.spawn_failsafe_timers = spawn;
+        self
+    }
+
+    /// Set whether transitions run the configured actions
+    pub fn with_actions(mut self, run: bool) -> Self {
+        self.run_actions = run;
+        self
+    }
+
+    /// Run failsafe timers, deferred pulls and the configured actions as
+    /// subcommands of this `recursor` binary (the CLI passes itself)
+    pub fn with_recursor_binary(mut self, binary: PathBuf) -> Self {
+        self.recursor_binary = Some(binary);
+        self
+    }
+
+    /// Handle a hook event and return the output Cursor expects for it
+    pub fn handle(&self, event: HookEvent) -> Result<HookOutput> {
+        // When disabled, answer every hook without any window management
+        if !self.config.enabled {
+            return Ok(event.passthrough_output());
+        }
+
+        let output = match event {
+            HookEvent::BeforeSubmitPrompt(input) => {
+                HookOutput::BeforeSubmitPrompt(self.handle_before_submit(input)?)
+            }
+            HookEvent::BeforeShellExecution(input) => {
+                self.begin_pending_tool_call(
+                    &input.common.conversation_id_or_default(),
+                    input.command,
+                )?;
+                // Always allow the command to proceed
+                HookOutput::BeforeShellExecution(hooks::BeforeShellOutput::allow())
+            }
+            HookEvent::BeforeMCPExecution(input) => {
+                // MCP tool calls can wait on approval just like shell commands,
+                // so they get the same failsafe
+                self.begin_pending_tool_call(
+                    &input.common.conversation_id_or_default(),
+                    input.tool_name,
+                )?;
+                HookOutput::BeforeMCPExecution(hooks::BeforeMCPExecutionOutput::allow())
+            }
+            HookEvent::AfterShellExecution(input) => {
+                let conversation_id = input.common.conversation_id_or_default();
+                let pending = self.finish_pending_tool_call(
+                    &conversation_id,
+                    input.common.workspace_roots.clone(),
+                    input.duration,
+                )?;
+                self.audit_command(&conversation_id, input, pending.as_ref());
+                HookOutput::None
+            }
+            HookEvent::AfterMCPExecution(input) => {
+                self.finish_pending_tool_call(
+                    &input.common.conversation_id_or_default(),
+                    input.common.workspace_roots,
+                    input.duration,
+                )?;
+                HookOutput::None
+            }
+            HookEvent::Stop(input) => HookOutput::Stop(self.handle_stop(input)?),
+            // File hooks are observed but never change focus or block the agent,
+            // and unknown events get no output so Cursor proceeds with its defaults
+            other @ (HookEvent::BeforeReadFile(_)
+            | HookEvent::AfterFileEdit(_)
+            | HookEvent::Unknown(_)) => other.passthrough_output(),
+        };
+
+        Ok(output)
+    }
+
+    /// Handle a beforeSubmitPrompt event: save the window to return to and bounce back to it
+    fn handle_before_submit(
+        &self,
+        input: hooks::BeforeSubmitPromptInput,
+    ) -> Result<hooks::BeforeSubmitPromptOutput> {
+        let wm = self.wm.as_ref();
+        let conversation_id = input.common.conversation_id_or_default();
+
+        // Get the current active window (this is Cursor, since user just submitted prompt)
+        let cursor_window = wm.get_active_window().ok();
+
+        // Get the previous app the user was using (before they switched to Cursor)
+        let previous_window = wm.get_previous_window().ok();
+
+        // Pick the window to bounce back to: usually the previous app, subject to the rules
+        let mut why = Vec::new();
+        let return_target = self.rules.choose_return_target(
+            previous_window.as_ref(),
+            || wm.list_windows().unwrap_or_default(),
+            &mut why,
+        );
+
+        // Predict the run time from past runs; a short run keeps the user in Cursor
+        let prompt_chars = input.prompt.as_deref().map_or(0, |p| p.chars().count());
+        let prediction = self.predict_run(
+            input.common.model.as_deref(),
+            input.common.workspace_roots.first().map(String::as_str),
+            prompt_chars,
+        );
+        let short_run = self.bounce_on_submit
+            && return_target.is_some()
+            && prediction
+                .as_ref()
+                .is_some_and(|p| p.upper_seconds <= self.config.bounce.short_run_seconds as f64);
+        if let Some(ref prediction) = prediction {
+            why.push(format!("predicted run time {}", prediction.describe()));
+        }
+        let run = RunRecord {
+            model: input.common.model.clone(),
+            prompt_chars,
+            predicted_seconds: prediction.map(|p| p.median_seconds),
+            skipped_bounce: short_run,
+            ..RunRecord::default()
+        };
+
+        // Save state: remember the window to return to after commands are approved.
+        // Prefer the return target, but fall back to the current Cursor window if needed.
+        let window_to_save = select_window_to_save(cursor_window.clone(), return_target.clone());
+
+        if let Some(ref w) = window_to_save {
+            self.state.save_conversation(
+                &conversation_id,
+                w.clone(),
+                cursor_window.clone(),
+                input.common.workspace_roots.clone(),
+                run,
+            )?;
+        }
+
+        let Some((_, lifecycle)) =
+            self.advance(&conversation_id, LifecycleEvent::PromptSubmitted)?
+        else {
+            return Ok(hooks::BeforeSubmitPromptOutput::allow());
+        };
+        self.record(
+            &conversation_id,
+            HistoryEvent::Submit {
+                workspace_roots: input.common.workspace_roots.clone(),
+                model: input.common.model.clone(),
+            },
+        );
+
+        // Quick runs would bounce the user away and straight back, so with a bounce
+        // delay they stay in Cursor and a timer bounces them once the run is long enough
+        let delay_seconds = self.config.bounce.delay_seconds;
+        let bounce_now = self.bounce_on_submit && !short_run && delay_seconds == 0;
+        if !self.bounce_on_submit {
+            why.push("not switching focus (--no-focus)".to_string());
+        } else if short_run {
+            why.push(format!(
+                "staying in Cursor: runs like this usually finish within {}s",
+                self.config.bounce.short_run_seconds
+            ));
+        } else if !bounce_now && return_target.is_some() {
+            why.push(format!(
+                "staying in Cursor for {}s in case the run is quick",
+                delay_seconds
+            ));
+            self.spawn_background(&[
+                "bounce",
+                "--delay-seconds",
+                &delay_seconds.to_string(),
+                "--",
+                &conversation_id,
+            ]);
+        }
+        self.explain(&conversation_id, LifecycleEvent::PromptSubmitted, why);
+
+        self.fire(ActionPayload {
+            workspace_roots: input.common.workspace_roots,
+            saved_window: window_to_save.clone(),
+            ..ActionPayload::new(Trigger::OnSubmit, &conversation_id)
+        });
+
+        // Update menu bar status with rich information
+        if let Some(ref w) = window_to_save {
+            let media_playing = if w.app_name == "Google Chrome" {
+                Some(wm.is_youtube_playing())
+            } else {
+                None
+            };
+            wm.update_menu_bar_status_full(
+                lifecycle.phase.menu_bar_status(),
+                Some("Agent working on task..."),
+                Some(&w.app_name),
+                Some(&w.title),
+                media_playing,
+                None,
+            );
+        }
+
+        // Switch focus back to the previous app (unless explicitly disabled or delayed).
+        if bounce_now {
+            if let Some(ref prev) = return_target {
+                // Small delay to let the prompt submission complete.
+                std::thread::sleep(Duration::from_millis(50));
+                self.bounce_to(&conversation_id, prev);
+            }
+        }
+
+        Ok(hooks::BeforeSubmitPromptOutput::allow())
+    }
+
+    /// Estimate how long a run will take, when prediction is enabled and the
+    /// history has enough similar runs
+    fn predict_run(
+        &self,
+        model: Option<&str>,
+        workspace: Option<&str>,
+        prompt_chars: usize,
+    ) -> Option<Prediction> {
+        if !self.config.bounce.predict {
+            return None;
+        }
+        let entries = self.history.as_ref()?.entries().ok()?;
+        Predictor::from_history(&entries, self.config.bounce.min_samples).predict(
+            model,
+            workspace,
+            prompt_chars,
+        )
+    }
+
+    /// The failsafe delay for a tool call, learned from the history if there is one
+    fn failsafe_delay(&self, command: Option<&str>) -> FailsafeDelay {
+        let entries = self
+            .history
+            .as_ref()
+            .and_then(|h| h.entries().ok())
+            .unwrap_or_default();
+        Failsafe::from_history(&entries, &self.config.failsafe).delay_for(command)
+    }
+
+    /// Focus the window the user came from, resuming YouTube if they were watching it
+    fn bounce_to(&self, conversation_id: &str, window: &WindowInfo) {
+        let wm = self.wm.as_ref();
+
+        // Focus the previous window first.
+        self.log_focus(
+            conversation_id,
+            &describe_window(window),
+            wm.focus_window(window),
+        );
+        self.record(
+            conversation_id,
+            HistoryEvent::Focus {
+                to: FocusTarget::App,
+            },
+        );
+
+        // If it's Chrome, resume only if a YouTube tab is paused.
+        if window.app_name == "Google Chrome" {
+            std::thread::sleep(Duration::from_millis(150));
+            let _ = wm.resume_youtube(&window.title);
+        }
+    }
+
+    /// Bounce the user back once the agent has been working for `bounce.delay_seconds`.
+    /// Nothing happens if the run already stopped (or is waiting on a tool call),
+    /// or if the user has left Cursor on their own. With `enforce_delay`, nothing
+    /// happens unless the prompt was submitted at least the delay ago (guards
+    /// against a timer firing for a newer prompt).
+    pub fn delayed_bounce(&self, conversation_id: &str, enforce_delay: bool) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let lifecycle = self.state.load_lifecycle(conversation_id)?;
+        let Some(saved) = self.state.load_conversation(conversation_id)? else {
+            return Ok(());
+        };
+        if lifecycle.phase != Phase::Working || saved.saved_window.is_cursor() {
+            return Ok(());
+        }
+        let delay_seconds = self.config.bounce.delay_seconds as f64;
+        if enforce_delay && seconds_since(saved.saved_at) < delay_seconds {
+            return Ok(());
+        }
+        if !self
+            .wm
+            .get_active_window()
+            .is_ok_and(|active| active.is_cursor())
+        {
+            return Ok(());
+        }
+
+        self.bounce_to(conversation_id, &saved.saved_window);
+        self.explain(
+            conversation_id,
+            LifecycleEvent::PromptSubmitted,
+            vec![format!(
+                "agent still working after {}s; bounced you back to {}",
+                self.config.bounce.delay_seconds,
+                describe_window(&saved.saved_window)
+            )],
+        );
+        Ok(())
+    }
+
+    /// Handle a stop event.
+    /// What happens depends on the stop status: by default a completed run brings the
+    /// user to Cursor, an aborted run leaves focus alone, and an error also notifies.
+    fn handle_stop(&self, input: hooks::StopInput) -> Result<hooks::StopOutput> {
+        let wm = self.wm.as_ref();
+        let conversation_id = input.common.conversation_id_or_default();
+        let status = input.stop_status();
+        let loop_count = input.loop_count;
+        let workspace_roots = input.common.workspace_roots;
+
+        // On an error with retry budget left, ask the agent to try again and leave the
+        // user where they are; they only get pulled back once the budget is exhausted.
+        let followup =
+            self.config
+                .error_followup
+                .followup_for(status, loop_count, &workspace_roots);
+        let event = if followup.is_some() {
+            LifecycleEvent::Retried
+        } else {
+            LifecycleEvent::Stopped
+        };
+        let Some((_, lifecycle)) = self.advance(&conversation_id, event)? else {
+            return Ok(hooks::StopOutput::empty());
+        };
+
+        // Load saved state BEFORE clearing - we need the specific Cursor window info
+        let saved_state = self.state.load_conversation(&conversation_id)?;
+
+        let trigger = match status {
+            StopStatus::Error => Trigger::OnError,
+            StopStatus::Completed | StopStatus::Aborted => Trigger::OnFinished,
+        };
+        self.fire(ActionPayload {
+            workspace_roots: workspace_roots.clone(),
+            duration_seconds: saved_state.as_ref().map(|s| seconds_since(s.saved_at)),
+            saved_window: saved_state.as_ref().map(|s| s.saved_window.clone()),
+            status: Some(status),
+            ..ActionPayload::new(trigger, &conversation_id)
+        });
+
+        if let Some(followup) = followup {
+            wm.update_menu_bar_status_full(
+                lifecycle.phase.menu_bar_status(),
+                Some("Agent retrying after an error..."),
+                None,
+                None,
+                None,
+                Some(status.as_str()),
+            );
+            self.explain(
+                &conversation_id,
+                LifecycleEvent::Retried,
+                vec!["error_followup asked the agent to retry; focus left alone".to_string()],
+            );
+            self.record_stop(
+                &conversation_id,
+                status,
+                loop_count,
+                workspace_roots,
+                true,
+                saved_state.as_ref(),
+            );
+            return Ok(hooks::StopOutput::with_followup(&followup));
+        }
+
+        let action = self.config.on_stop.action_for(status);
+        let mut why = vec![format!(
+            "on_stop.{} is {}",
+            status.as_str(),
+            action.as_str()
+        )];
+
+        // While the user is away, neither pull nor notify; they catch up on return
+        let away = (action.pulls() || action.notifies()) && self.user_away(&mut why);
+        if away {
+            self.note_away(
+                &conversation_id,
+                PullKind::Finished { status },
+                saved_state.as_ref().and_then(|s| s.cursor_window.clone()),
+                &mut why,
+            );
+        }
+
+        // While the user must not be disturbed, they get a notification instead of
+        // losing focus, or the pull waits until the condition ends
+        let protected = !away && action.pulls() && self.do_not_disturb(&mut why);
+        let deferred = protected && self.dnd.config().mode == DndMode::Defer;
+        if action.pulls()
+            && !away
+            && !protected
+            && self.request_pull(
+                &conversation_id,
+                PullKind::Finished { status },
+                saved_state.as_ref().and_then(|s| s.cursor_window.clone()),
+                &mut why,
+            )
+        {
+            self.wait_for_typing_pause(&mut why);
+            self.pull_to_cursor_after_stop(&conversation_id, saved_state.as_ref());
+            why.push("pulled you to Cursor".to_string());
+        }
+        if deferred {
+            why.push(self.deferral_reason());
+            self.spawn_background(&["deferred-pull", "--", &conversation_id]);
+        }
+        self.explain(&conversation_id, LifecycleEvent::Stopped, why);
+
+        let message = match status {
+            StopStatus::Completed => "Agent finished",
+            StopStatus::Aborted => "Agent aborted",
+            StopStatus::Error => "Agent stopped with an error",
+        };
+        if !away && (action.notifies() || (protected && !deferred)) {
+            wm.notify("Recursor", message);
+        }
+
+        // Update menu bar status - agent stopped, now idle
+        wm.update_menu_bar_status_full(
+            lifecycle.phase.menu_bar_status(),
+            Some(message),
+            None,
+            None,
+            None,
+            Some(status.as_str()),
+        );
+
+        // Clear the saved windows for this conversation; a deferred pull still needs them
+        if !deferred {
+            self.state.clear_conversation(&conversation_id)?;
+        }
+
+        self.record_stop(
+            &conversation_id,
+            status,
+            loop_count,
+            workspace_roots,
+            false,
+            saved_state.as_ref(),
+        );
+
+        Ok(hooks::StopOutput::empty())
+    }
+
+    /// Append an event to the history; history is best-effort and never fails the hook
+    fn record(&self, conversation_id: &str, event: HistoryEvent) {
+        if let Some(ref history) = self.history {
+            if let Err(e) = history.append(conversation_id, event) {
+                self.log(Level::Warn, conversation_id, "history", &format!("{:#}", e));
+            }
+        }
+    }
+
+    /// Write to the debug log, if there is one
+    fn log(&self, level: Level, conversation_id: &str, kind: &str, message: &str) {
+        if let Some(ref logger) = self.logger {
+            logger.log(level, Some(conversation_id), kind, message);
+        }
+    }
+
+    /// Log the outcome of focusing a window
+    fn log_focus(&self, conversation_id: &str, target: &str, result: Result<()>) {
+        match result {
+            Ok(()) => self.log(
+                Level::Debug,
+                conversation_id,
+                "focus",
+                &format!("focused {}", target),
+            ),
+            Err(e) => self.log(
+                Level::Warn,
+                conversation_id,
+                "focus",
+                &format!("failed to focus {}: {:#}", target, e),
+            ),
+        }
+    }
+
+    /// Record a stop in the history
+    fn record_stop(
+        &self,
+        conversation_id: &str,
+        status: StopStatus,
+        loop_count: u32,
+        workspace_roots: Vec<String>,
+        followup: bool,
+        saved_state: Option<&ConversationState>,
+    ) {
+        let run = saved_state.map(|s| RunRecord {
+            duration_seconds: seconds_since(s.saved_at),
+            ..s.run.clone()
+        });
+        self.record(
+            conversation_id,
+            HistoryEvent::Stop {
+                status,
+                loop_count,
+                workspace_roots,
+                followup,
+                run,
+            },
+        );
+    }
+
+    /// Record a finished tool call in the history, for learning failsafe delays
+    fn record_tool_call(
+        &self,
+        conversation_id: &str,
+        pending: &Lifecycle,
+        duration_ms: Option<f64>,
+    ) {
+        let Some(prefix) = pending
+            .command
+            .as_deref()
+            .and_then(failsafe::command_prefix)
+        else {
+            return;
+        };
+        self.record(
+            conversation_id,
+            HistoryEvent::ToolCall {
+                prefix,
+                pending_seconds: pending.pending_seconds(),
+                run_seconds: duration_ms.map(|ms| ms / 1000.0),
+            },
+        );
+    }
+
+    /// Record a finished shell command in the audit log; like the history,
+    /// auditing is best-effort and never fails the hook
+    fn audit_command(
+        &self,
+        conversation_id: &str,
+        input: hooks::AfterShellInput,
+        pending: Option<&Lifecycle>,
+    ) {
+        let Some(ref audit) = self.audit else {
+            return;
+        };
+        let Some(command) = input
+            .command
+            .or_else(|| pending.and_then(|p| p.command.clone()))
+        else {
+            return;
+        };
+        let duration_seconds = input.duration.map(|ms| ms / 1000.0);
+        let pending_seconds = pending.map(Lifecycle::pending_seconds);
+        let needed_approval = pending.is_some_and(|p| p.phase == Phase::ApprovalPending)
+            || pending_seconds.is_some_and(|pending| {
+                failsafe::approval_wait_seconds(pending, duration_seconds).is_some()
+            });
+        let recorded = audit.record(AuditEntry {
+            at: Utc::now(),
+            conversation_id: conversation_id.to_string(),
+            workspace_roots: input.common.workspace_roots,
+            command,
+            duration_seconds,
+            pending_seconds,
+            needed_approval,
+            output: input.output,
+            output_truncated: false,
+        });
+        if let Err(e) = recorded {
+            self.log(Level::Warn, conversation_id, "audit", &format!("{:#}", e));
+        }
+    }
+
+    /// Bring the user to the Cursor window the prompt was submitted from,
+    /// pausing YouTube first if that's what they were watching.
+    fn pull_to_cursor_after_stop(
+        &self,
+        conversation_id: &str,
+        saved_state: Option<&ConversationState>,
+    ) {
+        let wm = self.wm.as_ref();
+
+        // Pause YouTube if the saved previous window was Chrome with YouTube
+        // We use the saved state (not get_active_window) because Cursor may already
+        // have focus by the time this hook fires
+        if let Some(state) = saved_state {
+            if state.saved_window.app_name == "Google Chrome" && state.saved_window.pid > 0 {
+                wm.pause_youtube_in_window(&state.saved_window);
+            }
+        }
+
+        // Small delay then bring user to the CORRECT Cursor window.
+        // When multiple Cursor windows are open, we must focus the specific one
+        // where the prompt was submitted, not just any Cursor window.
+        std::thread::sleep(Duration::from_millis(100));
+        self.focus_cursor_for(
+            conversation_id,
+            saved_state.and_then(|s| s.cursor_window.as_ref()),
+        );
+    }
+
+    /// Focus a conversation's Cursor window, or any Cursor window if it is unknown
+    fn focus_cursor_for(&self, conversation_id: &str, cursor_window: Option<&WindowInfo>) {
+        let wm = self.wm.as_ref();
+        let result = match cursor_window {
+            Some(cursor_win) => wm.focus_cursor_window(cursor_win),
+            None => wm.focus_cursor(),
+        };
+        self.log_focus(
+            conversation_id,
+            &cursor_window.map_or("Cursor".to_string(), describe_window),
+            result,
+        );
+        self.record(
+            conversation_id,
+            HistoryEvent::Focus {
+                to: FocusTarget::Cursor,
+            },
+        );
+    }
+
+    /// Apply a lifecycle event to a conversation and persist the result.
+    /// Returns the lifecycle before and after the event, or `None` if the event is
+    /// not valid in the conversation's current phase; that is reported and nothing
+    /// else happens.
+    fn advance(
+        &self,
+        conversation_id: &str,
+        event: LifecycleEvent,
+    ) -> Result<Option<(Lifecycle, Lifecycle)>> {
+        let current = self.state.load_lifecycle(conversation_id)?;
+        match current.apply(event) {
+            Ok(next) => {
+                self.state.save_lifecycle(conversation_id, &next)?;
+                Ok(Some((current, next)))
+            }
+            Err(invalid) => {
+                self.log(
+                    Level::Warn,
+                    conversation_id,
+                    "lifecycle",
+                    &invalid.to_string(),
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Whether the user is away from the keyboard (see `away`)
+    fn user_away(&self, why: &mut Vec<String>) -> bool {
+        let after_seconds = self.config.away.after_seconds;
+        if after_seconds == 0 {
+            return false;
+        }
+        match self.wm.input_idle_ms() {
+            Some(idle_ms) if idle_ms >= after_seconds * 1000 => {
+                why.push(format!("you have been away for {}s", idle_ms / 1000));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remember that a conversation needed the user while they were away. The
+    /// first such event starts a process that catches them up on return.
+    fn note_away(
+        &self,
+        conversation_id: &str,
+        kind: PullKind,
+        cursor_window: Option<WindowInfo>,
+        why: &mut Vec<String>,
+    ) {
+        why.push("will catch you up when you're back".to_string());
+        let event = AwayEvent::new(conversation_id, kind, cursor_window);
+        if self.state.record_away(event).unwrap_or(false) {
+            // A background process catches the user up once they're back
+            self.spawn_background(&["catch-up"]);
+        }
+    }
+
+    /// Ask the pull queue for the user's attention (see `queue`). Returns true if
+    /// the pull may happen now; otherwise it waits behind the pull the user is
+    /// handling, and a background process makes it once they are done.
+    fn request_pull(
+        &self,
+        conversation_id: &str,
+        kind: PullKind,
+        cursor_window: Option<WindowInfo>,
+        why: &mut Vec<String>,
+    ) -> bool {
+        if !self.config.pull_queue.enabled {
+            return true;
+        }
+        let busy = self.handling_other_pull(conversation_id);
+        let pull = QueuedPull::new(conversation_id, kind, cursor_window);
+        let queued = self.state.update_pull_queue(|queue| {
+            if !busy {
+                queue.handling = None;
+            }
+            let first = queue.waiting.is_empty();
+            queue.request(pull).map(|behind| (behind, first))
+        });
+        // A broken state file never holds a pull
+        let Ok(Some((behind, first))) = queued else {
+            return true;
+        };
+        why.push(format!(
+            "you are handling conversation {} ({}); waiting for your turn",
+            behind.conversation_id,
+            behind.kind.describe()
+        ));
+        if first {
+            // A background process works through the pull queue
+            self.spawn_background(&["pull-queue"]);
+        }
+        false
+    }
+
+    /// Whether the user is handling a pull to another conversation
+    fn handling_other_pull(&self, conversation_id: &str) -> bool {
+        if !self.config.pull_queue.enabled {
+            return false;
+        }
+        match self.state.get_pull_queue() {
+            Ok(PullQueue {
+                handling: Some(handling),
+                ..
+            }) => handling.conversation_id != conversation_id && self.still_handling(&handling),
+            _ => false,
+        }
+    }
+
+    /// Whether the user is still handling a pull they were brought to: the
+    /// conversation still needs them and they haven't gone back to another app
+    fn still_handling(&self, pull: &QueuedPull) -> bool {
+        let phase = self
+            .state
+            .load_lifecycle(&pull.conversation_id)
+            .map(|l| l.phase)
+            .unwrap_or_default();
+        pull.kind.still_relevant(phase) && self.wm.get_active_window().is_ok_and(|w| w.is_cursor())
+    }
+
+    /// Whether the user must not be disturbed right now (see `dnd`)
+    fn do_not_disturb(&self, why: &mut Vec<String>) -> bool {
+        self.dnd.active(self.wm.as_ref(), &self.rules, why)
+    }
+
+    /// Hold a pull until the user has stopped typing, so keystrokes don't land
+    /// in the wrong window. Gives up after `typing.max_wait_ms`; does nothing if
+    /// the platform can't tell when the user last typed.
+    fn wait_for_typing_pause(&self, why: &mut Vec<String>) {
+        let typing = &self.config.typing;
+        if typing.idle_ms == 0 {
+            return;
+        }
+        let started = Instant::now();
+        let mut waited = false;
+        while let Some(idle_ms) = self.wm.input_idle_ms() {
+            let waited_ms = started.elapsed().as_millis() as u64;
+            if idle_ms >= typing.idle_ms {
+                if waited {
+                    why.push(format!("waited {}ms for you to stop typing", waited_ms));
+                }
+                return;
+            }
+            if waited_ms >= typing.max_wait_ms {
+                why.push(format!(
+                    "you were still typing after {}ms; pulling anyway",
+                    waited_ms
+                ));
+                return;
+            }
+            // Check again when the user would have been idle long enough
+            let remaining_ms = (typing.idle_ms - idle_ms).min(typing.max_wait_ms - waited_ms);
+            std::thread::sleep(Duration::from_millis(remaining_ms));
+            waited = true;
+        }
+    }
+
+    /// Explanation for a pull that waits for the user
+    fn deferral_reason(&self) -> String {
+        format!(
+            "deferring the pull until this ends; checking every {}s",
+            self.dnd.config().poll_seconds
+        )
+    }
+
+    /// Store the reasons behind a decision; explanations are best-effort
+    fn explain(&self, conversation_id: &str, event: LifecycleEvent, why: Vec<String>) {
+        self.log(
+            Level::Info,
+            conversation_id,
+            "decision",
+            &format!("{}: {}", event.name(), why.join("; ")),
+        );
+        let decision = Decision {
+            conversation_id: conversation_id.to_string(),
+            event: event.name(),
+            why,
+        };
+        self.save_decision(&decision);
+        if self.dry_run.is_some() {
+            *self.last_decision.borrow_mut() = Some(decision);
+        }
+    }
+
+    /// Save a decision for `recursor status`, with the calls held back in
+    /// dry-run mode so far (those made while deciding, or right after)
+    fn save_decision(&self, decision: &Decision) {
+        let held_back = self
+            .dry_run
+            .as_ref()
+            .map(SuppressedCalls::take)
+            .unwrap_or_default();
+        let mut reasons = decision.why.clone();
+        for call in held_back {
+            self.log(
+                Level::Info,
+                &decision.conversation_id,
+                "dry_run",
+                &would(&call, &decision.why),
+            );
+            reasons.push(format!("dry run: would {}", call));
+        }
+        if let Err(e) =
+            self.state
+                .save_explanation(&decision.conversation_id, decision.event, reasons)
+        {
+            self.log(
+                Level::Warn,
+                &decision.conversation_id,
+                "state",
+                &format!("{:#}", e),
+            );
+        }
+    }
+
+    /// Start a background `recursor` process, if the engine may spawn them
+    fn spawn_background(&self, args: &[&str]) {
+        if let (true, Some(binary)) = (self.spawn_failsafe_timers, &self.recursor_binary) {
+            spawn_recursor(binary, args);
+        }
+    }
+
+    /// Start a background process that checks whether the tool call is still
+    /// pending after `delay_seconds`. It sleeps internally before checking.
+    fn spawn_failsafe_timer(&self, conversation_id: &str, delay_seconds: u64) {
+        self.spawn_background(&[
+            "check-idle",
+            "--delay-seconds",
+            &delay_seconds.to_string(),
+            "--",
+            conversation_id,
+        ]);
+    }
+
+    /// Run the actions configured for a payload's trigger in the background
+    fn fire(&self, payload: ActionPayload) {
+        if let (true, Some(binary)) = (self.run_actions, &self.recursor_binary) {
+            actions::dispatch(binary, &self.config.actions, &payload);
+        }
+    }
+
+    /// Record a pending shell command or MCP tool call and arm the approval failsafe.
+    /// Instead of immediately bringing user to Cursor, we remember where to return to
+    /// and spawn a failsafe timer. If the call is still pending once the delay chosen
+    /// for its command has passed, the failsafe brings the user to Cursor.
+    fn begin_pending_tool_call(
+        &self,
+        conversation_id: &str,
+        command: Option<String>,
+    ) -> Result<()> {
+        let wm = self.wm.as_ref();
+
+        // Get current window and determine the secondary window to track
+        let current_window = wm.get_active_window().ok();
+
+        // Determine the secondary window (the one to return to after command completes)
+        // If user is in Cursor, get the previous window they were in
+        // If user is NOT in Cursor, use current window as secondary
+        let secondary_window = if let Some(ref current) = current_window {
+            if current.is_cursor() {
+                // User is in Cursor - get the previous window they came from
+                wm.get_previous_window().ok()
+            } else {
+                // User is in another app - that's our secondary window
+                Some(current.clone())
+            }
+        } else {
+            None
+        };
+
+        // Without a window to return to there is nothing for the failsafe to do
+        let delay = secondary_window
+            .as_ref()
+            .map(|_| self.failsafe_delay(command.as_deref()));
+
+        // A new call restarts the pending phase even if the previous one never finished.
+        // This fixes the back-to-back command issue where command 2 fires while user is still in Cursor
+        let event = LifecycleEvent::ToolCallStarted {
+            return_to: secondary_window,
+            command,
+            failsafe_seconds: delay.as_ref().map(|d| d.seconds),
+        };
+        if self.advance(conversation_id, event.clone())?.is_none() {
+            return Ok(());
+        }
+        self.state.count_tool_call(conversation_id)?;
+
+        // Spawn the failsafe timer
+        // If the command is still pending after the delay, check-idle will bring user to Cursor
+        if let Some(delay) = delay {
+            self.explain(
+                conversation_id,
+                event,
+                vec![format!(
+                    "pulling you to Cursor if still pending after {}s: {}",
+                    delay.seconds, delay.reason
+                )],
+            );
+            self.spawn_failsafe_timer(conversation_id, delay.seconds);
+        }
+
+        Ok(())
+    }
+
+    /// Finish a pending shell command or MCP tool call and send the user back to their app.
+    /// `duration_ms` is the call's duration as Cursor reports it, if it does.
+    /// Returns the lifecycle of the call as it was pending, if one was.
+    fn finish_pending_tool_call(
+        &self,
+        conversation_id: &str,
+        workspace_roots: Vec<String>,
+        duration_ms: Option<f64>,
+    ) -> Result<Option<Lifecycle>> {
+        let wm = self.wm.as_ref();
+
+        let Some((pending, lifecycle)) =
+            self.advance(conversation_id, LifecycleEvent::ToolCallFinished)?
+        else {
+            return Ok(None);
+        };
+
+        self.fire(ActionPayload {
+            workspace_roots,
+            command: pending.command.clone(),
+            duration_seconds: Some(
+                duration_ms
+                    .map(|ms| ms / 1000.0)
+                    .unwrap_or_else(|| seconds_since(pending.since)),
+            ),
+            saved_window: pending.return_to.clone(),
+            ..ActionPayload::new(Trigger::OnCommandDone, conversation_id)
+        });
+        self.record_tool_call(conversation_id, &pending, duration_ms);
+
+        let mut why = Vec::new();
+        if let Some(prev) = pending.return_to.clone() {
+            let returning = self.rules.allows_return(&prev, &mut why)
+                && !self.user_away(&mut why)
+                && !self.do_not_disturb(&mut why);
+
+            // We may have brought user to Cursor while the call was pending
+            // Now bring them back to where they were
+            let mut media_playing = None;
+            if returning {
+                why.push(format!("returning to {}", describe_window(&prev)));
+                std::thread::sleep(Duration::from_millis(100));
+                self.log_focus(
+                    conversation_id,
+                    &describe_window(&prev),
+                    wm.focus_window(&prev),
+                );
+                self.record(
+                    conversation_id,
+                    HistoryEvent::Focus {
+                        to: FocusTarget::App,
+                    },
+                );
+
+                // Resume YouTube if it was Chrome
+                if prev.app_name == "Google Chrome" {
+                    std::thread::sleep(Duration::from_millis(150));
+                    media_playing = Some(wm.resume_youtube(&prev.title));
+                }
+            }
+
+            // Update menu bar - command approved, back to working
+            wm.update_menu_bar_status_full(
+                lifecycle.phase.menu_bar_status(),
+                Some("Agent working..."),
+                Some(&prev.app_name),
+                Some(&prev.title),
+                media_playing,
+                None,
+            );
+        } else {
+            why.push("no window to return to".to_string());
+        }
+        self.explain(conversation_id, LifecycleEvent::ToolCallFinished, why);
+
+        Ok(Some(pending))
+    }
+
+    /// Try a pull to Cursor that was deferred when the agent stopped.
+    /// Returns true once nothing is left to do: the user was pulled, or a new
+    /// prompt made the pull obsolete. Returns false while the user still must not
+    /// be disturbed.
+    pub fn deferred_pull(&self, conversation_id: &str) -> Result<bool> {
+        if !self.config.enabled {
+            return Ok(true);
+        }
+        let lifecycle = self.state.load_lifecycle(conversation_id)?;
+        let saved_state = self.state.load_conversation(conversation_id)?;
+        if lifecycle.phase != Phase::Finished || saved_state.is_none() {
+            return Ok(true);
+        }
+
+        let mut why = Vec::new();
+        if self.do_not_disturb(&mut why) || self.handling_other_pull(conversation_id) {
+            return Ok(false);
+        }
+        self.wait_for_typing_pause(&mut why);
+        self.pull_to_cursor_after_stop(conversation_id, saved_state.as_ref());
+        why.push("do-not-disturb ended; pulled you to Cursor".to_string());
+        self.explain(conversation_id, LifecycleEvent::Stopped, why);
+        self.state.clear_conversation(conversation_id)?;
+        Ok(true)
+    }
+
+    /// Move the pull queue along: once the user is done with the pull they are
+    /// handling, bring them to the most urgent conversation waiting for them.
+    /// Returns true once no pull is left waiting.
+    pub fn advance_pull_queue(&self) -> Result<bool> {
+        let queue = self.state.get_pull_queue()?;
+        if queue.waiting.is_empty() {
+            return Ok(true);
+        }
+        if !self.config.enabled || !self.config.pull_queue.enabled {
+            self.state
+                .update_pull_queue(|queue| *queue = PullQueue::default())?;
+            return Ok(true);
+        }
+        let mut why = Vec::new();
+        if queue
+            .handling
+            .as_ref()
+            .is_some_and(|h| self.still_handling(h))
+            || self.user_away(&mut why)
+            || self.do_not_disturb(&mut why)
+        {
+            return Ok(false);
+        }
+
+        while let Some(next) = self.state.update_pull_queue(PullQueue::advance)? {
+            let phase = self.state.load_lifecycle(&next.conversation_id)?.phase;
+            if !next.kind.still_relevant(phase) {
+                continue;
+            }
+            self.wait_for_typing_pause(&mut why);
+            self.focus_cursor_for(&next.conversation_id, next.cursor_window.as_ref());
+            why.push(format!(
+                "{}; you were done with the previous conversation, so pulled you to Cursor",
+                next.kind.describe()
+            ));
+            let event = match next.kind {
+                PullKind::ApprovalNeeded => LifecycleEvent::ApprovalTimedOut,
+                PullKind::Finished { .. } => LifecycleEvent::Stopped,
+            };
+            self.explain(&next.conversation_id, event, why);
+            return Ok(self.state.get_pull_queue()?.waiting.is_empty());
+        }
+        Ok(true)
+    }
+
+    /// Catch the user up on what happened while they were away.
+    /// Returns false while they are still away. Once they're back, focuses the
+    /// Cursor window of the most urgent conversation that still needs them,
+    /// shows a digest of the rest and returns true.
+    pub fn catch_up(&self) -> Result<bool> {
+        let mut why = Vec::new();
+        if self.config.enabled && self.user_away(&mut why) {
+            return Ok(false);
+        }
+        let events = self.state.take_away_events()?;
+        if !self.config.enabled {
+            return Ok(true);
+        }
+
+        let mut relevant = Vec::new();
+        for event in events {
+            let phase = self.state.load_lifecycle(&event.conversation_id)?.phase;
+            if event.kind.still_relevant(phase) {
+                relevant.push(event);
+            }
+        }
+        let Some(target) = away::most_urgent(&relevant) else {
+            return Ok(true);
+        };
+
+        let wm = self.wm.as_ref();
+        let digest = away::digest(&relevant);
+        self.wait_for_typing_pause(&mut why);
+        self.focus_cursor_for(&target.conversation_id, target.cursor_window.as_ref());
+        wm.notify("Recursor", &format!("While you were away: {}", digest));
+
+        why.push(format!("while you were away: {}", digest));
+        why.push("you're back; brought you to the most urgent conversation".to_string());
+        let event = match target.kind {
+            PullKind::ApprovalNeeded => LifecycleEvent::ApprovalTimedOut,
+            PullKind::Finished { .. } => LifecycleEvent::Stopped,
+        };
+        self.explain(&target.conversation_id, event, why);
+        Ok(true)
+    }
+
+    /// Failsafe that brings user to Cursor if a tool call is still pending.
+    /// With `enforce_delay`, nothing happens unless the call has been pending for
+    /// the full failsafe delay (guards against a timer firing for a newer call).
+    pub fn check_idle(&self, conversation_id: &str, enforce_delay: bool) -> Result<()> {
+        // Don't pull user to Cursor when disabled
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let wm = self.wm.as_ref();
+
+        // Verify that the call's failsafe delay has actually elapsed since it started
+        // This prevents race conditions where timer fires but command just started
+        let current = self.state.load_lifecycle(conversation_id)?;
+        let delay_seconds = current
+            .failsafe_seconds
+            .unwrap_or(SHELL_FAILSAFE_DELAY_SECONDS);
+        if enforce_delay
+            && current.phase == Phase::ToolPending
+            && current.elapsed_seconds() < delay_seconds as i64
+        {
+            // Not enough time has passed, don't bring user to Cursor yet
+            return Ok(());
+        }
+
+        // In defer mode the call stays pending while the user must not be
+        // disturbed, and another timer checks again later
+        let mut why = Vec::new();
+        let pending = current.phase == Phase::ToolPending;
+        let away = pending && self.user_away(&mut why);
+        let busy = pending && !away && self.do_not_disturb(&mut why);
+        if busy && self.dnd.config().mode == DndMode::Defer {
+            why.push(self.deferral_reason());
+            self.explain(conversation_id, LifecycleEvent::ApprovalTimedOut, why);
+            self.spawn_failsafe_timer(conversation_id, self.dnd.config().poll_seconds);
+            return Ok(());
+        }
+
+        // If the call already finished, the transition is invalid and nothing happens
+        let Some((_, lifecycle)) =
+            self.advance(conversation_id, LifecycleEvent::ApprovalTimedOut)?
+        else {
+            return Ok(());
+        };
+        self.record(conversation_id, HistoryEvent::ApprovalNeeded);
+
+        // Still pending after the delay - command is likely waiting for approval
+        // This is our failsafe: bring user to Cursor
+        let return_to = lifecycle.return_to.as_ref();
+        let main_state = self.state.load_conversation(conversation_id)?;
+
+        self.fire(ActionPayload {
+            workspace_roots: main_state
+                .as_ref()
+                .map(|s| s.workspace_roots.clone())
+                .unwrap_or_default(),
+            command: lifecycle.command.clone(),
+            duration_seconds: Some(seconds_since(current.since)),
+            saved_window: lifecycle.return_to.clone(),
+            ..ActionPayload::new(Trigger::OnApprovalNeeded, conversation_id)
+        });
+
+        // While the user is away the approval waits for their return; while they
+        // must not be disturbed, they get a notification instead; while they are
+        // handling another conversation, it waits for its turn
+        let cursor_window = main_state.as_ref().and_then(|s| s.cursor_window.clone());
+        let queued = !away
+            && !busy
+            && !self.request_pull(
+                conversation_id,
+                PullKind::ApprovalNeeded,
+                cursor_window,
+                &mut why,
+            );
+        if away {
+            self.note_away(
+                conversation_id,
+                PullKind::ApprovalNeeded,
+                main_state.as_ref().and_then(|s| s.cursor_window.clone()),
+                &mut why,
+            );
+        } else if busy {
+            wm.notify("Recursor", "A command is waiting for your approval");
+        }
+        if away || busy || queued {
+            self.explain(conversation_id, LifecycleEvent::ApprovalTimedOut, why);
+            wm.update_menu_bar_status_full(
+                lifecycle.phase.menu_bar_status(),
+                Some("Waiting for command approval..."),
+                return_to.map(|w| w.app_name.as_str()),
+                return_to.map(|w| w.title.as_str()),
+                None,
+                None,
+            );
+            return Ok(());
+        }
+
+        self.wait_for_typing_pause(&mut why);
+
+        // Pause YouTube if user was watching.
+        let media_playing = match return_to {
+            Some(w) if w.app_name == "Google Chrome" && wm.pause_youtube_if_playing(&w.title) => {
+                Some(false)
+            }
+            _ => None,
+        };
+
+        // Get the Cursor window from the conversation's saved state
+        self.focus_cursor_for(
+            conversation_id,
+            main_state.as_ref().and_then(|s| s.cursor_window.as_ref()),
+        );
+        why.push("command still pending; pulled you to Cursor for approval".to_string());
+        self.explain(conversation_id, LifecycleEvent::ApprovalTimedOut, why);
+
+        // Update menu bar to indicate we're waiting for approval
+        wm.update_menu_bar_status_full(
+            lifecycle.phase.menu_bar_status(),
+            Some("Waiting for command approval..."),
+            return_to.map(|w| w.app_name.as_str()),
+            return_to.map(|w| w.title.as_str()),
+            media_playing,
+            None,
+        );
+
+        Ok(())
+    }
+}
+
+/// The answer `Engine::handle` gives a hook, worked out without any window
+/// work so Cursor can have it first (see `worker`). Everything is allowed; a
+/// `stop` gets the error followup when one applies and the conversation's
+/// saved lifecycle can still retry, which is the same rule `handle_stop` uses.
+pub fn respond(config: &Config, state: &StateManager, event: &HookEvent) -> Result<HookOutput> {
+    if let (true, HookEvent::Stop(input)) = (config.enabled, event) {
+        let followup = config.error_followup.followup_for(
+            input.stop_status(),
+            input.loop_count,
+            &input.common.workspace_roots,
+        );
+        if let Some(followup) = followup {
+            let lifecycle = state.load_lifecycle(&input.common.conversation_id_or_default())?;
+            if lifecycle.apply(LifecycleEvent::Retried).is_ok() {
+                return Ok(HookOutput::Stop(hooks::StopOutput::with_followup(
+                    &followup,
+                )));
+            }
+        }
+    }
+    Ok(event.passthrough_output())
+}
+
+/// Why the engine did what it did for a conversation
+struct Decision {
+    conversation_id: String,
+    event: &'static str,
+    why: Vec<String>,
+}
+
+impl Drop for Engine {
+    /// Report calls held back after the last decision was explained
+    fn drop(&mut self) {
+        if let Some(decision) = self.last_decision.take() {
+            self.save_decision(&decision);
+        }
+        let Some(ref suppressed) = self.dry_run else {
+            return;
+        };
+        for call in suppressed.take() {
+            if let Some(ref logger) = self.logger {
+                logger.log(Level::Info, None, "dry_run", &would(&call, &[]));
+            }
+        }
+    }
+}
+
+/// "would focus X because Y" for a held-back call
+fn would(call: &str, why: &[String]) -> String {
+    if why.is_empty() {
+        format!("would {}", call)
+    } else {
+        format!("would {} because {}", call, why.join("; "))
+    }
+}
+
+/// Spawn a detached `recursor` process with the given arguments.
+/// Arguments are passed directly (no shell), so conversation IDs and binary paths
+/// need no quoting.
+fn spawn_recursor(binary: &Path, args: &[&str]) {
+    use std::process::{Command, Stdio};
+
+    let _ = Command::new(binary)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// Seconds elapsed since `at`
+fn seconds_since(at: DateTime<Utc>) -> f64 {
+    (Utc::now() - at).num_milliseconds() as f64 / 1000.0
+}
+
+fn select_window_to_save(
+    cursor_window: Option<WindowInfo>,
+    previous_window: Option<WindowInfo>,
+) -> Option<WindowInfo> {
+    previous_window.or(cursor_window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app_name: &str, title: &str, pid: u32) -> WindowInfo {
+        WindowInfo {
+            pid,
+            window_id: format!("{}:1", pid),
+            app_name: app_name.to_string(),
+            title: title.to_string(),
+            class: None,
+        }
+    }
+
+    #[test]
+    fn select_window_to_save_prefers_previous_window() {
+        let cursor = window("Cursor", "main.rs - Recursor - Cursor", 100);
+        let previous = window("Google Chrome", "YouTube", 200);
+
+        let selected = select_window_to_save(Some(cursor), Some(previous.clone()));
+        assert_eq!(selected, Some(previous));
+    }
+
+    #[test]
+    fn select_window_to_save_falls_back_to_cursor_window() {
+        let cursor = window("Cursor", "main.rs - Recursor - Cursor", 100);
+
+        let selected = select_window_to_save(Some(cursor.clone()), None);
+        assert_eq!(selected, Some(cursor));
+    }
+
+    #[test]
+    fn select_window_to_save_handles_missing_windows() {
+        assert_eq!(select_window_to_save(None, None), None);
+    }
+
+    #[test]
+    fn tool_call_lifecycle_returns_user_and_ignores_late_failsafe() {
+        use crate::platform::recording::{
+            CallResult, PlaybackWindowManager, RecordedCall, RecordingWindowManager,
+        };
+
+        let chrome = window("Google Chrome", "Docs", 200);
+        let playback = PlaybackWindowManager::new(vec![RecordedCall {
+            method: "get_active_window".to_string(),
+            args: vec![],
+            result: CallResult::Window(chrome.clone()),
+        }]);
+        let (recorder, log) = RecordingWindowManager::new(Box::new(playback));
+
+        let state_path = std::env::temp_dir().join(format!(
+            "recursor_engine_lifecycle_{}.json",
+            std::process::id()
+        ));
+        let engine = Engine::new(
+            Box::new(recorder),
+            StateManager::with_state_path(state_path.clone()),
+            Config::default(),
+        )
+        .with_failsafe_timers(false);
+
+        let event = |name: &str| {
+            HookEvent::parse(&format!(
+                r#"{{"hook_event_name": "{}", "conversation_id": "c1"}}"#,
+                name
+            ))
+            .expect("parse")
+        };
+
+        engine
+            .handle(event("beforeShellExecution"))
+            .expect("before");
+        let pending = engine.state.load_lifecycle("c1").expect("load");
+        assert_eq!(pending.phase, Phase::ToolPending);
+        assert_eq!(pending.return_to, Some(chrome.clone()));
+
+        engine.handle(event("afterShellExecution")).expect("after");
+        assert_eq!(
+            engine.state.load_lifecycle("c1").expect("load").phase,
+            Phase::Working
+        );
+        log.take();
+
+        // The failsafe for the finished call must not pull the user anywhere
+        engine.check_idle("c1", false).expect("check idle");
+        assert!(log.take().is_empty());
+        assert_eq!(
+            engine.state.load_lifecycle("c1").expect("load").phase,
+            Phase::Working
+        );
+
+        let _ = std::fs::remove_file(&state_path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn background_processes_only_start_with_a_recursor_binary() {
+        use crate::platform::recording::{CallResult, PlaybackWindowManager, RecordedCall};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir =
+            std::env::temp_dir().join(format!("recursor_engine_spawn_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        // Stands in for `recursor`, noting the subcommands it was started with
+        let started = dir.join("started.txt");
+        let binary = dir.join("fake-recursor");
+        std::fs::write(
+            &binary,
+            format!("#!/bin/sh\necho \"$@\" >> '{}'\n", started.display()),
+        )
+        .expect("write binary");
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let chrome = window("Google Chrome", "Docs", 200);
+        let engine = || {
+            let playback = PlaybackWindowManager::new(vec![RecordedCall {
+                method: "get_active_window".to_string(),
+                args: vec![],
+                result: CallResult::Window(chrome.clone()),
+            }]);
+            Engine::new(
+                Box::new(playback),
+                StateManager::with_state_path(dir.join("recursor_state.json")),
+                Config::default(),
+            )
+        };
+        let before_shell = |conversation: &str| {
+            HookEvent::parse(&format!(
+                r#"{{"hook_event_name": "beforeShellExecution", "conversation_id": "{}"}}"#,
+                conversation
+            ))
+            .expect("parse")
+        };
+
+        engine().handle(before_shell("embedded")).expect("embedded");
+        engine()
+            .with_recursor_binary(binary)
+            .handle(before_shell("cli"))
+            .expect("cli");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !started.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        let started = std::fs::read_to_string(&started).expect("read started");
+        assert_eq!(
+            started.lines().collect::<Vec<_>>(),
+            ["check-idle --delay-seconds 5 -- cli"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
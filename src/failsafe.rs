@@ -0,0 +1,283 @@
+//! Adaptive approval failsafe
+//!
+//! When a shell command or MCP tool call starts, a timer pulls the user to
+//! Cursor if the call is still pending after a delay: it is probably waiting
+//! for approval. The right delay depends on the command. `cargo build` runs
+//! for a minute without needing anyone, while other commands always wait.
+//!
+//! Every finished call is recorded in the history with its command prefix
+//! (`cargo build`, `npm run`, ...), how long it was pending and how long it
+//! actually ran; the difference is time spent waiting for approval. The delay
+//! for a new call comes from past calls with the same prefix, or failing that
+//! the same program: if most of them waited for approval, the failsafe fires
+//! after `failsafe.min_seconds`, otherwise once 90% of them would have finished
+//! on their own, up to `failsafe.max_seconds`. Without enough history the fixed
+//! `SHELL_FAILSAFE_DELAY_SECONDS` applies.
+
+use crate::config::FailsafeConfig;
+use crate::engine::SHELL_FAILSAFE_DELAY_SECONDS;
+use crate::history::{HistoryEntry, HistoryEvent};
+use crate::predict::quantile;
+
+/// Pending this much longer than the call ran means it waited for approval
+const APPROVAL_WAIT_SECONDS: f64 = 1.0;
+
+/// Added to the time calls usually take, so the failsafe fires after they finish
+const MARGIN_SECONDS: f64 = 1.0;
+
+/// Only the most recent calls are used, so delays follow changing habits
+const MAX_SAMPLES: usize = 1000;
+
+/// The part of a command that says what kind of call it is: the program and
+/// its subcommand, e.g. `cargo build` for `cd app && cargo build --release`.
+/// Environment assignments are skipped; arguments that look like paths or
+/// flags are not part of the prefix. MCP tool names are their own prefix.
+pub fn command_prefix(command: &str) -> Option<String> {
+    // Of chained commands, the last one is the one that takes time
+    let last = command.rsplit("&&").find(|part| !part.trim().is_empty())?;
+    let mut words = last
+        .split_whitespace()
+        .skip_while(|word| word.contains('=') && !word.starts_with('='));
+    let program = words.next()?;
+    let subcommand = words.next().filter(|word| {
+        word.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':')
+            && !word.starts_with('-')
+    });
+    Some(match subcommand {
+        Some(subcommand) => format!("{} {}", program, subcommand),
+        None => program.to_string(),
+    })
+}
+
+/// How long a call waited for approval: the time it was pending beyond its
+/// own run time, if that is long enough to count. `None` if it didn't wait or
+/// Cursor didn't report the run time.
+pub fn approval_wait_seconds(pending_seconds: f64, run_seconds: Option<f64>) -> Option<f64> {
+    run_seconds
+        .map(|run| pending_seconds - run)
+        .filter(|&wait| wait >= APPROVAL_WAIT_SECONDS)
+}
+
+/// A finished call from the history
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSample {
+    /// Command prefix of the call
+    pub prefix: String,
+    /// How long the call was pending
+    pub pending_seconds: f64,
+    /// Whether it waited for approval, if Cursor reported how long it ran
+    pub needed_approval: Option<bool>,
+}
+
+impl CallSample {
+    /// The finished call recorded by a history entry, if it is one
+    pub fn from_entry(entry: &HistoryEntry) -> Option<Self> {
+        let HistoryEvent::ToolCall {
+            ref prefix,
+            pending_seconds,
+            run_seconds,
+        } = entry.event
+        else {
+            return None;
+        };
+        Some(Self {
+            prefix: prefix.clone(),
+            pending_seconds,
+            needed_approval: run_seconds
+                .map(|_| approval_wait_seconds(pending_seconds, run_seconds).is_some()),
+        })
+    }
+
+    fn program(&self) -> &str {
+        self.prefix.split(' ').next().unwrap_or_default()
+    }
+}
+
+/// The failsafe delay chosen for a call
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailsafeDelay {
+    /// Seconds to wait before pulling the user to Cursor
+    pub seconds: u64,
+    /// Why, for explanations
+    pub reason: String,
+}
+
+/// Chooses failsafe delays from past calls
+pub struct Failsafe {
+    samples: Vec<CallSample>,
+    config: FailsafeConfig,
+}
+
+impl Failsafe {
+    /// Learn from the calls in the history
+    pub fn from_history(entries: &[HistoryEntry], config: &FailsafeConfig) -> Self {
+        let mut samples: Vec<CallSample> =
+            entries.iter().filter_map(CallSample::from_entry).collect();
+        if samples.len() > MAX_SAMPLES {
+            samples.drain(..samples.len() - MAX_SAMPLES);
+        }
+        Self::new(samples, config)
+    }
+
+    /// Learn from the given calls
+    pub fn new(samples: Vec<CallSample>, config: &FailsafeConfig) -> Self {
+        Self {
+            samples,
+            config: config.clone(),
+        }
+    }
+
+    /// The delay for a call of `command`
+    pub fn delay_for(&self, command: Option<&str>) -> FailsafeDelay {
+        let Some(prefix) = command.and_then(command_prefix) else {
+            return self.fixed("no command to learn from".to_string());
+        };
+        let program = prefix.split(' ').next().unwrap_or_default().to_string();
+
+        let mut groups = vec![(prefix.clone(), false)];
+        if program != prefix {
+            groups.push((program, true));
+        }
+        for (name, by_program) in groups {
+            let similar: Vec<&CallSample> = self
+                .samples
+                .iter()
+                .filter(|s| {
+                    if by_program {
+                        s.program() == name
+                    } else {
+                        s.prefix == name
+                    }
+                })
+                .collect();
+            if similar.len() >= self.config.min_samples.max(1) {
+                return self.learned(&name, &similar);
+            }
+        }
+        self.fixed(format!("too few past `{}` calls", prefix))
+    }
+
+    /// The delay learned from enough similar calls
+    fn learned(&self, name: &str, similar: &[&CallSample]) -> FailsafeDelay {
+        let known = similar
+            .iter()
+            .filter(|s| s.needed_approval.is_some())
+            .count();
+        let approvals = similar
+            .iter()
+            .filter(|s| s.needed_approval == Some(true))
+            .count();
+        if approvals * 2 > known {
+            return FailsafeDelay {
+                seconds: self.clamp(self.config.min_seconds as f64),
+                reason: format!(
+                    "`{}` waited for approval in {} of {} past calls",
+                    name, approvals, known
+                ),
+            };
+        }
+
+        let mut seconds: Vec<f64> = similar
+            .iter()
+            .filter(|s| s.needed_approval != Some(true))
+            .map(|s| s.pending_seconds)
+            .collect();
+        seconds.sort_by(f64::total_cmp);
+        let usual = quantile(&seconds, 0.9);
+        FailsafeDelay {
+            seconds: self.clamp(usual + MARGIN_SECONDS),
+            reason: format!(
+                "90% of {} past `{}` calls finished within {:.0}s without approval",
+                seconds.len(),
+                name,
+                usual
+            ),
+        }
+    }
+
+    /// The fixed delay, for calls without enough history
+    fn fixed(&self, why: String) -> FailsafeDelay {
+        FailsafeDelay {
+            seconds: self.clamp(SHELL_FAILSAFE_DELAY_SECONDS as f64),
+            reason: format!("{}; using the default delay", why),
+        }
+    }
+
+    /// Round up into the configured bounds
+    fn clamp(&self, seconds: f64) -> u64 {
+        let min = self.config.min_seconds;
+        let max = self.config.max_seconds.max(min);
+        (seconds.ceil() as u64).clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(prefix: &str, pending_seconds: f64, run_seconds: Option<f64>) -> CallSample {
+        CallSample {
+            prefix: prefix.to_string(),
+            pending_seconds,
+            needed_approval: run_seconds
+                .map(|_| approval_wait_seconds(pending_seconds, run_seconds).is_some()),
+        }
+    }
+
+    #[test]
+    fn delay_follows_past_calls_of_the_same_command() {
+        assert_eq!(
+            command_prefix("cd app && RUST_LOG=debug cargo build --release").as_deref(),
+            Some("cargo build")
+        );
+        assert_eq!(command_prefix("ls -la").as_deref(), Some("ls"));
+        assert_eq!(
+            command_prefix("python3 scripts/gen.py").as_deref(),
+            Some("python3")
+        );
+        assert_eq!(command_prefix("  "), None);
+
+        let mut samples = Vec::new();
+        for seconds in [20.0, 25.0, 30.0, 40.0] {
+            samples.push(call("cargo build", seconds, Some(seconds)));
+        }
+        for _ in 0..3 {
+            samples.push(call("rm", 12.0, Some(0.1)));
+        }
+        samples.push(call("cargo test", 8.0, None));
+        let failsafe = Failsafe::new(samples, &FailsafeConfig::default());
+
+        let build = failsafe.delay_for(Some("cargo build --release"));
+        assert_eq!(build.seconds, 41);
+        assert_eq!(
+            build.reason,
+            "90% of 4 past `cargo build` calls finished within 40s without approval"
+        );
+
+        let remove = failsafe.delay_for(Some("rm -rf target"));
+        assert_eq!(remove.seconds, 2);
+        assert_eq!(
+            remove.reason,
+            "`rm` waited for approval in 3 of 3 past calls"
+        );
+
+        // Too few `cargo fmt` calls: every cargo call counts
+        assert_eq!(failsafe.delay_for(Some("cargo fmt")).seconds, 41);
+
+        let unknown = failsafe.delay_for(Some("make"));
+        assert_eq!(unknown.seconds, SHELL_FAILSAFE_DELAY_SECONDS);
+        assert_eq!(
+            unknown.reason,
+            "too few past `make` calls; using the default delay"
+        );
+
+        // Long builds are capped
+        let capped = FailsafeConfig {
+            max_seconds: 30,
+            ..FailsafeConfig::default()
+        };
+        let failsafe = Failsafe::new(failsafe.samples, &capped);
+        assert_eq!(failsafe.delay_for(Some("cargo build")).seconds, 30);
+    }
+}
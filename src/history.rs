@@ -0,0 +1,204 @@
+//! Run history for Recursor
+//!
+//! Appends one JSON line per agent event to `~/.cursor/recursor_history.jsonl`
+//! so past runs can be inspected after their conversation state is cleared:
+//! prompts, finished tool calls, approvals, focus changes and stops. `predict`,
+//! `failsafe` and `stats` learn from it.
+
+use crate::hooks::StopStatus;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// An event recorded in the history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HistoryEvent {
+    /// A prompt was submitted
+    Submit {
+        /// Workspace roots of the conversation
+        #[serde(default)]
+        workspace_roots: Vec<String>,
+        /// Model the prompt was sent to
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+    },
+    /// A tool call was still pending when the failsafe fired, so it needed approval
+    ApprovalNeeded,
+    /// Recursor moved focus
+    Focus {
+        /// Where the user was sent
+        to: FocusTarget,
+    },
+    /// The agent loop stopped
+    Stop {
+        /// Status Cursor reported
+        status: StopStatus,
+        /// How many follow-ups had already been sent in this run
+        #[serde(default)]
+        loop_count: u32,
+        /// Workspace roots of the conversation
+        #[serde(default)]
+        workspace_roots: Vec<String>,
+        /// Whether Recursor asked the agent to retry instead of pulling the user back
+        #[serde(default)]
+        followup: bool,
+        /// What is known about the run that stopped
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run: Option<RunRecord>,
+    },
+    /// A shell command or MCP tool call finished
+    ToolCall {
+        /// Start of the command or the tool name (see `failsafe::command_prefix`)
+        prefix: String,
+        /// Seconds from the before hook to the after hook, approval included
+        pending_seconds: f64,
+        /// Seconds the call itself ran, as Cursor reports it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_seconds: Option<f64>,
+    },
+}
+
+/// Where a focus change sent the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusTarget {
+    /// A Cursor window
+    Cursor,
+    /// The app the user was parked in while the agent worked
+    App,
+}
+
+/// Features of an agent run, collected from its hooks and recorded when it stops
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunRecord {
+    /// Model the prompt was sent to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Length of the prompt in characters
+    pub prompt_chars: usize,
+    /// Shell commands and MCP tool calls made so far
+    pub tool_calls: u32,
+    /// Seconds from submitting the prompt until the stop
+    pub duration_seconds: f64,
+    /// Run time predicted when the prompt was submitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_seconds: Option<f64>,
+    /// Whether the prediction kept the user in Cursor instead of bouncing back
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped_bounce: bool,
+}
+
+/// A single line of the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the event happened
+    pub at: DateTime<Utc>,
+    /// Conversation the event belongs to
+    pub conversation_id: String,
+    /// What happened
+    #[serde(flatten)]
+    pub event: HistoryEvent,
+}
+
+/// Append-only store for history entries
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Create a history store at the default location
+    pub fn new() -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self {
+            path: home.join(".cursor").join("recursor_history.jsonl"),
+        })
+    }
+
+    #[cfg(test)]
+    fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Append an event for a conversation
+    pub fn append(&self, conversation_id: &str, event: HistoryEvent) -> Result<()> {
+        let entry = HistoryEntry {
+            at: Utc::now(),
+            conversation_id: conversation_id.to_string(),
+            event,
+        };
+        let line = serde_json::to_string(&entry).context("Failed to serialize history entry")?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Failed to create history directory")?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open history file")?;
+        writeln!(file, "{}", line).context("Failed to write history entry")?;
+        Ok(())
+    }
+
+    /// Read all entries, skipping lines that fail to parse
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = fs::read_to_string(&self.path).context("Failed to read history file")?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::unique_test_dir;
+
+    #[test]
+    fn appended_entries_round_trip_and_bad_lines_are_skipped() {
+        let path = unique_test_dir("history_round_trip").join("recursor_history.jsonl");
+        let history = History::with_path(path.clone());
+
+        history
+            .append(
+                "conv-1",
+                HistoryEvent::Stop {
+                    status: StopStatus::Error,
+                    loop_count: 1,
+                    workspace_roots: vec!["/work".to_string()],
+                    followup: false,
+                    run: None,
+                },
+            )
+            .expect("append");
+        let mut file = OpenOptions::new().append(true).open(&path).expect("open");
+        writeln!(file, "{{garbage").expect("write garbage");
+
+        let entries = history.entries().expect("read");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].conversation_id, "conv-1");
+        assert!(matches!(
+            entries[0].event,
+            HistoryEvent::Stop {
+                status: StopStatus::Error,
+                loop_count: 1,
+                ..
+            }
+        ));
+
+        let raw = fs::read_to_string(&path).expect("read raw");
+        assert!(raw.contains(r#""event":"stop""#));
+        assert!(raw.contains(r#""status":"error""#));
+
+        let _ = fs::remove_dir_all(path.parent().expect("parent"));
+    }
+}
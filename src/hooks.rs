@@ -8,8 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::io::{self, BufRead, Write};
 
 /// Common fields present in all hook inputs
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct HookInput {
     /// Stable ID of the conversation
     #[serde(default)]
@@ -34,10 +33,19 @@ pub struct HookInput {
     pub user_email: Option<String>,
 }
 
+impl HookInput {
+    /// The conversation ID, or "default" when Cursor didn't send one
+    pub fn conversation_id_or_default(&self) -> String {
+        self.conversation_id
+            .clone()
+            .unwrap_or_else(|| "default".to_string())
+    }
+}
+
 /// Input for beforeSubmitPrompt hook
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct BeforeSubmitPromptInput {
+    /// Fields common to every hook
     #[serde(flatten)]
     pub common: HookInput,
     /// The user's prompt text
@@ -66,7 +74,6 @@ impl BeforeSubmitPromptOutput {
     }
 
     /// Create an output that blocks the submission
-    #[allow(dead_code)]
     pub fn block(message: &str) -> Self {
         Self {
             continue_submission: false,
@@ -75,24 +82,51 @@ impl BeforeSubmitPromptOutput {
     }
 }
 
+/// Output for beforeShellExecution hook
+#[derive(Debug, Serialize)]
+pub struct BeforeShellOutput {
+    /// Whether the command may run ("allow", "deny" or "ask")
+    pub permission: String,
+    /// Optional message to show to the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_message: Option<String>,
+    /// Optional message to send back to the agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_message: Option<String>,
+}
+
+impl BeforeShellOutput {
+    /// Create an output that lets the command run
+    pub fn allow() -> Self {
+        Self {
+            permission: "allow".to_string(),
+            user_message: None,
+            agent_message: None,
+        }
+    }
+}
+
 /// Input for afterShellExecution hook
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct AfterShellInput {
+    /// Fields common to every hook
     #[serde(flatten)]
     pub common: HookInput,
+    /// The command that ran
     #[serde(default)]
     pub command: Option<String>,
+    /// Output of the command
     #[serde(default)]
     pub output: Option<String>,
+    /// How long the call took, in milliseconds
     #[serde(default)]
     pub duration: Option<f64>,
 }
 
 /// Input for beforeShellExecution hook
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct BeforeShellInput {
+    /// Fields common to every hook
     #[serde(flatten)]
     pub common: HookInput,
     /// The command about to be executed
@@ -100,10 +134,108 @@ pub struct BeforeShellInput {
     pub command: Option<String>,
 }
 
+/// Input for beforeMCPExecution hook
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BeforeMCPExecutionInput {
+    /// Fields common to every hook
+    #[serde(flatten)]
+    pub common: HookInput,
+    /// Name of the MCP tool being called
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// JSON-encoded tool arguments
+    #[serde(default)]
+    pub tool_input: Option<String>,
+    /// Server URL (for remote MCP servers)
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Server command (for local MCP servers)
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// Output for beforeMCPExecution hook (same shape as beforeShellExecution)
+pub type BeforeMCPExecutionOutput = BeforeShellOutput;
+
+/// Input for afterMCPExecution hook
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AfterMCPExecutionInput {
+    /// Fields common to every hook
+    #[serde(flatten)]
+    pub common: HookInput,
+    /// Name of the MCP tool
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// JSON-encoded tool arguments
+    #[serde(default)]
+    pub tool_input: Option<String>,
+    /// JSON-encoded tool result
+    #[serde(default)]
+    pub result_json: Option<String>,
+    /// How long the call took, in milliseconds
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// Input for beforeReadFile hook
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BeforeReadFileInput {
+    /// Fields common to every hook
+    #[serde(flatten)]
+    pub common: HookInput,
+    /// Path of the file the agent is about to read
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// File contents
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Output for beforeReadFile hook
+#[derive(Debug, Serialize)]
+pub struct BeforeReadFileOutput {
+    /// Whether the agent may read the file ("allow" or "deny")
+    pub permission: String,
+}
+
+impl BeforeReadFileOutput {
+    /// Create an output that lets the agent read the file
+    pub fn allow() -> Self {
+        Self {
+            permission: "allow".to_string(),
+        }
+    }
+}
+
+/// A single edit reported by afterFileEdit
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FileEdit {
+    /// Text that was replaced
+    #[serde(default)]
+    pub old_string: String,
+    /// Text it was replaced with
+    #[serde(default)]
+    pub new_string: String,
+}
+
+/// Input for afterFileEdit hook
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AfterFileEditInput {
+    /// Fields common to every hook
+    #[serde(flatten)]
+    pub common: HookInput,
+    /// Path of the edited file
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// Edits applied to the file
+    #[serde(default)]
+    pub edits: Vec<FileEdit>,
+}
+
 /// Input for stop hook
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct StopInput {
+    /// Fields common to every hook
     #[serde(flatten)]
     pub common: HookInput,
     /// Status of the agent loop
@@ -114,6 +246,40 @@ pub struct StopInput {
     pub loop_count: u32,
 }
 
+impl StopInput {
+    /// The parsed stop status (unknown or missing statuses count as completed)
+    pub fn stop_status(&self) -> StopStatus {
+        match self.status.as_deref() {
+            Some("aborted") => StopStatus::Aborted,
+            Some("error") => StopStatus::Error,
+            _ => StopStatus::Completed,
+        }
+    }
+}
+
+/// Status of the agent loop when the stop hook fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopStatus {
+    /// The agent finished its work
+    Completed,
+    /// The user stopped the agent
+    Aborted,
+    /// The agent stopped because of an error
+    Error,
+}
+
+impl StopStatus {
+    /// The status name as Cursor sends it
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Aborted => "aborted",
+            Self::Error => "error",
+        }
+    }
+}
+
 /// Output for stop hook
 #[derive(Debug, Serialize)]
 pub struct StopOutput {
@@ -131,7 +297,6 @@ impl StopOutput {
     }
 
     /// Create an output with a follow-up message
-    #[allow(dead_code)]
     pub fn with_followup(message: &str) -> Self {
         Self {
             followup_message: Some(message.to_string()),
@@ -139,20 +304,188 @@ impl StopOutput {
     }
 }
 
-/// Read JSON input from stdin
-pub fn read_input<T: for<'de> Deserialize<'de>>() -> Result<T> {
+/// A hook payload parsed according to its `hook_event_name`
+#[derive(Debug)]
+pub enum HookEvent {
+    /// `beforeSubmitPrompt`
+    BeforeSubmitPrompt(BeforeSubmitPromptInput),
+    /// `beforeShellExecution`
+    BeforeShellExecution(BeforeShellInput),
+    /// `afterShellExecution`
+    AfterShellExecution(AfterShellInput),
+    /// `beforeMCPExecution`
+    BeforeMCPExecution(BeforeMCPExecutionInput),
+    /// `afterMCPExecution`
+    AfterMCPExecution(AfterMCPExecutionInput),
+    /// `beforeReadFile`
+    BeforeReadFile(BeforeReadFileInput),
+    /// `afterFileEdit`
+    AfterFileEdit(AfterFileEditInput),
+    /// `stop`
+    Stop(StopInput),
+    /// An event Recursor doesn't handle (carries the event name, if any)
+    Unknown(Option<String>),
+}
+
+impl HookEvent {
+    /// Parse a raw hook payload, dispatching on `hook_event_name`
+    pub fn parse(raw: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(raw).context("Failed to parse JSON input")?;
+        let event_name = value
+            .get("hook_event_name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        Self::from_value(value, event_name)
+    }
+
+    /// Parse a raw hook payload as a specific event, ignoring `hook_event_name`
+    /// (used by the per-event subcommands, where the command decides the event)
+    pub fn parse_as(raw: &str, event_name: &str) -> Result<Self> {
+        let value: serde_json::Value =
+            serde_json::from_str(raw).context("Failed to parse JSON input")?;
+        Self::from_value(value, Some(event_name.to_string()))
+    }
+
+    /// An event with every input field defaulted (no conversation ID, no command, ...)
+    pub fn empty(event_name: &str) -> Self {
+        Self::from_value(serde_json::json!({}), Some(event_name.to_string()))
+            .unwrap_or(Self::Unknown(Some(event_name.to_string())))
+    }
+
+    fn from_value(value: serde_json::Value, event_name: Option<String>) -> Result<Self> {
+        let event = match event_name.as_deref() {
+            Some("beforeSubmitPrompt") => Self::BeforeSubmitPrompt(parse_value(value)?),
+            Some("beforeShellExecution") => Self::BeforeShellExecution(parse_value(value)?),
+            Some("afterShellExecution") => Self::AfterShellExecution(parse_value(value)?),
+            Some("beforeMCPExecution") => Self::BeforeMCPExecution(parse_value(value)?),
+            Some("afterMCPExecution") => Self::AfterMCPExecution(parse_value(value)?),
+            Some("beforeReadFile") => Self::BeforeReadFile(parse_value(value)?),
+            Some("afterFileEdit") => Self::AfterFileEdit(parse_value(value)?),
+            Some("stop") => Self::Stop(parse_value(value)?),
+            _ => Self::Unknown(event_name),
+        };
+
+        Ok(event)
+    }
+
+    /// The hook event name, as Cursor spells it
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::BeforeSubmitPrompt(_) => Some("beforeSubmitPrompt"),
+            Self::BeforeShellExecution(_) => Some("beforeShellExecution"),
+            Self::AfterShellExecution(_) => Some("afterShellExecution"),
+            Self::BeforeMCPExecution(_) => Some("beforeMCPExecution"),
+            Self::AfterMCPExecution(_) => Some("afterMCPExecution"),
+            Self::BeforeReadFile(_) => Some("beforeReadFile"),
+            Self::AfterFileEdit(_) => Some("afterFileEdit"),
+            Self::Stop(_) => Some("stop"),
+            Self::Unknown(name) => name.as_deref(),
+        }
+    }
+
+    /// Common fields of the event (None for unknown events)
+    pub fn common(&self) -> Option<&HookInput> {
+        match self {
+            Self::BeforeSubmitPrompt(i) => Some(&i.common),
+            Self::BeforeShellExecution(i) => Some(&i.common),
+            Self::AfterShellExecution(i) => Some(&i.common),
+            Self::BeforeMCPExecution(i) => Some(&i.common),
+            Self::AfterMCPExecution(i) => Some(&i.common),
+            Self::BeforeReadFile(i) => Some(&i.common),
+            Self::AfterFileEdit(i) => Some(&i.common),
+            Self::Stop(i) => Some(&i.common),
+            Self::Unknown(_) => None,
+        }
+    }
+
+    /// The parsed input as JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        let value = match self {
+            Self::BeforeSubmitPrompt(i) => serde_json::to_value(i),
+            Self::BeforeShellExecution(i) => serde_json::to_value(i),
+            Self::AfterShellExecution(i) => serde_json::to_value(i),
+            Self::BeforeMCPExecution(i) => serde_json::to_value(i),
+            Self::AfterMCPExecution(i) => serde_json::to_value(i),
+            Self::BeforeReadFile(i) => serde_json::to_value(i),
+            Self::AfterFileEdit(i) => serde_json::to_value(i),
+            Self::Stop(i) => serde_json::to_value(i),
+            Self::Unknown(_) => Ok(serde_json::Value::Null),
+        };
+        value.unwrap_or(serde_json::Value::Null)
+    }
+
+    /// The output that lets Cursor proceed without Recursor doing anything
+    pub fn passthrough_output(&self) -> HookOutput {
+        match self {
+            Self::BeforeSubmitPrompt(_) => {
+                HookOutput::BeforeSubmitPrompt(BeforeSubmitPromptOutput::allow())
+            }
+            Self::BeforeShellExecution(_) => {
+                HookOutput::BeforeShellExecution(BeforeShellOutput::allow())
+            }
+            Self::BeforeMCPExecution(_) => {
+                HookOutput::BeforeMCPExecution(BeforeMCPExecutionOutput::allow())
+            }
+            Self::BeforeReadFile(_) => HookOutput::BeforeReadFile(BeforeReadFileOutput::allow()),
+            Self::Stop(_) => HookOutput::Stop(StopOutput::empty()),
+            Self::AfterShellExecution(_)
+            | Self::AfterMCPExecution(_)
+            | Self::AfterFileEdit(_)
+            | Self::Unknown(_) => HookOutput::None,
+        }
+    }
+}
+
+/// The response for a hook event, serialized as the output type Cursor expects
+#[derive(Debug)]
+pub enum HookOutput {
+    /// Response to `beforeSubmitPrompt`
+    BeforeSubmitPrompt(BeforeSubmitPromptOutput),
+    /// Response to `beforeShellExecution`
+    BeforeShellExecution(BeforeShellOutput),
+    /// Response to `beforeMCPExecution`
+    BeforeMCPExecution(BeforeMCPExecutionOutput),
+    /// Response to `beforeReadFile`
+    BeforeReadFile(BeforeReadFileOutput),
+    /// Response to `stop`
+    Stop(StopOutput),
+    /// The event takes no output
+    None,
+}
+
+impl HookOutput {
+    /// The output as JSON (None for events without output)
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        let value = match self {
+            Self::BeforeSubmitPrompt(o) => serde_json::to_value(o),
+            Self::BeforeShellExecution(o) => serde_json::to_value(o),
+            Self::BeforeMCPExecution(o) => serde_json::to_value(o),
+            Self::BeforeReadFile(o) => serde_json::to_value(o),
+            Self::Stop(o) => serde_json::to_value(o),
+            Self::None => return None,
+        };
+        value.ok()
+    }
+}
+
+fn parse_value<T: for<'de> Deserialize<'de>>(value: serde_json::Value) -> Result<T> {
+    serde_json::from_value(value).context("Failed to parse hook input")
+}
+
+/// Read all of stdin into a string
+pub fn read_stdin() -> Result<String> {
     let stdin = io::stdin();
     let mut input = String::new();
 
-    // Read all input from stdin
     for line in stdin.lock().lines() {
         let line = line.context("Failed to read from stdin")?;
         input.push_str(&line);
         input.push('\n');
     }
 
-    // Parse JSON
-    serde_json::from_str(&input).context("Failed to parse JSON input")
+    Ok(input)
 }
 
 /// Write JSON output to stdout
@@ -168,7 +501,140 @@ pub fn write_output<T: Serialize>(output: &T) -> Result<()> {
     Ok(())
 }
 
-/// Try to read input, returning None if stdin is empty or not valid JSON
-pub fn try_read_input<T: for<'de> Deserialize<'de>>() -> Option<T> {
-    read_input().ok()
+/// Write the output for a dispatched hook event (nothing for events without output)
+pub fn write_hook_output(output: &HookOutput) -> Result<()> {
+    match output.to_json() {
+        Some(json) => write_output(&json),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dispatches_on_hook_event_name() {
+        let raw =
+            r#"{"hook_event_name":"beforeShellExecution","conversation_id":"c1","command":"ls"}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::BeforeShellExecution(input) => {
+                assert_eq!(input.common.conversation_id.as_deref(), Some("c1"));
+                assert_eq!(input.command.as_deref(), Some("ls"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let raw = r#"{"hook_event_name":"stop","status":"completed","loop_count":2}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::Stop(input) => {
+                assert_eq!(input.status.as_deref(), Some("completed"));
+                assert_eq!(input.loop_count, 2);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_handles_mcp_and_file_events() {
+        let raw = r#"{"hook_event_name":"beforeMCPExecution","conversation_id":"c1","tool_name":"search","tool_input":"{\"q\":1}"}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::BeforeMCPExecution(input) => {
+                assert_eq!(input.common.conversation_id.as_deref(), Some("c1"));
+                assert_eq!(input.tool_name.as_deref(), Some("search"));
+                assert_eq!(input.tool_input.as_deref(), Some(r#"{"q":1}"#));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let raw = r#"{"hook_event_name":"afterMCPExecution","tool_name":"search","result_json":"{}","duration":1.5}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::AfterMCPExecution(input) => assert_eq!(input.duration, Some(1.5)),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let raw = r#"{"hook_event_name":"afterFileEdit","file_path":"/tmp/a.rs","edits":[{"old_string":"a","new_string":"b"}]}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::AfterFileEdit(input) => {
+                assert_eq!(input.file_path.as_deref(), Some("/tmp/a.rs"));
+                assert_eq!(input.edits.len(), 1);
+                assert_eq!(input.edits[0].new_string, "b");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let raw = r#"{"hook_event_name":"beforeReadFile","file_path":"/tmp/a.rs"}"#;
+        assert!(matches!(
+            HookEvent::parse(raw).expect("parse"),
+            HookEvent::BeforeReadFile(_)
+        ));
+    }
+
+    #[test]
+    fn stop_status_defaults_to_completed() {
+        let parse = |raw: &str| match HookEvent::parse(raw).expect("parse") {
+            HookEvent::Stop(input) => input.stop_status(),
+            other => panic!("unexpected event: {:?}", other),
+        };
+        assert_eq!(
+            parse(r#"{"hook_event_name":"stop","status":"aborted"}"#),
+            StopStatus::Aborted
+        );
+        assert_eq!(
+            parse(r#"{"hook_event_name":"stop","status":"error"}"#),
+            StopStatus::Error
+        );
+        assert_eq!(
+            parse(r#"{"hook_event_name":"stop"}"#),
+            StopStatus::Completed
+        );
+    }
+
+    #[test]
+    fn parse_keeps_unknown_event_names() {
+        let raw = r#"{"hook_event_name":"somethingNew"}"#;
+        match HookEvent::parse(raw).expect("parse") {
+            HookEvent::Unknown(name) => assert_eq!(name.as_deref(), Some("somethingNew")),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_as_ignores_hook_event_name() {
+        let raw = r#"{"hook_event_name":"stop","conversation_id":"c1"}"#;
+        let event = HookEvent::parse_as(raw, "afterShellExecution").expect("parse");
+        assert_eq!(event.name(), Some("afterShellExecution"));
+        assert_eq!(
+            event.common().map(|c| c.conversation_id_or_default()),
+            Some("c1".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_event_uses_default_conversation() {
+        let event = HookEvent::empty("beforeSubmitPrompt");
+        assert_eq!(event.name(), Some("beforeSubmitPrompt"));
+        assert_eq!(
+            event.common().map(|c| c.conversation_id_or_default()),
+            Some("default".to_string())
+        );
+        assert!(matches!(
+            event.passthrough_output(),
+            HookOutput::BeforeSubmitPrompt(BeforeSubmitPromptOutput {
+                continue_submission: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_json() {
+        assert!(HookEvent::parse("{not json").is_err());
+    }
+
+    #[test]
+    fn before_shell_allow_serializes_permission_only() {
+        let json = serde_json::to_string(&BeforeShellOutput::allow()).expect("serialize");
+        assert_eq!(json, r#"{"permission":"allow"}"#);
+    }
 }
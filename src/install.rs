@@ -0,0 +1,350 @@
+//! Installing Recursor's entries into Cursor's hooks.json
+//!
+//! Edits `~/.cursor/hooks.json` (or a project's `.cursor/hooks.json`) in place:
+//! Recursor's entries are added or removed per event and everything else in
+//! the file—other hooks, other events, unknown keys—is left untouched.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Hook events Recursor registers for
+pub const RECURSOR_EVENTS: &[&str] = &[
+    "beforeSubmitPrompt",
+    "beforeShellExecution",
+    "afterShellExecution",
+    "beforeMCPExecution",
+    "afterMCPExecution",
+    "stop",
+];
+
+/// Subcommands that mark a hook entry as belonging to Recursor
+const RECURSOR_SUBCOMMANDS: &[&str] = &["hook", "save", "restore", "before-shell", "after-shell"];
+
+/// Path of the user-level hooks file
+pub fn user_hooks_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    Ok(home.join(".cursor").join("hooks.json"))
+}
+
+/// Path of the project-level hooks file for a project directory
+pub fn project_hooks_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(".cursor").join("hooks.json")
+}
+
+/// The hook command for a Recursor binary
+pub fn hook_command(binary: &Path) -> String {
+    let path = binary.display().to_string();
+    if path.contains(' ') {
+        format!("\"{}\" hook", path)
+    } else {
+        format!("{} hook", path)
+    }
+}
+
+/// Split a hook command into its program and the rest of the line
+pub fn split_command(command: &str) -> Option<(&str, &str)> {
+    let command = command.trim();
+    // The program may be quoted if its path contains spaces
+    if let Some(stripped) = command.strip_prefix('"') {
+        stripped.split_once('"')
+    } else {
+        Some(command.split_once(' ').unwrap_or((command, "")))
+    }
+}
+
+/// Check whether a hook command runs Recursor
+pub fn is_recursor_command(command: &str) -> bool {
+    let Some((program, rest)) = split_command(command) else {
+        return false;
+    };
+
+    let file_name = program
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(program)
+        .to_lowercase();
+    let is_recursor_binary = file_name == "recursor" || file_name == "recursor.exe";
+    let subcommand = rest.split_whitespace().next().unwrap_or("");
+
+    is_recursor_binary && RECURSOR_SUBCOMMANDS.contains(&subcommand)
+}
+
+/// Load a hooks file, or an empty one if it doesn't exist
+pub fn load_hooks_file(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(json!({ "version": 1, "hooks": {} }));
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    if !value.is_object() {
+        return Err(anyhow!("{} is not a JSON object", path.display()));
+    }
+    Ok(value)
+}
+
+/// Render a hooks file the way it is written to disk
+pub fn render_hooks_file(hooks_file: &Value) -> Result<String> {
+    let mut json = serde_json::to_string_pretty(hooks_file).context("Failed to serialize hooks")?;
+    json.push('\n');
+    Ok(json)
+}
+
+fn hooks_map(hooks_file: &mut Value) -> Result<&mut Map<String, Value>> {
+    let root = hooks_file
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("hooks.json is not a JSON object"))?;
+    root.entry("version").or_insert(json!(1));
+    root.entry("hooks")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("\"hooks\" in hooks.json is not an object"))
+}
+
+fn entry_is_recursor(entry: &Value) -> bool {
+    entry
+        .get("command")
+        .and_then(|c| c.as_str())
+        .is_some_and(is_recursor_command)
+}
+
+/// Recursor commands registered in a hooks file, as (event, command) pairs
+pub fn registered_commands(hooks_file: &Value) -> Vec<(String, String)> {
+    let Some(hooks) = hooks_file.get("hooks").and_then(|h| h.as_object()) else {
+        return Vec::new();
+    };
+    hooks
+        .iter()
+        .filter_map(|(event, entries)| Some((event, entries.as_array()?)))
+        .flat_map(|(event, entries)| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.get("command")?.as_str())
+                .filter(|command| is_recursor_command(command))
+                .map(move |command| (event.clone(), command.to_string()))
+        })
+        .collect()
+}
+
+/// Register `command` for every Recursor event.
+/// An existing Recursor entry is replaced in place and duplicates are dropped,
+/// so installing twice leaves the file unchanged.
+pub fn install(hooks_file: &mut Value, command: &str) -> Result<()> {
+    let hooks = hooks_map(hooks_file)?;
+
+    for event in RECURSOR_EVENTS {
+        let entries = hooks
+            .entry(event.to_string())
+            .or_insert_with(|| json!([]))
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("\"{}\" in hooks.json is not a list", event))?;
+
+        let ours = json!({ "command": command });
+        match entries.iter().position(entry_is_recursor) {
+            Some(index) => {
+                entries[index] = ours;
+                let mut seen = 0;
+                entries.retain(|entry| {
+                    if entry_is_recursor(entry) {
+                        seen += 1;
+                        seen == 1
+                    } else {
+                        true
+                    }
+                });
+            }
+            None => entries.push(ours),
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove Recursor's entries from every event.
+/// Events left with no entries are removed as well.
+pub fn uninstall(hooks_file: &mut Value) -> Result<()> {
+    let hooks = hooks_map(hooks_file)?;
+
+    let events: Vec<String> = hooks.keys().cloned().collect();
+    for event in events {
+        let Some(entries) = hooks.get_mut(&event).and_then(|e| e.as_array_mut()) else {
+            continue;
+        };
+        let before = entries.len();
+        entries.retain(|entry| !entry_is_recursor(entry));
+        if entries.is_empty() && before > 0 {
+            hooks.remove(&event);
+        }
+    }
+
+    Ok(())
+}
+
+/// Line diff between two texts, with "-"/"+"/" " prefixes
+pub fn diff_lines(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push_str(&format!("  {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Write a hooks file atomically
+pub fn write_hooks_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create hooks directory")?;
+    }
+    let temp_path = path.with_extension(format!("json.tmp.{}", std::process::id()));
+    fs::write(&temp_path, contents).context("Failed to write temporary hooks file")?;
+    if let Err(err) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err).context("Failed to replace hooks file");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAND: &str = "/home/me/.cursor/bin/recursor hook";
+
+    #[test]
+    fn recognizes_recursor_commands() {
+        assert!(is_recursor_command("/home/me/.cursor/bin/recursor hook"));
+        assert!(is_recursor_command("recursor save --no-focus"));
+        assert!(is_recursor_command(
+            "\"C:\\Program Files\\recursor.exe\" restore"
+        ));
+        assert!(!is_recursor_command("/usr/bin/recursor-lint hook"));
+        assert!(!is_recursor_command("/home/me/bin/other-tool save"));
+        assert!(!is_recursor_command("recursor status"));
+    }
+
+    #[test]
+    fn install_keeps_other_hooks_and_is_idempotent() {
+        let mut hooks_file = json!({
+            "version": 1,
+            "hooks": {
+                "stop": [{ "command": "/usr/bin/notify-done" }],
+                "afterFileEdit": [{ "command": "prettier --write" }]
+            }
+        });
+
+        install(&mut hooks_file, COMMAND).expect("install");
+        let once = hooks_file.clone();
+        install(&mut hooks_file, COMMAND).expect("install again");
+        assert_eq!(hooks_file, once);
+
+        let stop = hooks_file["hooks"]["stop"].as_array().expect("stop list");
+        assert_eq!(stop.len(), 2);
+        assert_eq!(stop[0]["command"], "/usr/bin/notify-done");
+        assert_eq!(stop[1]["command"], COMMAND);
+        assert_eq!(
+            hooks_file["hooks"]["afterFileEdit"][0]["command"],
+            "prettier --write"
+        );
+        for event in RECURSOR_EVENTS {
+            assert!(hooks_file["hooks"][event]
+                .as_array()
+                .expect("event list")
+                .iter()
+                .any(|e| e["command"] == COMMAND));
+        }
+    }
+
+    #[test]
+    fn install_replaces_legacy_entries_in_place() {
+        let mut hooks_file = json!({
+            "version": 1,
+            "hooks": {
+                "beforeSubmitPrompt": [
+                    { "command": "first" },
+                    { "command": "/old/recursor save" },
+                    { "command": "last" },
+                    { "command": "/old/recursor save --no-focus" }
+                ]
+            }
+        });
+
+        install(&mut hooks_file, COMMAND).expect("install");
+        let commands: Vec<&str> = hooks_file["hooks"]["beforeSubmitPrompt"]
+            .as_array()
+            .expect("list")
+            .iter()
+            .filter_map(|e| e["command"].as_str())
+            .collect();
+        assert_eq!(commands, vec!["first", COMMAND, "last"]);
+    }
+
+    #[test]
+    fn uninstall_removes_only_recursor_entries() {
+        let mut hooks_file = json!({
+            "version": 1,
+            "hooks": {
+                "stop": [{ "command": "/usr/bin/notify-done" }],
+                "afterFileEdit": []
+            }
+        });
+        install(&mut hooks_file, COMMAND).expect("install");
+        uninstall(&mut hooks_file).expect("uninstall");
+
+        assert_eq!(
+            hooks_file,
+            json!({
+                "version": 1,
+                "hooks": {
+                    "stop": [{ "command": "/usr/bin/notify-done" }],
+                    "afterFileEdit": []
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn diff_marks_added_and_removed_lines() {
+        let diff = diff_lines("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(diff, "  a\n- b\n  c\n+ d\n");
+    }
+
+    #[test]
+    fn hook_command_quotes_paths_with_spaces() {
+        assert_eq!(
+            hook_command(Path::new("/Users/me/My Tools/recursor")),
+            "\"/Users/me/My Tools/recursor\" hook"
+        );
+        assert_eq!(
+            hook_command(Path::new("/usr/local/bin/recursor")),
+            "/usr/local/bin/recursor hook"
+        );
+    }
+}
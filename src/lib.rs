@@ -0,0 +1,85 @@
+//! Recursor - The "Bounce Back" Utility for Cursor AI Agents
+//!
+//! Recursor reacts to Cursor's agent hooks: when you submit a prompt it sends
+//! you back to what you were doing, and when the agent needs you (a command
+//! waiting for approval, or the run finishing) it brings you back to Cursor.
+//!
+//! The `recursor` binary is a thin CLI over this library. To embed the same
+//! behavior in other tools, build an [`Engine`] around a [`WindowManager`] and a
+//! [`StateManager`] and feed it [`HookEvent`]s:
+//!
+//! ```no_run
+//! use recursor::{Config, Engine, HookEvent, StateManager};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let wm = recursor::platform::create_backend(&Config::load().backend.resolve()?)?;
+//! let engine = Engine::new(wm, StateManager::new()?, Config::load());
+//!
+//! let event = HookEvent::parse(r#"{"hook_event_name": "stop", "conversation_id": "c1"}"#)?;
+//! if let Some(json) = engine.handle(event)?.to_json() {
+//!     println!("{}", json);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failsafe timers, deferred pulls and configured actions run as subcommands of
+//! the `recursor` binary, so an engine only starts them once it is given one
+//! with [`Engine::with_recursor_binary`].
+//!
+//! # API surface
+//!
+//! The supported API is the re-exports below and the [`config`], [`engine`],
+//! [`hooks`], [`lifecycle`], [`platform`] and [`state`] modules. The other
+//! modules back the CLI's subcommands. They are public only so the `recursor`
+//! binary can use them, are left out of these docs and may change in any
+//! release.
+
+#![warn(missing_docs)]
+
+pub mod config;
+pub mod engine;
+pub mod hooks;
+pub mod lifecycle;
+pub mod platform;
+pub mod state;
+
+#[doc(hidden)]
+pub mod actions;
+#[doc(hidden)]
+pub mod audit;
+#[doc(hidden)]
+pub mod away;
+#[doc(hidden)]
+pub mod bench;
+#[doc(hidden)]
+pub mod capture;
+#[doc(hidden)]
+pub mod dnd;
+#[doc(hidden)]
+pub mod doctor;
+#[doc(hidden)]
+pub mod history;
+#[doc(hidden)]
+pub mod install;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod queue;
+#[doc(hidden)]
+pub mod rules;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod worker;
+
+mod failsafe;
+mod predict;
+mod rotating;
+
+pub use config::Config;
+pub use engine::Engine;
+pub use hooks::{HookEvent, HookOutput};
+pub use lifecycle::{Lifecycle, LifecycleEvent, Phase};
+pub use platform::{WindowInfo, WindowManager};
+pub use state::StateManager;
@@ -0,0 +1,308 @@
+//! Agent lifecycle state machine
+//!
+//! A conversation moves through
+//!
+//! ```text
+//! idle -> working -> tool_pending -> approval_pending -> working -> ... -> finished
+//! ```
+//!
+//! driven by hook events. Each hook handler applies a `LifecycleEvent` to the
+//! conversation's persisted `Lifecycle` and only acts when the transition is
+//! valid; invalid transitions (a failsafe firing after the tool call already
+//! finished, a duplicate stop, ...) are reported and otherwise ignored.
+//! Nothing here touches windows, so the machine can be tested on its own.
+
+use crate::platform::WindowInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Where a conversation's agent is in its run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// No run seen yet (or its record expired)
+    #[default]
+    Idle,
+    /// The agent is working on a prompt
+    Working,
+    /// A shell command or MCP tool call has started and may be waiting for approval
+    ToolPending,
+    /// A tool call has been pending long enough that it needs the user's approval
+    ApprovalPending,
+    /// The agent stopped
+    Finished,
+}
+
+impl Phase {
+    /// The phase name as stored in the state file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Working => "working",
+            Self::ToolPending => "tool_pending",
+            Self::ApprovalPending => "approval_pending",
+            Self::Finished => "finished",
+        }
+    }
+
+    /// Status shown by the menu bar app for this phase
+    pub fn menu_bar_status(self) -> &'static str {
+        match self {
+            Self::Idle | Self::Finished => "idle",
+            Self::Working | Self::ToolPending => "working",
+            Self::ApprovalPending => "approval_needed",
+        }
+    }
+
+    /// The phase after `event`, or `None` if the event makes no sense in this phase
+    pub fn next(self, event: &LifecycleEvent) -> Option<Phase> {
+        use LifecycleEvent as E;
+        use Phase as P;
+
+        match (self, event) {
+            // A new prompt always starts a new run
+            (_, E::PromptSubmitted) => Some(P::Working),
+            // Tool calls may arrive without a recorded prompt if the record expired
+            (
+                P::Idle | P::Working | P::ToolPending | P::ApprovalPending,
+                E::ToolCallStarted { .. },
+            ) => Some(P::ToolPending),
+            (P::ToolPending | P::ApprovalPending, E::ToolCallFinished) => Some(P::Working),
+            (P::ToolPending, E::ApprovalTimedOut) => Some(P::ApprovalPending),
+            (P::Idle | P::Working | P::ToolPending | P::ApprovalPending, E::Retried) => {
+                Some(P::Working)
+            }
+            (P::Idle | P::Working | P::ToolPending | P::ApprovalPending, E::Stopped) => {
+                Some(P::Finished)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something that happened to a conversation's agent
+#[derive(Debug, Clone, PartialEq)]
+pub enum LifecycleEvent {
+    /// beforeSubmitPrompt
+    PromptSubmitted,
+    /// beforeShellExecution / beforeMCPExecution
+    ToolCallStarted {
+        /// Window to send the user back to once the call finishes
+        return_to: Option<WindowInfo>,
+        /// The shell command or MCP tool being called
+        command: Option<String>,
+        /// Seconds after which the failsafe counts the call as waiting for approval
+        failsafe_seconds: Option<u64>,
+    },
+    /// afterShellExecution / afterMCPExecution
+    ToolCallFinished,
+    /// The failsafe timer found the tool call still pending
+    ApprovalTimedOut,
+    /// The agent stopped with an error and was asked to try again
+    Retried,
+    /// The agent stopped for good
+    Stopped,
+}
+
+impl LifecycleEvent {
+    /// The event name used in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PromptSubmitted => "prompt_submitted",
+            Self::ToolCallStarted { .. } => "tool_call_started",
+            Self::ToolCallFinished => "tool_call_finished",
+            Self::ApprovalTimedOut => "approval_timed_out",
+            Self::Retried => "retried",
+            Self::Stopped => "stopped",
+        }
+    }
+}
+
+/// An event that is not valid in the conversation's current phase
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("ignoring {event} while {from}")]
+pub struct InvalidTransition {
+    /// Phase the conversation was in
+    pub from: Phase,
+    /// Name of the rejected event
+    pub event: &'static str,
+}
+
+/// Persisted lifecycle of one conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lifecycle {
+    /// Current phase
+    pub phase: Phase,
+    /// When the conversation entered this phase
+    pub since: DateTime<Utc>,
+    /// Window to send the user back to when the pending tool call finishes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_to: Option<WindowInfo>,
+    /// The shell command or MCP tool that is pending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Failsafe delay chosen for the pending call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failsafe_seconds: Option<u64>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            phase: Phase::Idle,
+            since: Utc::now(),
+            return_to: None,
+            command: None,
+            failsafe_seconds: None,
+        }
+    }
+}
+
+impl Lifecycle {
+    /// Apply an event, returning the new lifecycle
+    pub fn apply(&self, event: LifecycleEvent) -> Result<Lifecycle, InvalidTransition> {
+        let phase = self.phase.next(&event).ok_or(InvalidTransition {
+            from: self.phase,
+            event: event.name(),
+        })?;
+
+        let (return_to, command, failsafe_seconds) = match event {
+            LifecycleEvent::ToolCallStarted {
+                return_to,
+                command,
+                failsafe_seconds,
+            } => (return_to, command, failsafe_seconds),
+            // Waiting for approval still returns to the same window afterwards
+            LifecycleEvent::ApprovalTimedOut => (
+                self.return_to.clone(),
+                self.command.clone(),
+                self.failsafe_seconds,
+            ),
+            _ => (None, None, None),
+        };
+
+        Ok(Lifecycle {
+            phase,
+            since: Utc::now(),
+            return_to,
+            command,
+            failsafe_seconds,
+        })
+    }
+
+    /// Seconds spent in the current phase
+    pub fn elapsed_seconds(&self) -> i64 {
+        (Utc::now() - self.since).num_seconds()
+    }
+
+    /// Seconds since the pending tool call started. Once it waits for approval,
+    /// the phase started when the failsafe fired.
+    pub fn pending_seconds(&self) -> f64 {
+        let elapsed = (Utc::now() - self.since).num_milliseconds() as f64 / 1000.0;
+        match self.phase {
+            Phase::ApprovalPending => elapsed + self.failsafe_seconds.unwrap_or(0) as f64,
+            _ => elapsed,
+        }
+    }
+
+    /// Check if this record is stale (no transition for over an hour)
+    pub fn is_stale(&self) -> bool {
+        (Utc::now() - self.since).num_hours() >= 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window() -> WindowInfo {
+        WindowInfo {
+            pid: 200,
+            window_id: "200:1".to_string(),
+            app_name: "Google Chrome".to_string(),
+            title: "YouTube".to_string(),
+            class: None,
+        }
+    }
+
+    #[test]
+    fn full_run_with_approval() {
+        let lifecycle = Lifecycle::default()
+            .apply(LifecycleEvent::PromptSubmitted)
+            .and_then(|l| {
+                l.apply(LifecycleEvent::ToolCallStarted {
+                    return_to: Some(window()),
+                    command: Some("cargo test".to_string()),
+                    failsafe_seconds: Some(5),
+                })
+            })
+            .expect("start tool call");
+        assert_eq!(lifecycle.phase, Phase::ToolPending);
+
+        let waiting = lifecycle
+            .apply(LifecycleEvent::ApprovalTimedOut)
+            .expect("time out");
+        assert_eq!(waiting.phase, Phase::ApprovalPending);
+        assert_eq!(waiting.phase.menu_bar_status(), "approval_needed");
+        assert_eq!(waiting.return_to, Some(window()));
+        assert_eq!(waiting.command.as_deref(), Some("cargo test"));
+        assert!(waiting.pending_seconds() >= 5.0);
+
+        let working = waiting
+            .apply(LifecycleEvent::ToolCallFinished)
+            .expect("finish tool call");
+        assert_eq!(working.phase, Phase::Working);
+        assert_eq!(working.return_to, None);
+
+        let finished = working.apply(LifecycleEvent::Stopped).expect("stop");
+        assert_eq!(finished.phase, Phase::Finished);
+    }
+
+    #[test]
+    fn late_and_duplicate_events_are_invalid() {
+        let working = Lifecycle::default()
+            .apply(LifecycleEvent::PromptSubmitted)
+            .expect("submit");
+
+        // Failsafe firing after the tool call already finished
+        assert_eq!(
+            working.apply(LifecycleEvent::ApprovalTimedOut),
+            Err(InvalidTransition {
+                from: Phase::Working,
+                event: "approval_timed_out",
+            })
+        );
+        assert!(working.apply(LifecycleEvent::ToolCallFinished).is_err());
+
+        let finished = working.apply(LifecycleEvent::Stopped).expect("stop");
+        let err = finished.apply(LifecycleEvent::Stopped).unwrap_err();
+        assert_eq!(err.to_string(), "ignoring stopped while finished");
+        assert!(finished.apply(LifecycleEvent::Retried).is_err());
+    }
+
+    #[test]
+    fn expired_records_still_accept_tool_calls_and_stops() {
+        let idle = Lifecycle::default();
+        assert_eq!(
+            idle.apply(LifecycleEvent::ToolCallStarted {
+                return_to: None,
+                command: None,
+                failsafe_seconds: None,
+            })
+            .map(|l| l.phase),
+            Ok(Phase::ToolPending)
+        );
+        assert_eq!(
+            idle.apply(LifecycleEvent::Stopped).map(|l| l.phase),
+            Ok(Phase::Finished)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Structured debug log
+//!
+//! Hook invocations, the decisions the engine makes (with their reasons), focus
+//! attempts and the failures Recursor otherwise shrugs off are written as JSON
+//! lines to `~/.cursor/recursor_log.jsonl`. Records below `logging.level` are
+//! dropped; the file rotates like the audit log (see `rotating`).
+//! `recursor logs` reads it.
+
+use crate::config::LoggingConfig;
+use crate::rotating::RotatingFile;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How important a record is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    /// Something failed
+    Error,
+    /// Something went wrong but Recursor carried on
+    Warn,
+    /// Hook invocations and decisions
+    Info,
+    /// Every window operation
+    Debug,
+}
+
+impl Level {
+    /// Parse a level name such as `warn`
+    pub fn parse(value: &str) -> Result<Self> {
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "error" => Self::Error,
+            "warn" | "warning" => Self::Warn,
+            "info" => Self::Info,
+            "debug" => Self::Debug,
+            _ => bail!(
+                "Unknown log level {:?} (use error, warn, info or debug)",
+                value
+            ),
+        })
+    }
+
+    /// Upper-case name for text output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// When it was logged
+    pub at: DateTime<Utc>,
+    /// How important it is
+    pub level: Level,
+    /// Process that logged it, to tell hooks and background processes apart
+    pub pid: u32,
+    /// Conversation it is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    /// What it is about: `hook`, `decision`, `focus`, ...
+    pub kind: String,
+    /// What happened
+    pub message: String,
+}
+
+/// Writes log records
+#[derive(Debug, Clone)]
+pub struct Logger {
+    file: RotatingFile,
+    level: Level,
+}
+
+impl Logger {
+    /// Open the log at the default location
+    pub fn new(config: &LoggingConfig) -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self::with_path(
+            home.join(".cursor").join("recursor_log.jsonl"),
+            config,
+        ))
+    }
+
+    /// Open the log at a specific path
+    pub fn with_path(path: PathBuf, config: &LoggingConfig) -> Self {
+        Self {
+            file: RotatingFile::new(path, config.max_file_bytes, config.keep_files),
+            level: config.level,
+        }
+    }
+
+    /// The current log file
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Whether records of this level are written
+    pub fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
+
+    /// Write a record; logging is best-effort and never fails the caller
+    pub fn log(&self, level: Level, conversation_id: Option<&str>, kind: &str, message: &str) {
+        if !self.enabled(level) {
+            return;
+        }
+        let record = LogRecord {
+            at: Utc::now(),
+            level,
+            pid: std::process::id(),
+            conversation_id: conversation_id.map(str::to_string),
+            kind: kind.to_string(),
+            message: message.to_string(),
+        };
+        if let Ok(line) = serde_json::to_string(&record) {
+            let _ = self.file.append(&line);
+        }
+    }
+
+    /// Every record in the log and its rotated files, oldest first, skipping
+    /// lines that fail to parse
+    pub fn records(&self) -> Result<Vec<LogRecord>> {
+        Ok(self
+            .file
+            .lines()?
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::unique_test_dir;
+    use std::fs;
+
+    #[test]
+    fn records_below_the_level_are_dropped_and_old_files_rotated() {
+        let path = unique_test_dir("log_levels").join("recursor_log.jsonl");
+        let config = LoggingConfig {
+            level: Level::Info,
+            max_file_bytes: 1000,
+            keep_files: 1,
+        };
+        let logger = Logger::with_path(path.clone(), &config);
+
+        logger.log(Level::Debug, Some("c1"), "focus", "focused Chrome");
+        logger.log(Level::Warn, Some("c1"), "focus", "failed to focus Chrome");
+        let records = logger.records().expect("read");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, Level::Warn);
+        assert_eq!(records[0].conversation_id.as_deref(), Some("c1"));
+
+        for index in 0..40 {
+            logger.log(Level::Info, None, "hook", &format!("hook {}", index));
+        }
+        let records = logger.records().expect("read");
+        assert!(records.len() < 41);
+        assert_eq!(records.last().map(|r| r.message.as_str()), Some("hook 39"));
+
+        assert_eq!(Level::parse("WARNING").expect("parse"), Level::Warn);
+        assert!(Level::parse("loud").is_err());
+
+        let _ = fs::remove_dir_all(path.parent().expect("parent"));
+    }
+}
@@ -11,64 +11,73 @@
 //! # Restore focus to Cursor (called by stop hook)
 //! recursor restore
 //!
+//! # Handle any hook event, dispatching on hook_event_name
+//! recursor hook
+//!
 //! # Check current status
 //! recursor status
 //!
+//! # Replay a captured hook session
+//! recursor replay ~/.cursor/recursor_captures/<conversation>.jsonl
+//!
+//! # Register Recursor in ~/.cursor/hooks.json (keeping other hooks)
+//! recursor install-hooks
+//!
 //! # Trigger permission prompts (macOS)
 //! recursor permissions
+//!
+//! # Diagnose setup problems
+//! recursor doctor
 //! ```
 
-mod hooks;
-mod platform;
-mod state;
-
-use anyhow::Result;
-use chrono::Utc;
+use anyhow::{Context, Result};
+use chrono::{Local, Utc};
 use clap::{Parser, Subcommand};
-use platform::{create_window_manager, WindowManager};
-use state::StateManager;
-use std::path::PathBuf;
-use std::time::Duration;
-
-const SHELL_FAILSAFE_DELAY_SECONDS: u64 = 5;
-
-/// Check if Recursor is enabled by reading the config file
-/// Returns true if enabled (default), false if explicitly disabled
-fn is_enabled() -> bool {
-    let config_path = get_config_path();
-
-    if !config_path.exists() {
-        return true; // Default to enabled
-    }
-
-    match std::fs::read_to_string(&config_path) {
-        Ok(contents) => {
-            // Parse JSON and check "enabled" field
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&contents) {
-                return json
-                    .get("enabled")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
-            }
-            true // Default to enabled if parse fails
-        }
-        Err(_) => true, // Default to enabled if read fails
-    }
-}
-
-/// Get the path to the config file
-fn get_config_path() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".cursor")
-        .join("recursor_config.json")
-}
+use recursor::actions::{ActionPayload, ActionRunner};
+use recursor::audit::{AuditLog, AuditQuery, Redactor};
+use recursor::bench::{self, Stage, Timing};
+use recursor::capture::{self, CaptureDir, CaptureEntry};
+use recursor::config::{BackendConfig, BackendKind, Config, DndMode};
+use recursor::dnd::DoNotDisturb;
+use recursor::engine::{self, Engine};
+use recursor::history::History;
+use recursor::logging::{Level, LogRecord, Logger};
+use recursor::platform::dry_run::DryRunWindowManager;
+use recursor::platform::recording::describe_window;
+use recursor::platform::recording::{CallLog, PlaybackWindowManager, RecordingWindowManager};
+use recursor::platform::{create_backend, WindowManager};
+use recursor::rules::RuleSet;
+use recursor::state::{Explanation, StateManager};
+use recursor::stats::{self, Stats};
+use recursor::worker::{HookJob, JobQueue, LatencyLog};
+use recursor::{doctor, hooks, install};
+use regex::RegexBuilder;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Set while running with `--dry-run`, so background processes hold back too
+const DRY_RUN_ENV: &str = "RECURSOR_DRY_RUN";
+
+/// How long a deferred pull waits for do-not-disturb to end
+const DEFERRED_PULL_LIMIT: Duration = Duration::from_secs(60 * 60);
+
+/// How long catch-up waits for the user to come back
+const CATCH_UP_LIMIT: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long the pull queue waits for the user, when queued pulls go stale
+const PULL_QUEUE_LIMIT: Duration = Duration::from_secs(60 * 60);
 
 /// Recursor - The "Bounce Back" Utility for Cursor AI Agents
 #[derive(Parser)]
 #[command(name = "recursor")]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Don't change anything: decide and log what would happen, but leave focus,
+    /// media and hooks files alone (also the `dry_run` config setting)
+    #[arg(long, global = true)]
+    dry_run: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -91,12 +100,100 @@ enum Commands {
     /// Called after shell execution - switch back to video and resume
     AfterShell,
 
+    /// Handle any hook event read from stdin, dispatching on hook_event_name
+    Hook,
+
     /// Show current saved state
-    Status,
+    Status {
+        /// Also evaluate the per-app rules against the current windows
+        #[arg(long)]
+        explain: bool,
+    },
+
+    /// Show run times, approvals, parked time and focus changes per day,
+    /// workspace and model, and predicted vs. actual run times
+    Stats {
+        /// Only count events since then: a duration (7d, 12h) or a date (2026-01-31)
+        #[arg(long)]
+        since: Option<String>,
+        /// Print the statistics as JSON
+        #[arg(long, conflicts_with = "csv")]
+        json: bool,
+        /// Print the totals as CSV
+        #[arg(long)]
+        csv: bool,
+    },
+
+    /// Search the audit log of shell commands agents ran
+    History {
+        /// Regex to match against the command and its output (case-insensitive)
+        pattern: Option<String>,
+        /// Only commands of this conversation
+        #[arg(long)]
+        conversation: Option<String>,
+        /// Only commands in workspaces whose path contains this
+        #[arg(long)]
+        workspace: Option<String>,
+        /// Only commands since then: a duration (7d, 12h) or a date (2026-01-31)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only commands that waited for approval
+        #[arg(long)]
+        needed_approval: bool,
+        /// Show the most recent N matches
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+        /// Also print each command's output
+        #[arg(long)]
+        output: bool,
+        /// Print the matches as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the debug log: hook invocations, decisions and focus attempts
+    Logs {
+        /// Keep printing new records as they are written
+        #[arg(long, short)]
+        follow: bool,
+        /// Only records about this conversation
+        #[arg(long)]
+        conversation: Option<String>,
+        /// Only records at least this important: error, warn, info or debug
+        #[arg(long)]
+        level: Option<String>,
+        /// Show the most recent N records
+        #[arg(long, default_value_t = 50)]
+        lines: usize,
+        /// Print the records as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Trigger permission prompts (macOS) by attempting window operations
     Permissions,
 
+    /// Check the whole setup (display server, hooks, files, permissions) and suggest fixes
+    Doctor {
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Time each hook against the null (or real) window manager and show
+    /// where the time goes
+    Bench {
+        /// Runs per hook
+        #[arg(long, short = 'n', default_value_t = 50)]
+        iterations: usize,
+        /// Window manager to run against; `platform` moves real windows
+        #[arg(long, default_value = "null", value_parser = ["null", "platform"])]
+        backend: String,
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Clear saved state
     Clear,
 
@@ -108,10 +205,76 @@ enum Commands {
         #[arg(long, default_value_t = 0)]
         delay_seconds: u64,
     },
+
+    /// Bounce back to the saved window if the agent is still working (started by
+    /// the submit hook when `bounce.delay_seconds` is set)
+    #[command(hide = true)]
+    Bounce {
+        /// The conversation whose prompt was submitted
+        conversation_id: String,
+        /// Delay before bouncing
+        #[arg(long, default_value_t = 0)]
+        delay_seconds: u64,
+    },
+
+    /// Pull the user to Cursor once do-not-disturb ends (started by the stop hook)
+    #[command(hide = true)]
+    DeferredPull {
+        /// The conversation whose pull was deferred
+        conversation_id: String,
+    },
+
+    /// Catch the user up once they're back at the keyboard (started by hooks)
+    #[command(hide = true)]
+    CatchUp,
+
+    /// Pull the user to each queued conversation in turn (started by hooks)
+    #[command(hide = true)]
+    PullQueue,
+
+    /// Handle the hook events queued by hooks that already answered Cursor
+    #[command(hide = true)]
+    HookWorker,
+
+    /// Run the configured actions for a payload read from stdin (started by hooks)
+    #[command(hide = true)]
+    RunActions,
+
+    /// Replay a captured hook session against a recording window manager
+    Replay {
+        /// Capture file (from ~/.cursor/recursor_captures)
+        file: PathBuf,
+    },
+
+    /// Add Recursor to Cursor's hooks.json, keeping any other hooks
+    InstallHooks {
+        /// Edit .cursor/hooks.json in the current project instead of ~/.cursor/hooks.json
+        #[arg(long)]
+        project: bool,
+        /// Recursor binary to register (defaults to this executable)
+        #[arg(long)]
+        binary: Option<PathBuf>,
+    },
+
+    /// Remove Recursor from Cursor's hooks.json, keeping any other hooks
+    UninstallHooks {
+        /// Edit .cursor/hooks.json in the current project instead of ~/.cursor/hooks.json
+        #[arg(long)]
+        project: bool,
+    },
 }
 
 fn main() {
     if let Err(e) = run() {
+        if let Ok(logger) = Logger::new(&Config::load().logging) {
+            let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+            logger.log(
+                Level::Error,
+                None,
+                "command",
+                &format!("`recursor {}` failed: {:#}", command, e),
+            );
+        }
         eprintln!("Error: {:#}", e);
         std::process::exit(1);
     }
@@ -119,412 +282,687 @@ fn main() {
 
 fn run() -> Result<()> {
     let cli = Cli::parse();
+    if cli.dry_run {
+        // Background processes spawned from here inherit the environment
+        std::env::set_var(DRY_RUN_ENV, "1");
+    }
 
     match cli.command {
-        Commands::Save { no_focus } => cmd_save(no_focus),
-        Commands::Restore => cmd_restore(),
-        Commands::BeforeShell => cmd_before_shell(),
-        Commands::AfterShell => cmd_after_shell(),
-        Commands::Status => cmd_status(),
+        Commands::Save { no_focus: false } => run_hook("save"),
+        Commands::Save { no_focus: true } => run_hook("save --no-focus"),
+        Commands::Restore => run_hook("restore"),
+        Commands::BeforeShell => run_hook("before-shell"),
+        Commands::AfterShell => run_hook("after-shell"),
+        Commands::Hook => run_hook("hook"),
+        Commands::Status { explain } => cmd_status(explain),
+        Commands::Stats { since, json, csv } => cmd_stats(since.as_deref(), json, csv),
+        Commands::History {
+            pattern,
+            conversation,
+            workspace,
+            since,
+            needed_approval,
+            limit,
+            output,
+            json,
+        } => {
+            let query = AuditQuery {
+                pattern: pattern
+                    .map(|p| RegexBuilder::new(&p).case_insensitive(true).build())
+                    .transpose()
+                    .context("Invalid search pattern")?,
+                conversation_id: conversation,
+                workspace,
+                since: since
+                    .map(|s| stats::parse_since(&s, Utc::now()))
+                    .transpose()?,
+                needed_approval,
+            };
+            cmd_history(&query, limit, output, json)
+        }
+        Commands::Logs {
+            follow,
+            conversation,
+            level,
+            lines,
+            json,
+        } => {
+            let level = level.as_deref().map(Level::parse).transpose()?;
+            cmd_logs(conversation.as_deref(), level, lines, follow, json)
+        }
         Commands::Permissions => cmd_permissions(),
+        Commands::Doctor { json } => cmd_doctor(json),
+        Commands::Bench {
+            iterations,
+            backend,
+            json,
+        } => cmd_bench(iterations, &backend, json),
         Commands::Clear => cmd_clear(),
         Commands::CheckIdle {
             conversation_id,
             delay_seconds,
         } => cmd_check_idle(&conversation_id, delay_seconds),
+        Commands::Bounce {
+            conversation_id,
+            delay_seconds,
+        } => cmd_bounce(&conversation_id, delay_seconds),
+        Commands::DeferredPull { conversation_id } => cmd_deferred_pull(&conversation_id),
+        Commands::CatchUp => cmd_catch_up(),
+        Commands::PullQueue => cmd_pull_queue(),
+        Commands::HookWorker => cmd_hook_worker(),
+        Commands::RunActions => cmd_run_actions(),
+        Commands::Replay { file } => cmd_replay(&file),
+        Commands::InstallHooks { project, binary } => {
+            cmd_install_hooks(project, cli.dry_run, binary)
+        }
+        Commands::UninstallHooks { project } => cmd_uninstall_hooks(project, cli.dry_run),
     }
 }
 
-/// Save command - called by beforeSubmitPrompt hook
-fn cmd_save(no_focus: bool) -> Result<()> {
-    // Check if Recursor is enabled
-    if !is_enabled() {
-        // Just output allow response without any window management
-        hooks::write_output(&hooks::BeforeSubmitPromptOutput::allow())?;
-        return Ok(());
-    }
-
-    let wm = create_window_manager();
-    let state_mgr = StateManager::new()?;
-
-    // Read hook input from stdin (if available)
-    let input: Option<hooks::BeforeSubmitPromptInput> = hooks::try_read_input();
-
-    // Get conversation_id from hook input, or use a default
-    let conversation_id = input
-        .as_ref()
-        .and_then(|i| i.common.conversation_id.clone())
-        .unwrap_or_else(|| "default".to_string());
-
-    // Get the current active window (this is Cursor, since user just submitted prompt)
-    let cursor_window = wm.get_active_window().ok();
+/// How a hook subcommand reads its input
+struct HookCommand {
+    /// Event the subcommand always handles (None: dispatch on hook_event_name)
+    event_name: Option<&'static str>,
+    /// Whether beforeSubmitPrompt switches focus back to the saved window
+    bounce_on_submit: bool,
+}
 
-    // Get the previous app the user was using (before they switched to Cursor)
-    let previous_window = wm.get_previous_window().ok();
+/// Look up a hook subcommand by its command line (as recorded in captures)
+fn hook_command(command: &str) -> Option<HookCommand> {
+    let (event_name, bounce_on_submit) = match command {
+        "hook" => (None, true),
+        "save" => (Some("beforeSubmitPrompt"), true),
+        "save --no-focus" => (Some("beforeSubmitPrompt"), false),
+        "restore" => (Some("stop"), true),
+        "before-shell" => (Some("beforeShellExecution"), true),
+        "after-shell" => (Some("afterShellExecution"), true),
+        _ => return None,
+    };
+    Some(HookCommand {
+        event_name,
+        bounce_on_submit,
+    })
+}
 
-    // Save state: remember the window to return to after commands are approved.
-    // Prefer the previous app, but fall back to the current Cursor window if needed.
-    let window_to_save = select_window_to_save(cursor_window.clone(), previous_window.clone());
+/// Parse a hook payload for a subcommand.
+/// `hook` requires a valid payload; the per-event subcommands fall back to an
+/// empty input, as they always have. Returns the event (if any) and the parse error.
+fn parse_hook_input(
+    command: &HookCommand,
+    raw: &str,
+) -> (Option<hooks::HookEvent>, Option<anyhow::Error>) {
+    let parsed = match command.event_name {
+        Some(name) => hooks::HookEvent::parse_as(raw, name),
+        None => hooks::HookEvent::parse(raw),
+    };
+    match (parsed, command.event_name) {
+        (Ok(event), _) => (Some(event), None),
+        (Err(e), Some(name)) => (Some(hooks::HookEvent::empty(name)), Some(e)),
+        (Err(e), None) => (None, Some(e)),
+    }
+}
 
-    if let Some(ref w) = window_to_save {
-        state_mgr.save_conversation(&conversation_id, w.clone(), cursor_window.clone())?;
+/// Load the config file, with the backend's environment overrides applied.
+/// Settings that had to be ignored are logged.
+fn load_config() -> Result<Config> {
+    let (mut config, errors) = Config::load_with_errors();
+    if !errors.is_empty() {
+        if let Ok(logger) = Logger::new(&config.logging) {
+            for error in &errors {
+                logger.log(
+                    Level::Warn,
+                    None,
+                    "config",
+                    &format!("ignoring config setting {}", error),
+                );
+            }
+        }
     }
+    config.backend = config.backend.resolve()?;
+    config.dry_run |= std::env::var_os(DRY_RUN_ENV).is_some();
+    Ok(config)
+}
 
-    // Update menu bar status with rich information
-    if let Some(ref w) = window_to_save {
-        let media_playing = if w.app_name == "Google Chrome" {
-            Some(wm.is_youtube_playing())
-        } else {
-            None
-        };
-        wm.update_menu_bar_status_full(
-            "working",
-            Some("Agent working on task..."),
-            Some(&w.app_name),
-            Some(&w.title),
-            media_playing,
-        );
+/// Create the configured window manager, wrapped in a recorder when capturing
+fn window_manager_for(config: &Config) -> Result<(Box<dyn WindowManager>, Option<CallLog>)> {
+    let wm = create_backend(&config.backend)?;
+    if config.capture.enabled {
+        let (recorder, log) = RecordingWindowManager::new(wm);
+        Ok((Box::new(recorder), Some(log)))
+    } else {
+        Ok((wm, None))
     }
+}
 
-    // Switch focus back to the previous app (unless explicitly disabled).
-    if !no_focus {
-        if let Some(ref prev) = previous_window {
-            // Small delay to let the prompt submission complete.
-            std::thread::sleep(Duration::from_millis(50));
+/// Build the engine used by the CLI
+fn build_engine(wm: Box<dyn WindowManager>, config: Config) -> Result<Engine> {
+    // Simulated backends have nothing for a failsafe to act on later
+    let failsafe_timers = config.backend.drives_real_windows();
+    let audit = config.audit.clone();
+    let logging = config.logging.clone();
+    let (wm, suppressed): (Box<dyn WindowManager>, _) = if config.dry_run {
+        let (dry_run, suppressed) = DryRunWindowManager::new(wm);
+        (Box::new(dry_run), Some(suppressed))
+    } else {
+        (wm, None)
+    };
+    // Background work and actions run as subcommands of this binary
+    let binary = std::env::current_exe().unwrap_or_else(|_| "recursor".into());
+    let mut engine = Engine::new(wm, StateManager::new()?, config)
+        .with_failsafe_timers(failsafe_timers)
+        .with_recursor_binary(binary);
+    if let Some(suppressed) = suppressed {
+        engine = engine.with_dry_run(suppressed);
+    }
+    if let Ok(history) = History::new() {
+        engine = engine.with_history(history);
+    }
+    if audit.enabled {
+        if let Ok(log) = AuditLog::new(&audit) {
+            engine = engine.with_audit_log(log);
+        }
+    }
+    if let Ok(logger) = Logger::new(&logging) {
+        engine = engine.with_logger(logger);
+    }
+    Ok(engine)
+}
 
-            // Focus the previous window first.
-            let _ = wm.focus_window(prev);
+/// Append a capture entry; capturing is best-effort and never fails the hook
+fn write_capture(config: &Config, conversation_id: &str, entry: CaptureEntry) {
+    if let Ok(dir) = CaptureDir::new(config.capture.max_sessions) {
+        let _ = dir.append(conversation_id, &entry);
+    }
+}
 
-            // If it's Chrome, resume only if a YouTube tab is paused.
-            if prev.app_name == "Google Chrome" {
-                std::thread::sleep(Duration::from_millis(150));
-                let _ = wm.resume_youtube(&prev.title);
+/// Run one hook invocation: read stdin once, parse it, and write the output
+/// type Cursor expects for it. With the fast path the answer is written first
+/// and the event is handled afterwards by `hook-worker`; otherwise the engine
+/// handles it before answering.
+fn run_hook(command_line: &str) -> Result<()> {
+    let started = Instant::now();
+    let command = hook_command(command_line).context("Unknown hook command")?;
+    let config = load_config()?;
+    let raw = hooks::read_stdin().unwrap_or_default();
+
+    // Fast path: answer from the config, then leave the window work to the worker
+    let (event, _) = parse_hook_input(&command, &raw);
+    let event_name = event.as_ref().and_then(|e| e.name()).map(str::to_string);
+    if let (true, Some(event)) = (config.fast_path.enabled, event) {
+        // If the answer can't be worked out or the event can't be queued, it is
+        // handled before answering instead
+        let answered = JobQueue::new().and_then(|queue| {
+            // A stop's answer depends on the lifecycle, so the events queued
+            // before it are handled first
+            let _lock = match event {
+                hooks::HookEvent::Stop(_) => {
+                    let lock = queue.lock_worker()?;
+                    run_queued_jobs(&queue, &config)?;
+                    Some(lock)
+                }
+                _ => None,
+            };
+            let answer = engine::respond(&config, &StateManager::new()?, &event)?;
+            queue.push(&HookJob {
+                at: Utc::now(),
+                command: command_line.to_string(),
+                raw: raw.clone(),
+                answer: answer.to_json(),
+            })?;
+            Ok(answer)
+        });
+        if let Ok(answer) = answered {
+            hooks::write_hook_output(&answer)?;
+            record_latency(event_name.as_deref(), started);
+            // Simulated backends are driven synchronously, so their runs finish with the hook
+            if config.backend.drives_real_windows() {
+                spawn_hook_worker();
+                return Ok(());
             }
+            return cmd_hook_worker();
         }
     }
 
-    // Output success response for the hook
-    hooks::write_output(&hooks::BeforeSubmitPromptOutput::allow())?;
-
+    let output = handle_hook(command_line, config, raw, started, Answer::Handled)?;
+    hooks::write_hook_output(&output)?;
+    record_latency(event_name.as_deref(), started);
     Ok(())
 }
 
-/// Restore command - called by stop hook when agent finishes
-/// ALWAYS brings user to Cursor so they can see the results
-fn cmd_restore() -> Result<()> {
-    // Check if Recursor is enabled
-    if !is_enabled() {
-        // Just output empty response without any window management
-        hooks::write_output(&hooks::StopOutput::empty())?;
-        return Ok(());
-    }
-
-    let wm = create_window_manager();
-    let state_mgr = StateManager::new()?;
-
-    // Read hook input from stdin (if available)
-    let input: Option<hooks::StopInput> = hooks::try_read_input();
+/// What Cursor was told about a hook
+enum Answer {
+    /// Whatever the engine decides; the hook waits for it
+    Handled,
+    /// Sent by the fast path before the hook was handled
+    Sent(Option<serde_json::Value>),
+}
 
-    // Get conversation_id from hook input
-    let conversation_id = input
+/// Let the engine handle a hook, logging and capturing it like any other
+fn handle_hook(
+    command_line: &str,
+    config: Config,
+    raw: String,
+    started: Instant,
+    answer: Answer,
+) -> Result<hooks::HookOutput> {
+    let command = hook_command(command_line).context("Unknown hook command")?;
+    let (event, parse_error) = parse_hook_input(&command, &raw);
+    let event_name = event.as_ref().and_then(|e| e.name()).map(str::to_string);
+    let parsed = event.as_ref().map(|e| e.to_json());
+    let conversation_id = event
         .as_ref()
-        .and_then(|i| i.common.conversation_id.clone())
+        .and_then(|e| e.common())
+        .map(|c| c.conversation_id_or_default())
         .unwrap_or_else(|| "default".to_string());
 
-    // Load saved state BEFORE clearing - we need the specific Cursor window info
-    let saved_state = state_mgr.load_conversation(&conversation_id)?;
-
-    // Pause YouTube if the saved previous window was Chrome with YouTube
-    // We use the saved state (not get_active_window) because Cursor may already
-    // have focus by the time this hook fires
-    if let Some(ref state) = saved_state {
-        if state.saved_window.app_name == "Google Chrome" && state.saved_window.pid > 0 {
-            let script = format!(
-                r#"
-                tell application "System Events"
-                    set chromeProc to first application process whose unix id is {}
-                    set allWins to every window of chromeProc
-                    set winCount to count of allWins
-                    repeat with i from 1 to winCount
-                        try
-                            set winName to name of item i of allWins
-                            if winName contains "YouTube" and winName contains "Audio playing" then
-                                set frontmost of chromeProc to true
-                                delay 0.15
-                                perform action "AXRaise" of (item i of allWins)
-                                delay 0.2
-                                keystroke "k"
-                                return "paused"
-                            end if
-                        end try
-                    end repeat
-                    return "not_playing"
-                end tell
-                "#,
-                state.saved_window.pid
-            );
-            let _ = std::process::Command::new("osascript")
-                .args(["-e", &script])
-                .output();
-        }
-    }
+    let (wm, call_log) = window_manager_for(&config)?;
+    let capture_config = config.clone();
+    let logger = Logger::new(&config.logging).ok();
+    let result = match event {
+        Some(event) => build_engine(wm, config).and_then(|engine| {
+            engine
+                .with_bounce_on_submit(command.bounce_on_submit)
+                .handle(event)
+        }),
+        None => Err(parse_error
+            .as_ref()
+            .map(|e| anyhow::anyhow!("{:#}", e))
+            .unwrap_or_else(|| anyhow::anyhow!("No hook input"))),
+    };
 
-    // Small delay then bring user to the CORRECT Cursor window.
-    // When multiple Cursor windows are open, we must focus the specific one
-    // where the prompt was submitted, not just any Cursor window.
-    std::thread::sleep(Duration::from_millis(100));
-    if let Some(ref state) = saved_state {
-        if let Some(ref cursor_win) = state.cursor_window {
-            let _ = wm.focus_cursor_window(cursor_win);
-        } else {
-            let _ = wm.focus_cursor();
+    if let Some(ref logger) = logger {
+        let what = format!(
+            "{} via `recursor {}`",
+            event_name.as_deref().unwrap_or("unknown event"),
+            command_line
+        );
+        match result {
+            Ok(_) => logger.log(
+                Level::Info,
+                Some(&conversation_id),
+                "hook",
+                &format!("{} handled in {}ms", what, started.elapsed().as_millis()),
+            ),
+            Err(ref e) => logger.log(
+                Level::Error,
+                Some(&conversation_id),
+                "hook",
+                &format!("{} failed: {:#}", what, e),
+            ),
+        }
+        if logger.enabled(Level::Debug) {
+            let payload = Redactor::new(&capture_config.audit.redact).redact(&raw);
+            logger.log(Level::Debug, Some(&conversation_id), "hook", &payload);
         }
-    } else {
-        let _ = wm.focus_cursor();
     }
 
-    // Update menu bar status - agent finished, now idle
-    wm.update_menu_bar_status_full("idle", Some("Agent finished"), None, None, None);
-
-    // Clear the saved state for this conversation
-    state_mgr.clear_conversation(&conversation_id)?;
-
-    // Output response for the hook
-    hooks::write_output(&hooks::StopOutput::empty())?;
-
-    Ok(())
-}
-
-/// BeforeShell command - called before every shell execution.
-/// Instead of immediately bringing user to Cursor, we save state and spawn a 5-second
-/// failsafe timer. If the command is still pending after 5 seconds, the failsafe
-/// brings the user to Cursor.
-fn cmd_before_shell() -> Result<()> {
-    // Check if Recursor is enabled
-    if !is_enabled() {
-        // Just allow the command without any window management
-        let output = serde_json::json!({ "permission": "allow" });
-        println!("{}", output);
-        return Ok(());
+    if let Some(log) = call_log {
+        write_capture(
+            &capture_config,
+            &conversation_id,
+            CaptureEntry {
+                at: Utc::now(),
+                command: command_line.to_string(),
+                raw,
+                event: event_name,
+                parsed,
+                parse_error: parse_error.map(|e| format!("{:#}", e)),
+                response: match answer {
+                    Answer::Sent(json) => json,
+                    Answer::Handled => result.as_ref().ok().and_then(|o| o.to_json()),
+                },
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+                calls: log.take(),
+            },
+        );
     }
 
-    let state_mgr = StateManager::new()?;
-    let wm = create_window_manager();
-
-    // Read hook input to get the command
-    let input: Option<hooks::BeforeShellInput> = hooks::try_read_input();
-
-    let conversation_id = input
-        .as_ref()
-        .and_then(|i| i.common.conversation_id.clone())
-        .unwrap_or_else(|| "default".to_string());
+    result
+}
 
-    // Get current window and determine the secondary window to track
-    let current_window = wm.get_active_window().ok();
+/// HookWorker command - handle the events queued by hooks that answered Cursor
+/// already, oldest first, until the queue is empty. Workers take turns, so a
+/// worker started while another is running handles whatever is left after it.
+fn cmd_hook_worker() -> Result<()> {
+    let queue = JobQueue::new()?;
+    // Loaded before any job is taken, so a bad config can't lose queued events
+    let config = load_config()?;
+    let _lock = queue.lock_worker()?;
+    run_queued_jobs(&queue, &config)
+}
 
-    // Determine the secondary window (the one to return to after command completes)
-    // If user is in Cursor, get the previous window they were in
-    // If user is NOT in Cursor, use current window as secondary
-    let secondary_window = if let Some(ref current) = current_window {
-        if current.is_cursor() {
-            // User is in Cursor - get the previous window they came from
-            wm.get_previous_window().ok()
-        } else {
-            // User is in another app - that's our secondary window
-            Some(current.clone())
+/// Handle queued events until the queue is empty; the caller holds the worker lock
+fn run_queued_jobs(queue: &JobQueue, config: &Config) -> Result<()> {
+    let logger = Logger::new(&config.logging).ok();
+    loop {
+        let jobs = queue.take_all()?;
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        for job in jobs {
+            let answered = job.answer;
+            // Failures are logged by handle_hook; the rest of the queue still runs
+            let Ok(output) = handle_hook(
+                &job.command,
+                config.clone(),
+                job.raw,
+                Instant::now(),
+                Answer::Sent(answered.clone()),
+            ) else {
+                continue;
+            };
+            let decided = output.to_json();
+            if decided != answered {
+                if let Some(ref logger) = logger {
+                    logger.log(
+                        Level::Warn,
+                        None,
+                        "hook",
+                        &format!(
+                            "`recursor {}` answered {} but the engine decided on {}",
+                            job.command,
+                            describe_output(answered.as_ref()),
+                            describe_output(decided.as_ref())
+                        ),
+                    );
+                }
+            }
         }
-    } else {
-        None
-    };
-
-    // Always save state and spawn failsafe timer
-    // This fixes the back-to-back command issue where command 2 fires while user is still in Cursor
-    if let Some(ref secondary) = secondary_window {
-        let shell_conv_id = format!("{}_shell", conversation_id);
-        state_mgr.save_conversation(&shell_conv_id, secondary.clone(), None)?;
-
-        // Spawn a 5-second failsafe timer
-        // If the command is still pending after 5 seconds, check-idle will bring user to Cursor
-        spawn_failsafe_timer(&conversation_id);
     }
+}
 
-    // Always allow the command to proceed
-    let output = serde_json::json!({ "permission": "allow" });
-    println!("{}", output);
-    Ok(())
+/// A hook response for the log
+fn describe_output(json: Option<&serde_json::Value>) -> String {
+    json.map(|j| j.to_string())
+        .unwrap_or_else(|| "nothing".to_string())
 }
 
-/// Spawn a background process that will check if the shell command is still pending after 5 seconds
-fn spawn_failsafe_timer(conversation_id: &str) {
+/// Start a detached `recursor hook-worker`, which outlives the hook
+fn spawn_hook_worker() {
     use std::process::{Command, Stdio};
 
-    // Get the path to the recursor binary
     let recursor_path = std::env::current_exe().unwrap_or_else(|_| "recursor".into());
-
-    // Spawn a detached check-idle process that sleeps internally before checking.
-    // This avoids shell interpolation issues for conversation IDs and binary paths.
     let _ = Command::new(recursor_path)
-        .arg("check-idle")
-        .arg("--delay-seconds")
-        .arg(SHELL_FAILSAFE_DELAY_SECONDS.to_string())
-        .arg("--")
-        .arg(conversation_id)
+        .arg("hook-worker")
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn();
 }
 
-/// AfterShell command - called after a shell command has run.
-/// Switch user back to where they were (e.g., YouTube) if we brought them to Cursor.
-fn cmd_after_shell() -> Result<()> {
-    // Check if Recursor is enabled
-    if !is_enabled() {
-        // No window management when disabled
-        return Ok(());
+/// Record how long a hook took to answer; best-effort like the other logs
+fn record_latency(event: Option<&str>, started: Instant) {
+    if let Ok(log) = LatencyLog::new() {
+        let _ = log.record(
+            event.unwrap_or("unknown"),
+            started.elapsed().as_millis() as u64,
+        );
     }
+}
 
-    let wm = create_window_manager();
-    let state_mgr = StateManager::new()?;
+/// CheckIdle command - failsafe that brings user to Cursor if shell command is still pending
+/// Called by the background timer spawned in beforeShellExecution once the failsafe delay has passed
+fn cmd_check_idle(conversation_id: &str, delay_seconds: u64) -> Result<()> {
+    let config = load_config()?;
 
-    // Read raw stdin and parse
-    use std::io::{self, BufRead};
-    let stdin = io::stdin();
-    let mut raw_input = String::new();
-    for l in stdin.lock().lines().map_while(Result::ok) {
-        raw_input.push_str(&l);
-        raw_input.push('\n');
+    // Don't pull user to Cursor when disabled
+    if !config.enabled {
+        return Ok(());
     }
 
-    let input: Option<hooks::AfterShellInput> = serde_json::from_str(&raw_input).ok();
-
-    let conversation_id = input
-        .as_ref()
-        .and_then(|i| i.common.conversation_id.clone())
-        .unwrap_or_else(|| "default".to_string());
-
-    // Check for shell-specific saved state
-    let shell_conv_id = format!("{}_shell", conversation_id);
-
-    if let Some(state) = state_mgr.load_conversation(&shell_conv_id)? {
-        // We saved state in beforeShellExecution, meaning we brought user to Cursor
-        // Now bring them back to where they were
-        let prev = &state.saved_window;
-
-        std::thread::sleep(Duration::from_millis(100));
-        let _ = wm.focus_window(prev);
-
-        // Resume YouTube if it was Chrome
-        let mut media_playing = None;
-        if prev.app_name == "Google Chrome" {
-            std::thread::sleep(Duration::from_millis(150));
-            media_playing = Some(wm.resume_youtube(&prev.title));
-        }
+    if delay_seconds > 0 {
+        std::thread::sleep(Duration::from_secs(delay_seconds));
+    }
 
-        // Clear the shell-specific state
-        state_mgr.clear_conversation(&shell_conv_id)?;
+    let (wm, call_log) = window_manager_for(&config)?;
+    let capture_config = config.clone();
+    let result = build_engine(wm, config).and_then(|e| e.check_idle(conversation_id, true));
 
-        // Update menu bar - command approved, back to working
-        wm.update_menu_bar_status_full(
-            "working",
-            Some("Agent working..."),
-            Some(&prev.app_name),
-            Some(&prev.title),
-            media_playing,
+    if let Some(log) = call_log {
+        write_capture(
+            &capture_config,
+            conversation_id,
+            CaptureEntry {
+                at: Utc::now(),
+                command: "check-idle".to_string(),
+                raw: conversation_id.to_string(),
+                event: None,
+                parsed: None,
+                parse_error: None,
+                response: None,
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+                calls: log.take(),
+            },
         );
     }
 
-    Ok(())
+    result
 }
 
-/// CheckIdle command - failsafe that brings user to Cursor if shell command is still pending
-/// Called by background timer spawned in beforeShellExecution after 5 seconds
-fn cmd_check_idle(conversation_id: &str, delay_seconds: u64) -> Result<()> {
-    // Check if Recursor is enabled
-    if !is_enabled() {
-        // Don't pull user to Cursor when disabled
-        return Ok(());
-    }
-
+/// Bounce command - timer for a delayed bounce after submit.
+/// Bounces only if the agent is still working when the delay is up.
+fn cmd_bounce(conversation_id: &str, delay_seconds: u64) -> Result<()> {
+    let config = load_config()?;
     if delay_seconds > 0 {
         std::thread::sleep(Duration::from_secs(delay_seconds));
     }
+    let (wm, _) = window_manager_for(&config)?;
+    build_engine(wm, config)?.delayed_bounce(conversation_id, true)
+}
 
-    let wm = create_window_manager();
-    let state_mgr = StateManager::new()?;
+/// DeferredPull command - wait until the user may be disturbed, then pull them to Cursor.
+/// Gives up after an hour, when the conversation's state would be stale anyway.
+fn cmd_deferred_pull(conversation_id: &str) -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < DEFERRED_PULL_LIMIT {
+        // Reload the config each time so turning Recursor off stops the wait
+        let config = load_config()?;
+        std::thread::sleep(Duration::from_secs(
+            config.do_not_disturb.poll_seconds.max(1),
+        ));
+        let (wm, _) = window_manager_for(&config)?;
+        if build_engine(wm, config)?.deferred_pull(conversation_id)? {
+            break;
+        }
+    }
+    Ok(())
+}
 
-    // Check for shell-specific saved state
-    let shell_conv_id = format!("{}_shell", conversation_id);
+/// CatchUp command - wait for the user to come back, then take them to the most
+/// urgent conversation. Gives up after 12 hours, when away events go stale.
+fn cmd_catch_up() -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < CATCH_UP_LIMIT {
+        let config = load_config()?;
+        std::thread::sleep(Duration::from_secs(config.away.poll_seconds.max(1)));
+        let (wm, _) = window_manager_for(&config)?;
+        if build_engine(wm, config)?.catch_up()? {
+            break;
+        }
+    }
+    Ok(())
+}
 
-    if let Some(state) = state_mgr.load_conversation(&shell_conv_id)? {
-        // Verify that at least 5 seconds have actually elapsed since state was saved
-        // This prevents race conditions where timer fires but command just started
-        let elapsed = Utc::now() - state.saved_at;
-        if elapsed.num_seconds() < SHELL_FAILSAFE_DELAY_SECONDS as i64 {
-            // Not enough time has passed, don't bring user to Cursor yet
-            return Ok(());
+/// PullQueue command - whenever the user is done with a pull, take them to the
+/// next conversation waiting for them. Gives up after an hour, when queued
+/// pulls go stale.
+fn cmd_pull_queue() -> Result<()> {
+    let started = Instant::now();
+    while started.elapsed() < PULL_QUEUE_LIMIT {
+        let config = load_config()?;
+        std::thread::sleep(Duration::from_secs(config.pull_queue.poll_seconds.max(1)));
+        let (wm, _) = window_manager_for(&config)?;
+        if build_engine(wm, config)?.advance_pull_queue()? {
+            break;
         }
+    }
+    Ok(())
+}
 
-        // State still exists after 5 seconds - command is likely waiting for approval
-        // This is our failsafe: bring user to Cursor
+/// RunActions command - run the user's actions for one transition.
+/// Spawned in the background by hooks, so the hook never waits for them.
+fn cmd_run_actions() -> Result<()> {
+    let config = load_config()?;
+    let raw = hooks::read_stdin()?;
+    let payload: ActionPayload =
+        serde_json::from_str(&raw).context("Failed to parse action payload")?;
+    ActionRunner::new(config.actions)?.run(&payload)
+}
 
-        // Pause YouTube if user was watching.
-        let media_playing = if state.saved_window.app_name == "Google Chrome"
-            && wm.pause_youtube_if_playing(&state.saved_window.title)
-        {
-            Some(false)
+/// Replay command - feed a captured session back through the handlers.
+/// Window queries are answered from the capture, nothing is focused, no
+/// actions run, and the session runs against a scratch state file with the
+/// current config.
+/// Failsafe checks run immediately, as if their delay had elapsed.
+fn cmd_replay(file: &Path) -> Result<()> {
+    let entries = capture::read_session(file)?;
+    let config = Config::load();
+
+    let scratch_dir = std::env::temp_dir().join(format!("recursor_replay_{}", std::process::id()));
+    std::fs::create_dir_all(&scratch_dir).context("Failed to create replay directory")?;
+    let state_path = scratch_dir.join("recursor_state.json");
+
+    for entry in entries {
+        println!("[{}] {}", entry.at.format("%H:%M:%S%.3f"), entry.command);
+
+        let (recorder, log) =
+            RecordingWindowManager::new(Box::new(PlaybackWindowManager::new(entry.calls.clone())));
+        let engine = Engine::new(
+            Box::new(recorder),
+            StateManager::with_state_path(state_path.clone()),
+            config.clone(),
+        )
+        .with_failsafe_timers(false)
+        .with_actions(false);
+
+        let response = if entry.command == "check-idle" {
+            engine.check_idle(&entry.raw, false).map(|()| None)
+        } else if let Some(command) = hook_command(&entry.command) {
+            let (event, parse_error) = parse_hook_input(&command, &entry.raw);
+            if let Some(e) = parse_error {
+                println!("  parse error: {:#}", e);
+            }
+            match event {
+                Some(event) => {
+                    println!("  event: {}", event.name().unwrap_or("(unknown)"));
+                    engine
+                        .with_bounce_on_submit(command.bounce_on_submit)
+                        .handle(event)
+                        .map(|o| o.to_json())
+                }
+                None => Ok(None),
+            }
         } else {
-            None
+            println!("  skipped: unknown command");
+            continue;
         };
 
-        // Get the Cursor window from the main conversation state
-        if let Some(main_state) = state_mgr.load_conversation(conversation_id)? {
-            if let Some(ref cursor_win) = main_state.cursor_window {
-                let _ = wm.focus_cursor_window(cursor_win);
-            } else {
-                let _ = wm.focus_cursor();
-            }
-        } else {
-            let _ = wm.focus_cursor();
+        for call in log.take() {
+            println!("  {}", call);
         }
 
-        // Update menu bar to indicate we're waiting for approval
-        wm.update_menu_bar_status_full(
-            "approval_needed",
-            Some("Waiting for command approval..."),
-            Some(&state.saved_window.app_name),
-            Some(&state.saved_window.title),
-            media_playing,
-        );
+        match response {
+            Ok(response) => {
+                if let Some(ref json) = response {
+                    println!("  response: {}", json);
+                }
+                if response != entry.response {
+                    println!(
+                        "  note: captured response was {}",
+                        entry
+                            .response
+                            .as_ref()
+                            .map(|r| r.to_string())
+                            .unwrap_or_else(|| "(none)".to_string())
+                    );
+                }
+            }
+            Err(e) => println!("  error: {:#}", e),
+        }
     }
-    // If state doesn't exist, command already finished - do nothing
 
+    let _ = std::fs::remove_dir_all(&scratch_dir);
     Ok(())
 }
 
-fn select_window_to_save(
-    cursor_window: Option<platform::WindowInfo>,
-    previous_window: Option<platform::WindowInfo>,
-) -> Option<platform::WindowInfo> {
-    previous_window.or(cursor_window)
+/// InstallHooks command - register Recursor for every hook event it handles
+fn cmd_install_hooks(project: bool, dry_run: bool, binary: Option<PathBuf>) -> Result<()> {
+    let binary = match binary {
+        Some(path) => path,
+        None => std::env::current_exe().context("Could not determine the recursor binary path")?,
+    };
+    let command = install::hook_command(&binary);
+    edit_hooks_file(project, dry_run, |hooks_file| {
+        install::install(hooks_file, &command)
+    })
+}
+
+/// UninstallHooks command - remove Recursor's entries from hooks.json
+fn cmd_uninstall_hooks(project: bool, dry_run: bool) -> Result<()> {
+    edit_hooks_file(project, dry_run, install::uninstall)
+}
+
+/// Apply an edit to the user or project hooks.json, printing a diff on --dry-run
+fn edit_hooks_file(
+    project: bool,
+    dry_run: bool,
+    edit: impl FnOnce(&mut serde_json::Value) -> Result<()>,
+) -> Result<()> {
+    let path = if project {
+        let cwd = std::env::current_dir().context("Could not determine current directory")?;
+        install::project_hooks_path(&cwd)
+    } else {
+        install::user_hooks_path()?
+    };
+
+    let mut hooks_file = install::load_hooks_file(&path)?;
+    let before = if path.exists() {
+        install::render_hooks_file(&hooks_file)?
+    } else {
+        String::new()
+    };
+    edit(&mut hooks_file)?;
+    let after = install::render_hooks_file(&hooks_file)?;
+
+    if before == after {
+        println!("{} is already up to date.", path.display());
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("Would update {}:", path.display());
+        print!("{}", install::diff_lines(&before, &after));
+        return Ok(());
+    }
+
+    install::write_hooks_file(&path, &after)?;
+    println!("Updated {}", path.display());
+    Ok(())
 }
 
 /// Status command - show current saved state
-fn cmd_status() -> Result<()> {
+fn cmd_status(explain: bool) -> Result<()> {
     let state_mgr = StateManager::new()?;
+    if load_config()?.dry_run {
+        println!("Dry run: decisions are logged, focus and media are left alone.\n");
+    }
 
     let conversations = state_mgr.get_all_conversations()?;
+    let mut lifecycles = state_mgr.get_all_lifecycles()?;
+    let mut explanations = state_mgr.get_all_explanations()?;
 
-    if conversations.is_empty() {
+    if conversations.is_empty() && lifecycles.is_empty() {
         println!("No saved state.");
-        return Ok(());
+    } else {
+        println!("Recursor State:");
+        println!("===============");
     }
 
-    println!("Recursor State:");
-    println!("===============");
-
     for (conv_id, state) in conversations {
         println!("\nConversation: {}", conv_id);
+        if let Some(lifecycle) = lifecycles.remove(&conv_id) {
+            println!("  Phase: {} (since {})", lifecycle.phase, lifecycle.since);
+        }
         println!("  Saved Window:");
         println!("    App: {}", state.saved_window.app_name);
         println!("    Title: {}", state.saved_window.title);
@@ -536,6 +974,124 @@ fn cmd_status() -> Result<()> {
         }
         println!("  Saved At: {}", state.saved_at);
         println!("  User Switched: {}", state.user_switched);
+        print_explanation(explanations.remove(&conv_id));
+    }
+
+    for (conv_id, lifecycle) in lifecycles {
+        println!("\nConversation: {}", conv_id);
+        println!("  Phase: {} (since {})", lifecycle.phase, lifecycle.since);
+        if let Some(ref window) = lifecycle.return_to {
+            println!("  Return To: {} ({})", window.app_name, window.title);
+        }
+        print_explanation(explanations.remove(&conv_id));
+    }
+
+    let away = state_mgr.get_away_events()?;
+    if !away.is_empty() {
+        println!("\nWhile you were away: {}", recursor::away::digest(&away));
+        for event in away {
+            println!(
+                "  - {}: {} at {}",
+                event.conversation_id,
+                event.kind.describe(),
+                event.at
+            );
+        }
+    }
+
+    let pulls = state_mgr.get_pull_queue()?;
+    if !pulls.waiting.is_empty() {
+        println!("\nPull queue:");
+        if let Some(handling) = pulls.handling {
+            println!(
+                "  Handling: {} - {}",
+                handling.conversation_id,
+                handling.kind.describe()
+            );
+        }
+        for pull in pulls.waiting {
+            println!(
+                "  Waiting: {} - {} since {}",
+                pull.conversation_id,
+                pull.kind.describe(),
+                pull.at
+            );
+        }
+    }
+
+    if explain {
+        explain_rules()?;
+    }
+
+    Ok(())
+}
+
+/// Print the reasons behind a conversation's latest decision
+fn print_explanation(explanation: Option<Explanation>) {
+    if let Some(explanation) = explanation {
+        println!(
+            "  Last Decision ({} at {}):",
+            explanation.event, explanation.at
+        );
+        for reason in explanation.reasons {
+            println!("    - {}", reason);
+        }
+    }
+}
+
+/// Evaluate the per-app rules against the current windows, as a prompt submitted now would
+fn explain_rules() -> Result<()> {
+    let config = load_config()?;
+    let wm = create_backend(&config.backend)?;
+    let rules = RuleSet::new(&config.rules);
+
+    println!("\nRules:");
+    if config.rules.is_empty() {
+        println!("  (none)");
+    }
+    for (index, rule) in config.rules.iter().enumerate() {
+        println!(
+            "  {}. {} {}",
+            index + 1,
+            rule.action.as_str(),
+            rule.window.describe()
+        );
+    }
+
+    let previous = wm.get_previous_window().ok();
+    let mut why = Vec::new();
+    let target = rules.choose_return_target(
+        previous.as_ref(),
+        || wm.list_windows().unwrap_or_default(),
+        &mut why,
+    );
+    println!("\nIf you submitted a prompt now:");
+    for reason in why {
+        println!("  - {}", reason);
+    }
+    println!(
+        "  => {}",
+        target
+            .map(|w| format!("bounce back to {}", describe_window(&w)))
+            .unwrap_or_else(|| "stay in Cursor".to_string())
+    );
+
+    let dnd = DoNotDisturb::new(&config.do_not_disturb);
+    let mut why = Vec::new();
+    if dnd.active(wm.as_ref(), &rules, &mut why) {
+        let instead = match config.do_not_disturb.mode {
+            DndMode::Notify => "you would get a notification instead",
+            DndMode::Defer => "pulls would wait until this ends",
+        };
+        println!("\nDo not disturb ({}):", instead);
+        for reason in why {
+            println!("  - {}", reason);
+        }
+    } else if let Ok(active) = wm.get_active_window() {
+        println!(
+            "\nThe active window ({}) may be interrupted.",
+            describe_window(&active)
+        );
     }
 
     Ok(())
@@ -543,7 +1099,7 @@ fn cmd_status() -> Result<()> {
 
 /// Permissions command - trigger permission prompts on macOS
 fn cmd_permissions() -> Result<()> {
-    let wm = create_window_manager();
+    let wm = create_backend(&load_config()?.backend)?;
 
     println!("Recursor Permissions Check");
     println!("==========================");
@@ -596,46 +1152,361 @@ fn cmd_permissions() -> Result<()> {
     Ok(())
 }
 
-/// Clear command - remove saved state
-fn cmd_clear() -> Result<()> {
-    let state_mgr = StateManager::new()?;
-    state_mgr.clear()?;
-    println!("Saved state cleared.");
+/// Stats command - summarize the history
+fn cmd_stats(since: Option<&str>, json: bool, csv: bool) -> Result<()> {
+    let config = load_config()?;
+    let mut entries = History::new()?.entries()?;
+    if let Some(since) = since {
+        let since = stats::parse_since(since, Utc::now())?;
+        entries.retain(|entry| entry.at >= since);
+    }
+    let stats = Stats::from_entries(&entries, config.bounce.short_run_seconds as f64);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats).context("Failed to serialize stats")?
+        );
+        return Ok(());
+    }
+    if csv {
+        print!("{}", stats.to_csv());
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("No history recorded yet.");
+        return Ok(());
+    }
+    println!(
+        "Runs: {}  median {}  p90 {}",
+        stats.runs,
+        format_seconds(stats.total.median_seconds),
+        format_seconds(stats.total.p90_seconds)
+    );
+    println!(
+        "Approvals: {} ({} waiting)",
+        stats.total.approvals,
+        format_seconds(stats.total.approval_wait_seconds)
+    );
+    println!(
+        "Parked elsewhere: {}  Focus changes: {}",
+        format_seconds(stats.total.parked_seconds),
+        stats.total.focus_changes
+    );
+    for (title, groups) in [
+        ("By day", &stats.by_day),
+        ("By workspace", &stats.by_workspace),
+        ("By model", &stats.by_model),
+    ] {
+        println!("\n{}:", title);
+        println!(
+            "  {:<40} {:>5} {:>8} {:>8} {:>9} {:>8} {:>8} {:>6}",
+            "", "runs", "median", "p90", "approvals", "waiting", "parked", "focus"
+        );
+        for group in groups {
+            println!(
+                "  {:<40} {:>5} {:>8} {:>8} {:>9} {:>8} {:>8} {:>6}",
+                group.name,
+                group.runs,
+                format_seconds(group.median_seconds),
+                format_seconds(group.p90_seconds),
+                group.approvals,
+                format_seconds(group.approval_wait_seconds),
+                format_seconds(group.parked_seconds),
+                group.focus_changes
+            );
+        }
+    }
+
+    println!("\nPredictions:");
+    let Some(predictions) = stats.predictions else {
+        println!("  none yet (set bounce.predict to true)");
+        return Ok(());
+    };
+    println!(
+        "  {} runs predicted, median error {:.1}s",
+        predictions.runs.len(),
+        predictions.median_error_seconds
+    );
+    println!(
+        "  {} bounces skipped, {} of them for runs longer than {}s",
+        predictions.skipped_bounces, predictions.wrong_skips, config.bounce.short_run_seconds
+    );
+    println!("\n  Recent runs:");
+    let recent = predictions.runs.len().saturating_sub(10);
+    for run in &predictions.runs[recent..] {
+        println!(
+            "    {:<36} predicted {:>6.1}s  actual {:>6.1}s{}",
+            run.conversation_id,
+            run.predicted_seconds,
+            run.actual_seconds,
+            if run.skipped_bounce {
+                "  (stayed in Cursor)"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Short human-readable duration, e.g. `42s`, `3m05s` or `2h10m`
+fn format_seconds(seconds: f64) -> String {
+    let whole = seconds.round() as u64;
+    match whole {
+        0..=59 => format!("{}s", whole),
+        60..=3599 => format!("{}m{:02}s", whole / 60, whole % 60),
+        _ => format!("{}h{:02}m", whole / 3600, whole % 3600 / 60),
+    }
+}
+
+/// History command - search the audit log
+fn cmd_history(query: &AuditQuery, limit: usize, output: bool, json: bool) -> Result<()> {
+    let config = load_config()?;
+    let log = AuditLog::new(&config.audit)?;
+    for error in log.errors() {
+        eprintln!("Warning: {}", error);
+    }
+    let matches: Vec<_> = log
+        .entries()?
+        .into_iter()
+        .filter(|entry| query.matches(entry))
+        .collect();
+    let shown = &matches[matches.len().saturating_sub(limit)..];
+
+    if json {
+        for entry in shown {
+            println!(
+                "{}",
+                serde_json::to_string(entry).context("Failed to serialize audit entry")?
+            );
+        }
+        return Ok(());
+    }
+    if shown.is_empty() {
+        println!("No matching commands.");
+        return Ok(());
+    }
+    for entry in shown {
+        println!(
+            "{}  {}  {:>8}{}  {}",
+            entry.at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            entry.conversation_id,
+            entry
+                .duration_seconds
+                .map(format_seconds)
+                .unwrap_or_else(|| "-".to_string()),
+            if entry.needed_approval {
+                "  [approval]"
+            } else {
+                ""
+            },
+            entry.command
+        );
+        if let Some(ref workspace) = entry.workspace_roots.first() {
+            println!("    in {}", workspace);
+        }
+        if output {
+            if let Some(ref text) = entry.output {
+                for line in text.lines() {
+                    println!("    | {}", line);
+                }
+            }
+        }
+    }
+    if shown.len() < matches.len() {
+        println!(
+            "\n{} older matches not shown (use --limit)",
+            matches.len() - shown.len()
+        );
+    }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Logs command - print the debug log, optionally following it
+fn cmd_logs(
+    conversation: Option<&str>,
+    level: Option<Level>,
+    lines: usize,
+    follow: bool,
+    json: bool,
+) -> Result<()> {
+    let config = load_config()?;
+    let logger = Logger::new(&config.logging)?;
+    let wanted = |record: &LogRecord| {
+        conversation.is_none_or(|id| record.conversation_id.as_deref() == Some(id))
+            && level.is_none_or(|level| record.level <= level)
+    };
 
-    fn window(app_name: &str, title: &str, pid: u32) -> platform::WindowInfo {
-        platform::WindowInfo {
-            pid,
-            window_id: format!("{}:1", pid),
-            app_name: app_name.to_string(),
-            title: title.to_string(),
+    let records: Vec<LogRecord> = logger.records()?.into_iter().filter(wanted).collect();
+    if records.is_empty() && !follow && !json {
+        println!("No log records yet ({}).", logger.path().display());
+        return Ok(());
+    }
+    for record in &records[records.len().saturating_sub(lines)..] {
+        print_log_record(record, json)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    // Poll the current file for appended lines; a shorter file means it was rotated
+    let path = logger.path();
+    let mut offset = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if len < offset {
+            offset = 0;
+        }
+        if len == offset {
+            continue;
+        }
+        let mut file = std::fs::File::open(path).context("Failed to open log")?;
+        file.seek(SeekFrom::Start(offset))
+            .context("Failed to read log")?;
+        let mut appended = String::new();
+        file.read_to_string(&mut appended)
+            .context("Failed to read log")?;
+        // Leave a partly written last line for the next round
+        let complete = appended.rfind('\n').map_or(0, |end| end + 1);
+        offset += complete as u64;
+        for line in appended[..complete].lines() {
+            if let Ok(record) = serde_json::from_str::<LogRecord>(line) {
+                if wanted(&record) {
+                    print_log_record(&record, json)?;
+                }
+            }
         }
     }
+}
 
-    #[test]
-    fn select_window_to_save_prefers_previous_window() {
-        let cursor = window("Cursor", "main.rs - Recursor - Cursor", 100);
-        let previous = window("Google Chrome", "YouTube", 200);
+/// Print one log record, as JSON or as a line of text
+fn print_log_record(record: &LogRecord, json: bool) -> Result<()> {
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(record).context("Failed to serialize log record")?
+        );
+        return Ok(());
+    }
+    println!(
+        "{} {:<5} {:>6} {}{}: {}",
+        record
+            .at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f"),
+        record.level.as_str(),
+        record.pid,
+        record
+            .conversation_id
+            .as_ref()
+            .map(|id| format!("[{}] ", id))
+            .unwrap_or_default(),
+        record.kind,
+        record.message
+    );
+    Ok(())
+}
 
-        let selected = select_window_to_save(Some(cursor), Some(previous.clone()));
-        assert_eq!(selected, Some(previous));
+/// Doctor command - run every diagnostic check; fails if any check fails
+fn cmd_doctor(json: bool) -> Result<()> {
+    let config = load_config()?;
+    let wm = create_backend(&config.backend)?;
+    let checks = doctor::run_checks(wm.as_ref(), &config.backend);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&checks).context("Failed to serialize checks")?
+        );
+    } else {
+        print!("{}", doctor::render_text(&checks));
     }
 
-    #[test]
-    fn select_window_to_save_falls_back_to_cursor_window() {
-        let cursor = window("Cursor", "main.rs - Recursor - Cursor", 100);
+    match doctor::failures(&checks) {
+        0 => Ok(()),
+        1 => Err(anyhow::anyhow!("1 check failed")),
+        n => Err(anyhow::anyhow!("{} checks failed", n)),
+    }
+}
 
-        let selected = select_window_to_save(Some(cursor.clone()), None);
-        assert_eq!(selected, Some(cursor));
+/// Bench command - run the hooks of a synthetic prompt repeatedly and report
+/// how long each one blocks Cursor and where the time goes
+fn cmd_bench(iterations: usize, backend: &str, json: bool) -> Result<()> {
+    if iterations == 0 {
+        return Err(anyhow::anyhow!("--iterations must be at least 1"));
+    }
+    let config = load_config()?;
+    let kind = match backend {
+        "platform" => BackendKind::Platform,
+        _ => BackendKind::Null,
+    };
+    let wm = create_backend(&BackendConfig {
+        kind,
+        ..BackendConfig::default()
+    })?;
+    let scratch_dir = std::env::temp_dir().join(format!("recursor_bench_{}", std::process::id()));
+    let results = bench::run(wm, &config, iterations, &scratch_dir);
+    let _ = std::fs::remove_dir_all(&scratch_dir);
+    let results = results?;
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&results).context("Failed to serialize results")?
+        );
+        return Ok(());
     }
 
-    #[test]
-    fn select_window_to_save_handles_missing_windows() {
-        assert_eq!(select_window_to_save(None, None), None);
+    println!(
+        "{} runs per hook against the {} backend, fast path {}",
+        iterations,
+        backend,
+        if config.fast_path.enabled {
+            "on"
+        } else {
+            "off"
+        }
+    );
+    let row = |indent: usize, label: &str, timing: &Timing| {
+        println!(
+            "{:indent$}{:<width$} {:>10} {:>10} {:>10}",
+            "",
+            label,
+            format_ms(timing.p50_ms),
+            format_ms(timing.p95_ms),
+            format_ms(timing.max_ms),
+            width = 24 - indent
+        );
+    };
+    for result in &results {
+        println!(
+            "\n{:<24} {:>10} {:>10} {:>10}",
+            result.event, "p50", "p95", "max"
+        );
+        row(2, "blocks Cursor", &result.blocking);
+        row(2, Stage::Parse.label(), &result.stages[&Stage::Parse]);
+        row(2, Stage::Answer.label(), &result.stages[&Stage::Answer]);
+        row(2, "handler", &result.handler);
+        for stage in Stage::ALL.iter().filter(|stage| stage.in_handler()) {
+            row(4, stage.label(), &result.stages[stage]);
+        }
+        row(4, "other", &result.other);
     }
+    Ok(())
+}
+
+/// Milliseconds with enough precision for sub-millisecond stages
+fn format_ms(ms: f64) -> String {
+    format!("{:.2}ms", ms)
+}
+
+/// Clear command - remove saved state
+fn cmd_clear() -> Result<()> {
+    let state_mgr = StateManager::new()?;
+    state_mgr.clear()?;
+    println!("Saved state cleared.");
+    Ok(())
 }
@@ -0,0 +1,158 @@
+//! Dry-run window manager
+//!
+//! `DryRunWindowManager` wraps another window manager and answers every query
+//! from it, but never moves focus or touches media playback. Each call that
+//! would have done so is kept as a description ("focus Google Chrome ...") for
+//! the engine to report together with the reasons for its decision. Menu bar
+//! updates and notifications still go through, so status stays accurate.
+
+use super::{WindowInfo, WindowManager};
+use crate::doctor::Check;
+use crate::platform::recording::describe_window;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Shared handle to the calls a `DryRunWindowManager` held back
+#[derive(Debug, Clone, Default)]
+pub struct SuppressedCalls(Rc<RefCell<Vec<String>>>);
+
+impl SuppressedCalls {
+    /// Take the descriptions of all calls held back so far
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    fn push(&self, call: String) {
+        self.0.borrow_mut().push(call);
+    }
+}
+
+/// Window manager that forwards queries to another one and holds back
+/// everything that would change focus or playback
+pub struct DryRunWindowManager {
+    inner: Box<dyn WindowManager>,
+    suppressed: SuppressedCalls,
+}
+
+impl DryRunWindowManager {
+    /// Wrap a window manager, returning it with a handle to the held-back calls
+    pub fn new(inner: Box<dyn WindowManager>) -> (Self, SuppressedCalls) {
+        let suppressed = SuppressedCalls::default();
+        (
+            Self {
+                inner,
+                suppressed: suppressed.clone(),
+            },
+            suppressed,
+        )
+    }
+}
+
+impl WindowManager for DryRunWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        self.inner.get_active_window()
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        self.inner.get_previous_window()
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        self.inner.list_windows()
+    }
+
+    fn focus_window(&self, window: &WindowInfo) -> Result<()> {
+        self.suppressed
+            .push(format!("focus {}", describe_window(window)));
+        Ok(())
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        self.suppressed.push("focus Cursor".to_string());
+        Ok(())
+    }
+
+    fn focus_cursor_window(&self, window: &WindowInfo) -> Result<()> {
+        self.suppressed
+            .push(format!("focus {}", describe_window(window)));
+        Ok(())
+    }
+
+    fn is_cursor_window(&self, window: &WindowInfo) -> bool {
+        self.inner.is_cursor_window(window)
+    }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        self.inner.is_fullscreen(window)
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        self.inner.is_screen_locked()
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        self.inner.input_idle_ms()
+    }
+
+    fn pause_youtube_if_playing(&self, window_title: &str) -> bool {
+        // Report what a real pause would have, so the rest of the run plays out the same
+        let playing = self.inner.is_youtube_playing();
+        if playing {
+            self.suppressed
+                .push(format!("pause YouTube in {:?}", window_title));
+        }
+        playing
+    }
+
+    fn pause_youtube_in_window(&self, window: &WindowInfo) -> bool {
+        self.suppressed.push(format!(
+            "pause YouTube in {} if it is playing",
+            describe_window(window)
+        ));
+        false
+    }
+
+    fn resume_youtube(&self, window_title: &str) -> bool {
+        self.suppressed.push(format!(
+            "resume YouTube in {:?} if it is paused",
+            window_title
+        ));
+        false
+    }
+
+    fn is_youtube_playing(&self) -> bool {
+        self.inner.is_youtube_playing()
+    }
+
+    fn update_menu_bar_status(&self, status: &str, window_title: Option<&str>) {
+        self.inner.update_menu_bar_status(status, window_title);
+    }
+
+    fn update_menu_bar_status_full(
+        &self,
+        status: &str,
+        cursor_state: Option<&str>,
+        secondary_app: Option<&str>,
+        secondary_title: Option<&str>,
+        media_playing: Option<bool>,
+        stop_status: Option<&str>,
+    ) {
+        self.inner.update_menu_bar_status_full(
+            status,
+            cursor_state,
+            secondary_app,
+            secondary_title,
+            media_playing,
+            stop_status,
+        );
+    }
+
+    fn notify(&self, title: &str, message: &str) {
+        self.inner.notify(title, message);
+    }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        self.inner.diagnostics()
+    }
+}
@@ -4,9 +4,11 @@
 //! Note: Wayland support is limited due to protocol restrictions.
 
 use super::{WindowInfo, WindowManager};
+use crate::doctor::{find_in_path, Check};
 use anyhow::{anyhow, Context, Result};
 use std::process::Command;
 use x11rb::connection::Connection;
+use x11rb::protocol::screensaver::ConnectionExt as _;
 use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, Window};
 use x11rb::rust_connection::RustConnection;
 
@@ -21,6 +23,7 @@ pub struct LinuxWindowManager {
 }
 
 impl LinuxWindowManager {
+    /// Connect to the X server, falling back to xdotool/wmctrl if that fails
     pub fn new() -> Self {
         // Try to connect to X11
         match RustConnection::connect(None) {
@@ -86,20 +89,21 @@ impl LinuxWindowManager {
             String::new()
         };
 
-        // Try to get app name from /proc
-        let app_name = if pid > 0 {
-            std::fs::read_to_string(format!("/proc/{}/comm", pid))
-                .map(|s| s.trim().to_string())
-                .unwrap_or_else(|_| "Unknown".to_string())
-        } else {
-            "Unknown".to_string()
-        };
+        // Get window class (needs xdotool 3.20210804 or later)
+        let class = Command::new("xdotool")
+            .args(["getwindowclassname", &window_id])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|class| !class.is_empty());
 
         Ok(WindowInfo {
             pid,
             window_id,
-            app_name,
+            app_name: process_name(pid),
             title,
+            class,
         })
     }
 
@@ -136,6 +140,47 @@ impl LinuxWindowManager {
             reply.value[3],
         ]);
 
+        self.window_info_x11(conn, window_id)
+    }
+
+    /// List client windows using X11, most recently raised first
+    fn list_windows_x11(&self) -> Result<Vec<WindowInfo>> {
+        let conn = self
+            .conn
+            .as_ref()
+            .ok_or_else(|| anyhow!("No X11 connection"))?;
+
+        let stacking_atom = conn
+            .intern_atom(false, b"_NET_CLIENT_LIST_STACKING")
+            .context("Failed to intern _NET_CLIENT_LIST_STACKING")?
+            .reply()
+            .context("Failed to get _NET_CLIENT_LIST_STACKING reply")?
+            .atom;
+
+        // Stacking order is bottom to top
+        let reply = conn
+            .get_property(
+                false,
+                self.root,
+                stacking_atom,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )
+            .context("Failed to get client list")?
+            .reply()
+            .context("Failed to get client list reply")?;
+        let windows: Vec<u32> = reply.value32().map(|v| v.collect()).unwrap_or_default();
+
+        Ok(windows
+            .into_iter()
+            .rev()
+            .filter_map(|window_id| self.window_info_x11(conn, window_id).ok())
+            .collect())
+    }
+
+    /// Read a window's PID, title and class using X11
+    fn window_info_x11(&self, conn: &RustConnection, window_id: u32) -> Result<WindowInfo> {
         // Get _NET_WM_PID
         let pid_atom = conn
             .intern_atom(false, b"_NET_WM_PID")
@@ -186,20 +231,32 @@ impl LinuxWindowManager {
             .trim()
             .to_string();
 
-        // Get app name from /proc
-        let app_name = if pid > 0 {
-            std::fs::read_to_string(format!("/proc/{}/comm", pid))
-                .map(|s| s.trim().to_string())
-                .unwrap_or_else(|_| "Unknown".to_string())
-        } else {
-            "Unknown".to_string()
-        };
+        // WM_CLASS holds the instance and class names, each NUL-terminated
+        let class_reply = conn
+            .get_property(
+                false,
+                window_id,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                0,
+                1024,
+            )
+            .context("Failed to get window class")?
+            .reply()
+            .context("Failed to get window class reply")?;
+        let class = class_reply
+            .value
+            .split(|&b| b == 0)
+            .nth(1)
+            .map(|class| String::from_utf8_lossy(class).to_string())
+            .filter(|class| !class.is_empty());
 
         Ok(WindowInfo {
             pid,
             window_id: format!("{}", window_id),
-            app_name,
+            app_name: process_name(pid),
             title,
+            class,
         })
     }
 
@@ -270,86 +327,80 @@ impl LinuxWindowManager {
         Ok(())
     }
 
-    /// Find a Cursor window whose title contains the given search string
-    fn find_cursor_window_by_title(&self, search: &str) -> Option<WindowInfo> {
-        let output = Command::new("wmctrl").args(["-l", "-p"]).output().ok()?;
+    /// Check _NET_WM_STATE for _NET_WM_STATE_FULLSCREEN using X11
+    fn is_fullscreen_x11(&self, window: &WindowInfo) -> Result<bool> {
+        let conn = self
+            .conn
+            .as_ref()
+            .ok_or_else(|| anyhow!("No X11 connection"))?;
+        let window_id = x11_window_id(&window.window_id).context("Invalid window ID")?;
+
+        let intern = |name: &[u8]| -> Result<u32> {
+            Ok(conn
+                .intern_atom(false, name)
+                .context("Failed to intern atom")?
+                .reply()
+                .context("Failed to get atom reply")?
+                .atom)
+        };
+        let state_atom = intern(b"_NET_WM_STATE")?;
+        let fullscreen_atom = intern(b"_NET_WM_STATE_FULLSCREEN")?;
 
-        if !output.status.success() {
-            return None;
-        }
+        let reply = conn
+            .get_property(false, window_id, state_atom, AtomEnum::ATOM, 0, u32::MAX)
+            .context("Failed to get _NET_WM_STATE")?
+            .reply()
+            .context("Failed to get _NET_WM_STATE reply")?;
+        Ok(reply
+            .value32()
+            .is_some_and(|mut states| states.any(|s| s == fullscreen_atom)))
+    }
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
+    /// Find a Cursor window whose title contains the given search string
+    fn find_cursor_window_by_title(&self, search: &str) -> Option<WindowInfo> {
         let search_lower = search.to_lowercase();
+        wmctrl_windows()?
+            .into_iter()
+            .find(|w| w.is_cursor() && w.title.to_lowercase().contains(&search_lower))
+    }
 
-        for line in output_str.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 5 {
-                let window_id = parts[0];
-                let pid: u32 = parts[2].parse().unwrap_or(0);
-                let title = parts[4..].join(" ");
-
-                let app_name = if pid > 0 {
-                    std::fs::read_to_string(format!("/proc/{}/comm", pid))
-                        .map(|s| s.trim().to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
-                };
-
-                if app_name.to_lowercase().contains("cursor")
-                    && title.to_lowercase().contains(&search_lower)
-                {
-                    return Some(WindowInfo {
-                        pid,
-                        window_id: window_id.to_string(),
-                        app_name,
-                        title,
-                    });
-                }
+    /// Check which EWMH properties the window manager advertises in _NET_SUPPORTED
+    fn check_ewmh(&self, conn: &RustConnection) -> Result<Vec<&'static str>> {
+        let intern = |name: &[u8]| -> Result<u32> {
+            Ok(conn
+                .intern_atom(false, name)
+                .context("Failed to intern atom")?
+                .reply()
+                .context("Failed to get atom reply")?
+                .atom)
+        };
+        let supported_atom = intern(b"_NET_SUPPORTED")?;
+        let reply = conn
+            .get_property(
+                false,
+                self.root,
+                supported_atom,
+                AtomEnum::ATOM,
+                0,
+                u32::MAX,
+            )
+            .context("Failed to get _NET_SUPPORTED")?
+            .reply()
+            .context("Failed to get _NET_SUPPORTED reply")?;
+        let supported: Vec<u32> = reply.value32().map(|v| v.collect()).unwrap_or_default();
+
+        let mut missing = Vec::new();
+        for name in ["_NET_ACTIVE_WINDOW", "_NET_CLIENT_LIST"] {
+            if !supported.contains(&intern(name.as_bytes())?) {
+                missing.push(name);
             }
         }
-
-        None
+        Ok(missing)
     }
 
     /// Find Cursor window
     fn find_cursor_window(&self) -> Option<WindowInfo> {
-        // Try using wmctrl to list windows
-        let output = Command::new("wmctrl").args(["-l", "-p"]).output().ok()?;
-
-        if !output.status.success() {
-            return None;
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-
-        for line in output_str.lines() {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 5 {
-                let window_id = parts[0];
-                let pid: u32 = parts[2].parse().unwrap_or(0);
-
-                // Check if this is Cursor
-                let app_name = if pid > 0 {
-                    std::fs::read_to_string(format!("/proc/{}/comm", pid))
-                        .map(|s| s.trim().to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
-                };
-
-                if app_name.to_lowercase().contains("cursor") {
-                    return Some(WindowInfo {
-                        pid,
-                        window_id: window_id.to_string(),
-                        app_name,
-                        title: parts[4..].join(" "),
-                    });
-                }
-            }
-        }
-
-        None
+        wmctrl_windows()?.into_iter().find(|w| w.is_cursor())
     }
 }
 
@@ -362,6 +413,15 @@ impl WindowManager for LinuxWindowManager {
         }
     }
 
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        if self.use_x11 {
+            if let Ok(windows) = self.list_windows_x11() {
+                return Ok(windows);
+            }
+        }
+        wmctrl_windows().ok_or_else(|| anyhow!("Could not list windows (is wmctrl installed?)"))
+    }
+
     fn focus_window(&self, window: &WindowInfo) -> Result<()> {
         if self.use_x11 {
             // Try X11 first, fall back to xdotool
@@ -390,6 +450,7 @@ impl WindowManager for LinuxWindowManager {
                         window_id: first_id.trim().to_string(),
                         app_name: "Cursor".to_string(),
                         title: String::new(),
+                        class: None,
                     };
                     return self.focus_window(&window);
                 }
@@ -426,6 +487,166 @@ impl WindowManager for LinuxWindowManager {
         // Strategy 4: Generic Cursor focus
         self.focus_cursor()
     }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        if self.use_x11 {
+            if let Ok(fullscreen) = self.is_fullscreen_x11(window) {
+                return fullscreen;
+            }
+        }
+        // Fall back to xprop, which accepts decimal and hex IDs
+        Command::new("xprop")
+            .args(["-id", &window.window_id, "_NET_WM_STATE"])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout).contains("_NET_WM_STATE_FULLSCREEN")
+            })
+            .unwrap_or(false)
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        // logind tracks the lock state screen lockers report for the session
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+        Command::new("loginctl")
+            .args([
+                "show-session",
+                &session,
+                "--property",
+                "LockedHint",
+                "--value",
+            ])
+            .output()
+            .map(|output| output.status.success() && output.stdout.trim_ascii() == b"yes")
+            .unwrap_or(false)
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        // The MIT-SCREEN-SAVER extension tracks time since the last input event
+        if let Some(ref conn) = self.conn {
+            if let Ok(info) = conn
+                .screensaver_query_info(self.root)
+                .map_err(anyhow::Error::from)
+                .and_then(|cookie| cookie.reply().map_err(anyhow::Error::from))
+            {
+                return Some(u64::from(info.ms_since_user_input));
+            }
+        }
+        if let Some(ms) = Command::new("xprintidle")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+        {
+            return Some(ms);
+        }
+        logind_idle_ms()
+    }
+
+    fn notify(&self, title: &str, message: &str) {
+        let _ = Command::new("notify-send")
+            .args(["--app-name", "Recursor", title, message])
+            .output();
+    }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        let mut checks = Vec::new();
+
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland");
+        checks.push(if wayland {
+            Check::warn(
+                "session type",
+                "Wayland session; only XWayland windows can be seen or focused",
+                "Log into an X11 session, or run Cursor under XWayland (--ozone-platform=x11)",
+            )
+        } else if std::env::var_os("DISPLAY").is_some() {
+            Check::pass("session type", "X11")
+        } else {
+            Check::warn(
+                "session type",
+                "neither DISPLAY nor WAYLAND_DISPLAY is set",
+                "Run Recursor from inside your desktop session",
+            )
+        });
+
+        match &self.conn {
+            Some(conn) => {
+                let display = std::env::var("DISPLAY").unwrap_or_default();
+                checks.push(Check::pass(
+                    "X11 connection",
+                    format!("connected to {}", display),
+                ));
+                checks.push(match self.check_ewmh(conn) {
+                    Ok(missing) if missing.is_empty() => Check::pass(
+                        "EWMH support",
+                        "_NET_ACTIVE_WINDOW and _NET_CLIENT_LIST supported",
+                    ),
+                    Ok(missing) => Check::fail(
+                        "EWMH support",
+                        format!("window manager does not support {}", missing.join(", ")),
+                        "Use an EWMH-compliant window manager (GNOME, KDE, Xfce, i3, ...)",
+                    ),
+                    Err(e) => Check::fail(
+                        "EWMH support",
+                        format!("{:#}", e),
+                        "Use an EWMH-compliant window manager (GNOME, KDE, Xfce, i3, ...)",
+                    ),
+                });
+            }
+            None => checks.push(Check::fail(
+                "X11 connection",
+                "could not connect to the X server",
+                "Make sure DISPLAY is set; otherwise Recursor falls back to xdotool and wmctrl",
+            )),
+        }
+
+        let missing_tools: Vec<&str> = ["xdotool", "wmctrl"]
+            .into_iter()
+            .filter(|tool| find_in_path(tool).is_none())
+            .collect();
+        checks.push(if missing_tools.is_empty() {
+            Check::pass("fallback tools", "xdotool and wmctrl found")
+        } else {
+            let detail = format!("{} not found", missing_tools.join(" and "));
+            let hint = format!(
+                "Install with e.g. `sudo apt install {}`",
+                missing_tools.join(" ")
+            );
+            if self.use_x11 && !missing_tools.contains(&"wmctrl") {
+                Check::warn("fallback tools", detail, hint)
+            } else {
+                // wmctrl is needed to find Cursor windows, and both are needed without X11
+                Check::fail("fallback tools", detail, hint)
+            }
+        });
+
+        checks.push(if find_in_path("notify-send").is_some() {
+            Check::pass("notifications", "notify-send found")
+        } else {
+            Check::warn(
+                "notifications",
+                "notify-send not found",
+                "Install libnotify (e.g. `sudo apt install libnotify-bin`)",
+            )
+        });
+
+        checks.push(match self.find_cursor_window() {
+            Some(window) => Check::pass("Cursor window", window.title),
+            None => Check::warn(
+                "Cursor window",
+                "no Cursor window found",
+                "Start Cursor; windows are discovered with wmctrl",
+            ),
+        });
+
+        checks.push(Check::warn(
+            "media control",
+            "pausing media is not supported on Linux",
+            "Nothing to do; YouTube won't be paused when Recursor pulls you back",
+        ));
+
+        checks
+    }
 }
 
 impl Default for LinuxWindowManager {
@@ -433,3 +654,124 @@ impl Default for LinuxWindowManager {
         Self::new()
     }
 }
+
+/// Name of a process from /proc ("Unknown" if it can't be read)
+fn process_name(pid: u32) -> String {
+    if pid == 0 {
+        return "Unknown".to_string();
+    }
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|_| "Unknown".to_string())
+}
+
+/// Time since logind's idle hint was set, for sessions without X11 (only known
+/// while the session is idle)
+fn logind_idle_ms() -> Option<u64> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+    let output = Command::new("loginctl")
+        .args([
+            "show-session",
+            &session,
+            "--property",
+            "IdleHint",
+            "--property",
+            "IdleSinceHint",
+        ])
+        .output()
+        .ok()?;
+    let properties = String::from_utf8_lossy(&output.stdout);
+    parse_idle_hint(&properties, chrono::Utc::now().timestamp_micros())
+}
+
+/// Idle time from `IdleHint=` and `IdleSinceHint=` (microseconds since the epoch)
+fn parse_idle_hint(properties: &str, now_us: i64) -> Option<u64> {
+    let value = |key: &str| {
+        properties
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+    };
+    if value("IdleHint")? != "yes" {
+        return None;
+    }
+    let since_us: i64 = value("IdleSinceHint")?.parse().ok()?;
+    u64::try_from((now_us - since_us) / 1000).ok()
+}
+
+/// Parse a window ID as X11 and xdotool print it (decimal) or as wmctrl does (hex)
+fn x11_window_id(id: &str) -> Option<u32> {
+    match id.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => id.parse().ok(),
+    }
+}
+
+/// List client windows using wmctrl
+fn wmctrl_windows() -> Option<Vec<WindowInfo>> {
+    let output = Command::new("wmctrl")
+        .args(["-l", "-p", "-x"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(parse_wmctrl_line)
+            .collect(),
+    )
+}
+
+/// Parse a line of `wmctrl -l -p -x`: ID, desktop, PID, instance.Class, host, title
+fn parse_wmctrl_line(line: &str) -> Option<WindowInfo> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return None;
+    }
+    let pid: u32 = parts[2].parse().unwrap_or(0);
+    let class = parts[3]
+        .rsplit_once('.')
+        .map(|(_, class)| class)
+        .unwrap_or(parts[3]);
+
+    Some(WindowInfo {
+        pid,
+        window_id: parts[0].to_string(),
+        app_name: process_name(pid),
+        title: parts.get(5..).map(|t| t.join(" ")).unwrap_or_default(),
+        class: Some(class.to_string()).filter(|c| !c.is_empty()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wmctrl_line_reads_class_and_title() {
+        let window = parse_wmctrl_line(
+            "0x04200007  0 0 navigator.Firefox  host Meeting - Zoom - Mozilla Firefox",
+        )
+        .expect("window");
+        assert_eq!(window.window_id, "0x04200007");
+        assert_eq!(window.class.as_deref(), Some("Firefox"));
+        assert_eq!(window.title, "Meeting - Zoom - Mozilla Firefox");
+
+        assert_eq!(parse_wmctrl_line("0x04200007  0 0"), None);
+        assert_eq!(x11_window_id(&window.window_id), Some(0x04200007));
+        assert_eq!(x11_window_id("69206023"), Some(69206023));
+    }
+
+    #[test]
+    fn parse_idle_hint_only_reports_idle_sessions() {
+        let idle = "IdleHint=yes\nIdleSinceHint=1000000000\n";
+        assert_eq!(parse_idle_hint(idle, 1_300_000_000), Some(300_000));
+        assert_eq!(
+            parse_idle_hint("IdleHint=no\nIdleSinceHint=0\n", 1_300_000_000),
+            None
+        );
+    }
+}
@@ -4,6 +4,7 @@
 //! This approach is more reliable and doesn't require complex CoreFoundation bindings.
 
 use super::{WindowInfo, WindowManager};
+use crate::doctor::Check;
 use anyhow::{anyhow, Context, Result};
 use rusqlite::Connection;
 use serde_json::{Map, Value};
@@ -20,6 +21,7 @@ struct AsnInfo {
 pub struct MacOSWindowManager;
 
 impl MacOSWindowManager {
+    /// Create a macOS window manager
     pub fn new() -> Self {
         Self
     }
@@ -155,10 +157,10 @@ impl MacOSWindowManager {
     /// Update the menu bar status with rich information
     #[allow(dead_code)]
     pub fn update_menu_bar_status(&self, status: &str, window_title: Option<&str>) {
-        self.update_menu_bar_status_full(status, None, None, None, None);
+        self.update_menu_bar_status_full(status, None, None, None, None, None);
         // Also update with window title for backwards compatibility
         if window_title.is_some() {
-            self.update_menu_bar_status_full(status, None, None, window_title, None);
+            self.update_menu_bar_status_full(status, None, None, window_title, None, None);
         }
     }
 
@@ -170,6 +172,7 @@ impl MacOSWindowManager {
         secondary_app: Option<&str>,
         secondary_title: Option<&str>,
         media_playing: Option<bool>,
+        stop_status: Option<&str>,
     ) {
         let Some(cursor_dir) = Self::cursor_dir() else {
             return;
@@ -183,6 +186,7 @@ impl MacOSWindowManager {
             secondary_app,
             secondary_title,
             media_playing,
+            stop_status,
         );
         if let Ok(json) = serde_json::to_string(&payload) {
             let _ = std::fs::write(&status_file, json);
@@ -231,6 +235,7 @@ impl MacOSWindowManager {
                                 window_id: format!("{}:1", info.pid),
                                 app_name: "Google Chrome".to_string(),
                                 title,
+                                class: None,
                             });
                         }
                     }
@@ -483,6 +488,7 @@ impl MacOSWindowManager {
                     window_id: "saved".to_string(),
                     app_name: "Google Chrome".to_string(),
                     title,
+                    class: None,
                 });
             }
         }
@@ -524,6 +530,7 @@ impl MacOSWindowManager {
             window_id,
             app_name: app_name.to_string(),
             title: title.to_string(),
+            class: None,
         })
     }
 
@@ -537,6 +544,7 @@ impl MacOSWindowManager {
         secondary_app: Option<&str>,
         secondary_title: Option<&str>,
         media_playing: Option<bool>,
+        stop_status: Option<&str>,
     ) -> Value {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -564,6 +572,9 @@ impl MacOSWindowManager {
         if let Some(playing) = media_playing {
             payload.insert("media_playing".to_string(), Value::Bool(playing));
         }
+        if let Some(stop) = stop_status {
+            payload.insert("stop_status".to_string(), Value::String(stop.to_string()));
+        }
 
         Value::Object(payload)
     }
@@ -803,6 +814,37 @@ impl WindowManager for MacOSWindowManager {
         MacOSWindowManager::resume_youtube(self, window_title)
     }
 
+    fn pause_youtube_in_window(&self, window: &WindowInfo) -> bool {
+        if window.pid == 0 {
+            return false;
+        }
+        let script = format!(
+            r#"
+            tell application "System Events"
+                set chromeProc to first application process whose unix id is {}
+                set allWins to every window of chromeProc
+                set winCount to count of allWins
+                repeat with i from 1 to winCount
+                    try
+                        set winName to name of item i of allWins
+                        if winName contains "YouTube" and winName contains "Audio playing" then
+                            set frontmost of chromeProc to true
+                            delay 0.15
+                            perform action "AXRaise" of (item i of allWins)
+                            delay 0.2
+                            keystroke "k"
+                            return "paused"
+                        end if
+                    end try
+                end repeat
+                return "not_playing"
+            end tell
+            "#,
+            window.pid
+        );
+        matches!(self.run_applescript(&script).as_deref(), Ok("paused"))
+    }
+
     fn update_menu_bar_status(&self, status: &str, window_title: Option<&str>) {
         MacOSWindowManager::update_menu_bar_status(self, status, window_title)
     }
@@ -818,6 +860,7 @@ impl WindowManager for MacOSWindowManager {
         secondary_app: Option<&str>,
         secondary_title: Option<&str>,
         media_playing: Option<bool>,
+        stop_status: Option<&str>,
     ) {
         MacOSWindowManager::update_menu_bar_status_full(
             self,
@@ -826,8 +869,117 @@ impl WindowManager for MacOSWindowManager {
             secondary_app,
             secondary_title,
             media_playing,
+            stop_status,
         )
     }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        let script = format!(
+            r#"
+            tell application "System Events"
+                set targetProc to first application process whose unix id is {}
+                return value of attribute "AXFullScreen" of front window of targetProc
+            end tell
+            "#,
+            window.pid
+        );
+        matches!(self.run_applescript(&script).as_deref(), Ok("true"))
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        // The login window session reports the lock in the I/O registry root
+        Command::new("ioreg")
+            .args(["-n", "Root", "-d1"])
+            .output()
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout).contains("\"CGSSessionScreenIsLocked\"=Yes")
+            })
+            .unwrap_or(false)
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        // IOHIDSystem reports nanoseconds since the last keyboard or mouse event
+        let output = Command::new("ioreg")
+            .args(["-c", "IOHIDSystem", "-d", "4"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find(|line| line.contains("\"HIDIdleTime\""))
+            .and_then(|line| line.rsplit('=').next())
+            .and_then(|ns| ns.trim().parse::<u64>().ok())
+            .map(|ns| ns / 1_000_000)
+    }
+
+    fn notify(&self, title: &str, message: &str) {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape_applescript(message),
+            escape_applescript(title)
+        );
+        let _ = self.run_applescript(&script);
+    }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        let mut checks = Vec::new();
+
+        let cursor_window = self.run_applescript(
+            r#"
+            tell application "System Events"
+                if exists (process "Cursor") then
+                    set winNames to name of every window of process "Cursor"
+                    if (count of winNames) > 0 then return item 1 of winNames
+                end if
+            end tell
+            return ""
+        "#,
+        );
+        checks.push(match cursor_window {
+            Ok(title) if !title.is_empty() => Check::pass("Cursor window", title),
+            Ok(_) => Check::warn(
+                "Cursor window",
+                "no Cursor window found",
+                "Start Cursor and open a project",
+            ),
+            Err(e) => Check::fail(
+                "Cursor window",
+                format!("{:#}", e),
+                "Grant Accessibility access in System Settings > Privacy & Security > Accessibility",
+            ),
+        });
+
+        let chrome_javascript = self.run_applescript(
+            r#"
+            if application "Google Chrome" is running then
+                tell application "Google Chrome"
+                    if (count of windows) > 0 then
+                        execute active tab of front window javascript "1"
+                        return "ok"
+                    end if
+                end tell
+            end if
+            return "not running"
+        "#,
+        );
+        checks.push(match chrome_javascript {
+            Ok(result) if result == "not running" => Check::warn(
+                "media control",
+                "Google Chrome is not running, so YouTube control can't be verified",
+                "Open Chrome and run `recursor doctor` again",
+            ),
+            Ok(_) => Check::pass(
+                "media control",
+                "Google Chrome accepts JavaScript from Apple Events",
+            ),
+            Err(e) => Check::fail(
+                "media control",
+                format!("{:#}", e),
+                "In Chrome, enable View > Developer > Allow JavaScript from Apple Events",
+            ),
+        });
+
+        checks
+    }
 }
 
 impl Default for MacOSWindowManager {
@@ -836,6 +988,11 @@ impl Default for MacOSWindowManager {
     }
 }
 
+/// Escape a string for use inside an AppleScript string literal
+fn escape_applescript(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 /// Read Cursor's command allowlist from its SQLite database.
 /// Returns the list of commands that are auto-approved (won't need user confirmation).
 #[allow(dead_code)]
@@ -927,6 +1084,7 @@ mod tests {
             Some("Google Chrome"),
             Some("Line 1\nLine 2 \\ path"),
             Some(false),
+            Some("error"),
         );
 
         assert_eq!(payload["status"], "working");
@@ -935,6 +1093,7 @@ mod tests {
         assert_eq!(payload["secondary_title"], "Line 1\nLine 2 \\ path");
         assert_eq!(payload["window"], "Line 1\nLine 2 \\ path");
         assert_eq!(payload["media_playing"], false);
+        assert_eq!(payload["stop_status"], "error");
         assert!(payload["timestamp"].is_number());
     }
 
@@ -954,6 +1113,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn escape_applescript_escapes_quotes_and_backslashes() {
+        assert_eq!(
+            escape_applescript(r#"say "hi" \ bye"#),
+            r#"say \"hi\" \\ bye"#
+        );
+    }
+
     #[test]
     fn command_allowlist_prefix_is_boundary_aware() {
         let allowlist = vec!["git commit".to_string()];
@@ -3,7 +3,9 @@
 //! This module provides a cross-platform abstraction for window management operations
 //! needed by Reflex: getting the active window, focusing windows, and detecting Cursor.
 
-use anyhow::Result;
+use crate::config::{BackendConfig, BackendKind};
+use crate::doctor::Check;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 /// Information about a window
@@ -18,6 +20,9 @@ pub struct WindowInfo {
     /// Window title (e.g., "YouTube - Google Chrome")
     #[serde(default)]
     pub title: String,
+    /// Window class (WM_CLASS on X11), where the platform has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
 }
 
 impl WindowInfo {
@@ -60,7 +65,6 @@ impl WindowInfo {
 }
 
 /// Trait for platform-specific window management operations
-#[allow(dead_code)]
 pub trait WindowManager {
     /// Get information about the currently active/focused window
     fn get_active_window(&self) -> Result<WindowInfo>;
@@ -72,6 +76,12 @@ pub trait WindowManager {
         self.get_active_window()
     }
 
+    /// List the windows the user could be sent to, most recently used first
+    /// where the platform knows the order
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        Ok(Vec::new()) // Default: unsupported, no windows
+    }
+
     /// Focus/activate a specific window
     fn focus_window(&self, window: &WindowInfo) -> Result<()>;
 
@@ -89,11 +99,33 @@ pub trait WindowManager {
         window.is_cursor()
     }
 
+    /// Check if a window covers the whole screen (fullscreen video, screen share, slides)
+    fn is_fullscreen(&self, _window: &WindowInfo) -> bool {
+        false // Default: unknown, assume not
+    }
+
+    /// Check if the screen is locked
+    fn is_screen_locked(&self) -> bool {
+        false // Default: unknown, assume not
+    }
+
+    /// Milliseconds since the user last pressed a key or moved the mouse,
+    /// if the platform can tell
+    fn input_idle_ms(&self) -> Option<u64> {
+        None
+    }
+
     /// Pause YouTube if playing in the given window (returns true if paused)
     fn pause_youtube_if_playing(&self, _window_title: &str) -> bool {
         false // Default: no-op
     }
 
+    /// Pause a playing YouTube tab in a specific (possibly unfocused) browser window
+    /// by raising it and sending the player's pause key (returns true if paused)
+    fn pause_youtube_in_window(&self, _window: &WindowInfo) -> bool {
+        false // Default: no-op
+    }
+
     /// Resume YouTube in the given window (returns true if resumed)
     fn resume_youtube(&self, _window_title: &str) -> bool {
         false // Default: no-op
@@ -117,11 +149,30 @@ pub trait WindowManager {
         _secondary_app: Option<&str>,
         _secondary_title: Option<&str>,
         _media_playing: Option<bool>,
+        _stop_status: Option<&str>,
     ) {
         // Default: no-op
     }
+
+    /// Show a desktop notification
+    fn notify(&self, _title: &str, _message: &str) {
+        // Default: no-op
+    }
+
+    /// Platform-specific checks for `recursor doctor`
+    #[doc(hidden)]
+    fn diagnostics(&self) -> Vec<Check> {
+        Vec::new()
+    }
 }
 
+pub mod dry_run;
+pub mod null;
+pub mod recording;
+pub mod scripted;
+#[doc(hidden)]
+pub mod timing;
+
 // Platform-specific implementations
 #[cfg(target_os = "macos")]
 pub mod macos;
@@ -143,6 +194,39 @@ pub fn create_window_manager() -> PlatformWindowManager {
     PlatformWindowManager::new()
 }
 
+/// Create the window manager selected by a (resolved) backend config
+pub fn create_backend(backend: &BackendConfig) -> Result<Box<dyn WindowManager>> {
+    let scripted = || -> Result<Box<dyn WindowManager>> {
+        let script = backend
+            .script
+            .as_ref()
+            .context("The scripted backend needs a script (RECURSOR_BACKEND_SCRIPT)")?;
+        Ok(Box::new(scripted::ScriptedWindowManager::load(script)?))
+    };
+
+    match backend.kind {
+        BackendKind::Platform => Ok(Box::new(create_window_manager())),
+        BackendKind::Null => Ok(Box::new(null::NullWindowManager)),
+        BackendKind::Scripted => scripted(),
+        BackendKind::Recording => {
+            let inner = if backend.script.is_some() {
+                scripted()?
+            } else {
+                Box::new(create_window_manager())
+            };
+            let log = match backend.log {
+                Some(ref path) => path.clone(),
+                None => dirs::home_dir()
+                    .context("Could not determine home directory")?
+                    .join(".cursor")
+                    .join("recursor_calls.jsonl"),
+            };
+            let (recorder, _) = recording::RecordingWindowManager::new(inner);
+            Ok(Box::new(recorder.logging_to(log)))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -154,6 +238,7 @@ mod tests {
             window_id: "test".to_string(),
             app_name: "Cursor".to_string(),
             title: "main.rs - Cursor".to_string(),
+            class: None,
         };
         assert!(cursor_window.is_cursor());
 
@@ -162,6 +247,7 @@ mod tests {
             window_id: "test2".to_string(),
             app_name: "Google Chrome".to_string(),
             title: "YouTube".to_string(),
+            class: None,
         };
         assert!(!chrome_window.is_cursor());
     }
@@ -174,6 +260,7 @@ mod tests {
             window_id: "1".to_string(),
             app_name: "Cursor".to_string(),
             title: "main.rs - Recursor - Cursor".to_string(),
+            class: None,
         };
         assert_eq!(w.cursor_project_name(), Some("Recursor".to_string()));
 
@@ -183,6 +270,7 @@ mod tests {
             window_id: "1".to_string(),
             app_name: "Cursor".to_string(),
             title: "Recursor - Cursor".to_string(),
+            class: None,
         };
         assert_eq!(w2.cursor_project_name(), Some("Recursor".to_string()));
 
@@ -192,6 +280,7 @@ mod tests {
             window_id: "1".to_string(),
             app_name: "Cursor".to_string(),
             title: "Cursor".to_string(),
+            class: None,
         };
         assert_eq!(w3.cursor_project_name(), None);
 
@@ -201,6 +290,7 @@ mod tests {
             window_id: "1".to_string(),
             app_name: "Cursor".to_string(),
             title: "".to_string(),
+            class: None,
         };
         assert_eq!(w4.cursor_project_name(), None);
 
@@ -210,6 +300,7 @@ mod tests {
             window_id: "1".to_string(),
             app_name: "Cursor".to_string(),
             title: "index.ts - My Cool Project - Cursor".to_string(),
+            class: None,
         };
         assert_eq!(
             w5.cursor_project_name(),
@@ -0,0 +1,32 @@
+//! Window manager that does nothing
+//!
+//! Every query fails and every action succeeds without touching any window, so
+//! hooks can run (and answer Cursor) on machines without a usable display.
+
+use super::{WindowInfo, WindowManager};
+use anyhow::{anyhow, Result};
+
+/// Window manager backend with no windows
+pub struct NullWindowManager;
+
+impl WindowManager for NullWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        Err(anyhow!("No windows (null backend)"))
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        Err(anyhow!("No windows (null backend)"))
+    }
+
+    fn focus_window(&self, _window: &WindowInfo) -> Result<()> {
+        Ok(())
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn focus_cursor_window(&self, _window: &WindowInfo) -> Result<()> {
+        Ok(())
+    }
+}
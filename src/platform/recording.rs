@@ -0,0 +1,522 @@
+//! Recording and playback window managers
+//!
+//! `RecordingWindowManager` wraps another window manager and logs every call
+//! together with its result. `PlaybackWindowManager` answers queries from such
+//! a log instead of touching real windows, so a captured hook session can be
+//! replayed and its actions inspected.
+//!
+//! With a log file, the recorder also appends each call to it as it happens;
+//! that is the `recording` backend.
+
+use super::{WindowInfo, WindowManager};
+use crate::doctor::Check;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Result of a recorded window manager call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallResult {
+    /// The call completed and returns nothing
+    #[default]
+    Done,
+    /// The call returned a window
+    Window(WindowInfo),
+    /// The call returned a list of windows
+    Windows(Vec<WindowInfo>),
+    /// The call returned a flag
+    Bool(bool),
+    /// The call returned a duration in milliseconds, if known
+    Millis(Option<u64>),
+    /// The call failed
+    Error(String),
+}
+
+/// A single window manager call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCall {
+    /// Trait method name (e.g. "focus_window")
+    pub method: String,
+    /// Human-readable arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// What the call returned
+    #[serde(default)]
+    pub result: CallResult,
+}
+
+impl fmt::Display for RecordedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.method, self.args.join(", "))?;
+        match &self.result {
+            CallResult::Done => Ok(()),
+            CallResult::Window(w) => write!(f, " -> {}", describe_window(w)),
+            CallResult::Windows(windows) => write!(f, " -> {} windows", windows.len()),
+            CallResult::Bool(b) => write!(f, " -> {}", b),
+            CallResult::Millis(Some(ms)) => write!(f, " -> {}ms", ms),
+            CallResult::Millis(None) => write!(f, " -> unknown"),
+            CallResult::Error(e) => write!(f, " -> error: {}", e),
+        }
+    }
+}
+
+/// Short description of a window for logs
+pub fn describe_window(window: &WindowInfo) -> String {
+    if window.title.is_empty() {
+        format!("{} (pid {})", window.app_name, window.pid)
+    } else {
+        format!(
+            "{} \"{}\" (pid {})",
+            window.app_name, window.title, window.pid
+        )
+    }
+}
+
+/// A call as written to a recording backend's log file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedCall {
+    /// When the call was made
+    pub at: DateTime<Utc>,
+    /// The call itself
+    #[serde(flatten)]
+    pub call: RecordedCall,
+}
+
+/// Shared handle to the calls made through a `RecordingWindowManager`
+#[derive(Debug, Clone, Default)]
+pub struct CallLog(Rc<RefCell<Vec<RecordedCall>>>);
+
+impl CallLog {
+    /// Take all calls recorded so far
+    pub fn take(&self) -> Vec<RecordedCall> {
+        std::mem::take(&mut *self.0.borrow_mut())
+    }
+
+    fn push(&self, call: RecordedCall) {
+        self.0.borrow_mut().push(call);
+    }
+}
+
+fn window_result(result: &Result<WindowInfo>) -> CallResult {
+    match result {
+        Ok(w) => CallResult::Window(w.clone()),
+        Err(e) => CallResult::Error(e.to_string()),
+    }
+}
+
+fn unit_result(result: &Result<()>) -> CallResult {
+    match result {
+        Ok(()) => CallResult::Done,
+        Err(e) => CallResult::Error(e.to_string()),
+    }
+}
+
+fn opt_arg(value: Option<&str>) -> String {
+    value
+        .map(|v| format!("{:?}", v))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Window manager that forwards to another one and records every call
+pub struct RecordingWindowManager {
+    inner: Box<dyn WindowManager>,
+    log: CallLog,
+    /// JSONL file every call is also appended to
+    file: Option<PathBuf>,
+}
+
+impl RecordingWindowManager {
+    /// Wrap a window manager, returning it with a handle to its call log
+    pub fn new(inner: Box<dyn WindowManager>) -> (Self, CallLog) {
+        let log = CallLog::default();
+        (
+            Self {
+                inner,
+                log: log.clone(),
+                file: None,
+            },
+            log,
+        )
+    }
+
+    /// Also append every call to a JSONL file as it happens
+    pub fn logging_to(mut self, path: PathBuf) -> Self {
+        self.file = Some(path);
+        self
+    }
+
+    fn record(&self, method: &str, args: Vec<String>, result: CallResult) {
+        let call = RecordedCall {
+            method: method.to_string(),
+            args,
+            result,
+        };
+        if let Some(ref path) = self.file {
+            // Logging is best-effort and never fails the call
+            let logged = LoggedCall {
+                at: Utc::now(),
+                call: call.clone(),
+            };
+            if let (Ok(line), Ok(mut file)) = (
+                serde_json::to_string(&logged),
+                OpenOptions::new().create(true).append(true).open(path),
+            ) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+        self.log.push(call);
+    }
+}
+
+impl WindowManager for RecordingWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        let result = self.inner.get_active_window();
+        self.record("get_active_window", vec![], window_result(&result));
+        result
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        let result = self.inner.get_previous_window();
+        self.record("get_previous_window", vec![], window_result(&result));
+        result
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        let result = self.inner.list_windows();
+        let recorded = match result {
+            Ok(ref windows) => CallResult::Windows(windows.clone()),
+            Err(ref e) => CallResult::Error(e.to_string()),
+        };
+        self.record("list_windows", vec![], recorded);
+        result
+    }
+
+    fn focus_window(&self, window: &WindowInfo) -> Result<()> {
+        let result = self.inner.focus_window(window);
+        self.record(
+            "focus_window",
+            vec![describe_window(window)],
+            unit_result(&result),
+        );
+        result
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        let result = self.inner.focus_cursor();
+        self.record("focus_cursor", vec![], unit_result(&result));
+        result
+    }
+
+    fn focus_cursor_window(&self, window: &WindowInfo) -> Result<()> {
+        let result = self.inner.focus_cursor_window(window);
+        self.record(
+            "focus_cursor_window",
+            vec![describe_window(window)],
+            unit_result(&result),
+        );
+        result
+    }
+
+    fn is_cursor_window(&self, window: &WindowInfo) -> bool {
+        self.inner.is_cursor_window(window)
+    }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        let result = self.inner.is_fullscreen(window);
+        self.record(
+            "is_fullscreen",
+            vec![describe_window(window)],
+            CallResult::Bool(result),
+        );
+        result
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        let result = self.inner.is_screen_locked();
+        self.record("is_screen_locked", vec![], CallResult::Bool(result));
+        result
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        let result = self.inner.input_idle_ms();
+        self.record("input_idle_ms", vec![], CallResult::Millis(result));
+        result
+    }
+
+    fn pause_youtube_if_playing(&self, window_title: &str) -> bool {
+        let result = self.inner.pause_youtube_if_playing(window_title);
+        self.record(
+            "pause_youtube_if_playing",
+            vec![format!("{:?}", window_title)],
+            CallResult::Bool(result),
+        );
+        result
+    }
+
+    fn pause_youtube_in_window(&self, window: &WindowInfo) -> bool {
+        let result = self.inner.pause_youtube_in_window(window);
+        self.record(
+            "pause_youtube_in_window",
+            vec![describe_window(window)],
+            CallResult::Bool(result),
+        );
+        result
+    }
+
+    fn resume_youtube(&self, window_title: &str) -> bool {
+        let result = self.inner.resume_youtube(window_title);
+        self.record(
+            "resume_youtube",
+            vec![format!("{:?}", window_title)],
+            CallResult::Bool(result),
+        );
+        result
+    }
+
+    fn is_youtube_playing(&self) -> bool {
+        let result = self.inner.is_youtube_playing();
+        self.record("is_youtube_playing", vec![], CallResult::Bool(result));
+        result
+    }
+
+    fn update_menu_bar_status(&self, status: &str, window_title: Option<&str>) {
+        self.inner.update_menu_bar_status(status, window_title);
+        self.record(
+            "update_menu_bar_status",
+            vec![format!("{:?}", status), opt_arg(window_title)],
+            CallResult::Done,
+        );
+    }
+
+    fn update_menu_bar_status_full(
+        &self,
+        status: &str,
+        cursor_state: Option<&str>,
+        secondary_app: Option<&str>,
+        secondary_title: Option<&str>,
+        media_playing: Option<bool>,
+        stop_status: Option<&str>,
+    ) {
+        self.inner.update_menu_bar_status_full(
+            status,
+            cursor_state,
+            secondary_app,
+            secondary_title,
+            media_playing,
+            stop_status,
+        );
+        self.record(
+            "update_menu_bar_status_full",
+            vec![
+                format!("{:?}", status),
+                opt_arg(cursor_state),
+                opt_arg(secondary_app),
+                opt_arg(secondary_title),
+                media_playing
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                opt_arg(stop_status),
+            ],
+            CallResult::Done,
+        );
+    }
+
+    fn notify(&self, title: &str, message: &str) {
+        self.inner.notify(title, message);
+        self.record(
+            "notify",
+            vec![format!("{:?}", title), format!("{:?}", message)],
+            CallResult::Done,
+        );
+    }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        self.inner.diagnostics()
+    }
+}
+
+/// Window manager that answers from previously recorded calls and changes nothing.
+///
+/// Each query returns the next recorded result for the same method; when the
+/// recording runs out, window queries fail and flag queries return false.
+pub struct PlaybackWindowManager {
+    calls: RefCell<VecDeque<RecordedCall>>,
+}
+
+impl PlaybackWindowManager {
+    /// Play back the given calls
+    pub fn new(calls: Vec<RecordedCall>) -> Self {
+        Self {
+            calls: RefCell::new(calls.into()),
+        }
+    }
+
+    fn next_result(&self, method: &str) -> Option<CallResult> {
+        let mut calls = self.calls.borrow_mut();
+        let index = calls.iter().position(|c| c.method == method)?;
+        calls.remove(index).map(|c| c.result)
+    }
+
+    fn next_window(&self, method: &str) -> Result<WindowInfo> {
+        match self.next_result(method) {
+            Some(CallResult::Window(w)) => Ok(w),
+            Some(CallResult::Error(e)) => Err(anyhow!(e)),
+            _ => Err(anyhow!("No recorded result for {}", method)),
+        }
+    }
+
+    fn next_unit(&self, method: &str) -> Result<()> {
+        match self.next_result(method) {
+            Some(CallResult::Error(e)) => Err(anyhow!(e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn next_bool(&self, method: &str) -> bool {
+        matches!(self.next_result(method), Some(CallResult::Bool(true)))
+    }
+}
+
+impl WindowManager for PlaybackWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        self.next_window("get_active_window")
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        self.next_window("get_previous_window")
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        match self.next_result("list_windows") {
+            Some(CallResult::Windows(windows)) => Ok(windows),
+            Some(CallResult::Error(e)) => Err(anyhow!(e)),
+            _ => Err(anyhow!("No recorded result for list_windows")),
+        }
+    }
+
+    fn focus_window(&self, _window: &WindowInfo) -> Result<()> {
+        self.next_unit("focus_window")
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        self.next_unit("focus_cursor")
+    }
+
+    fn focus_cursor_window(&self, _window: &WindowInfo) -> Result<()> {
+        self.next_unit("focus_cursor_window")
+    }
+
+    fn is_fullscreen(&self, _window: &WindowInfo) -> bool {
+        self.next_bool("is_fullscreen")
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        self.next_bool("is_screen_locked")
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        match self.next_result("input_idle_ms") {
+            Some(CallResult::Millis(ms)) => ms,
+            _ => None,
+        }
+    }
+
+    fn pause_youtube_if_playing(&self, _window_title: &str) -> bool {
+        self.next_bool("pause_youtube_if_playing")
+    }
+
+    fn pause_youtube_in_window(&self, _window: &WindowInfo) -> bool {
+        self.next_bool("pause_youtube_in_window")
+    }
+
+    fn resume_youtube(&self, _window_title: &str) -> bool {
+        self.next_bool("resume_youtube")
+    }
+
+    fn is_youtube_playing(&self) -> bool {
+        self.next_bool("is_youtube_playing")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app_name: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            pid: 42,
+            window_id: "42:1".to_string(),
+            app_name: app_name.to_string(),
+            title: title.to_string(),
+            class: None,
+        }
+    }
+
+    #[test]
+    fn recorded_calls_play_back_in_order() {
+        let chrome = window("Google Chrome", "YouTube");
+        let cursor = window("Cursor", "main.rs - Recursor - Cursor");
+        let playback = PlaybackWindowManager::new(vec![
+            RecordedCall {
+                method: "get_active_window".to_string(),
+                args: vec![],
+                result: CallResult::Window(cursor.clone()),
+            },
+            RecordedCall {
+                method: "get_previous_window".to_string(),
+                args: vec![],
+                result: CallResult::Error("no previous window".to_string()),
+            },
+            RecordedCall {
+                method: "is_youtube_playing".to_string(),
+                args: vec![],
+                result: CallResult::Bool(true),
+            },
+        ]);
+
+        let (recorder, log) = RecordingWindowManager::new(Box::new(playback));
+        assert_eq!(recorder.get_previous_window().ok(), None);
+        assert_eq!(recorder.get_active_window().ok(), Some(cursor));
+        assert!(recorder.get_active_window().is_err());
+        assert!(recorder.is_youtube_playing());
+        assert!(recorder.focus_window(&chrome).is_ok());
+
+        let calls = log.take();
+        let methods: Vec<&str> = calls.iter().map(|c| c.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            vec![
+                "get_previous_window",
+                "get_active_window",
+                "get_active_window",
+                "is_youtube_playing",
+                "focus_window"
+            ]
+        );
+        assert_eq!(
+            calls[4].to_string(),
+            "focus_window(Google Chrome \"YouTube\" (pid 42))"
+        );
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn recorded_call_round_trips_through_json() {
+        let call = RecordedCall {
+            method: "get_active_window".to_string(),
+            args: vec![],
+            result: CallResult::Window(window("Cursor", "")),
+        };
+        let json = serde_json::to_string(&call).expect("serialize");
+        let parsed: RecordedCall = serde_json::from_str(&json).expect("parse");
+        assert_eq!(parsed, call);
+    }
+}
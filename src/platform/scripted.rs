@@ -0,0 +1,261 @@
+//! Window manager driven by a JSON script
+//!
+//! The script describes a desktop: its windows, the order they were focused in
+//! and whether YouTube is playing. Queries are answered from it and actions
+//! (focusing, pausing, resuming) update it and write it back, so a sequence of
+//! `recursor` invocations sees the effects of the previous ones:
+//!
+//! ```json
+//! {
+//!   "windows": [
+//!     { "pid": 100, "window_id": "cursor-1", "app_name": "Cursor", "title": "main.rs - app - Cursor" },
+//!     { "pid": 200, "window_id": "chrome-1", "app_name": "Google Chrome", "title": "YouTube" }
+//!   ],
+//!   "focus": ["chrome-1", "cursor-1"],
+//!   "youtube_playing": true
+//! }
+//! ```
+//!
+//! `focus` lists window IDs from least to most recently focused; the last one is
+//! the active window. Edit the file between invocations to simulate the user
+//! switching windows. `fullscreen` lists the IDs of fullscreen windows,
+//! `screen_locked` simulates a locked screen and `idle_ms` the time since the
+//! user last typed.
+
+use super::{WindowInfo, WindowManager};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A scripted desktop
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Script {
+    /// Every window on the desktop
+    pub windows: Vec<WindowInfo>,
+    /// Window IDs from least to most recently focused
+    pub focus: Vec<String>,
+    /// Whether a YouTube video is playing
+    pub youtube_playing: bool,
+    /// Whether YouTube was paused by Recursor (and so may be resumed)
+    pub youtube_paused: bool,
+    /// IDs of windows that are fullscreen
+    pub fullscreen: Vec<String>,
+    /// Whether the screen is locked
+    pub screen_locked: bool,
+    /// Milliseconds since the user's last input; unknown if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_ms: Option<u64>,
+}
+
+impl Script {
+    fn window(&self, window_id: &str) -> Option<&WindowInfo> {
+        self.windows.iter().find(|w| w.window_id == window_id)
+    }
+
+    /// The focused window `back` steps ago (0 is the active window)
+    fn focused(&self, back: usize) -> Option<&WindowInfo> {
+        self.focus
+            .iter()
+            .rev()
+            .nth(back)
+            .and_then(|id| self.window(id))
+    }
+
+    fn focus(&mut self, window_id: &str) -> Result<()> {
+        if self.window(window_id).is_none() {
+            return Err(anyhow!("Unknown window {}", window_id));
+        }
+        self.focus.retain(|id| id != window_id);
+        self.focus.push(window_id.to_string());
+        Ok(())
+    }
+
+    /// The most recently focused Cursor window, or the first one listed
+    fn cursor_window(&self) -> Option<&WindowInfo> {
+        self.focus
+            .iter()
+            .rev()
+            .filter_map(|id| self.window(id))
+            .chain(self.windows.iter())
+            .find(|w| w.is_cursor())
+    }
+
+    fn pause_youtube(&mut self) -> bool {
+        if !self.youtube_playing {
+            return false;
+        }
+        self.youtube_playing = false;
+        self.youtube_paused = true;
+        true
+    }
+}
+
+/// Window manager backend that reads and updates a script file
+pub struct ScriptedWindowManager {
+    path: PathBuf,
+    script: RefCell<Script>,
+}
+
+impl ScriptedWindowManager {
+    /// Load the script at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read backend script {}", path.display()))?;
+        let script = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse backend script {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            script: RefCell::new(script),
+        })
+    }
+
+    /// Apply a change to the script and write it back
+    fn update<T>(&self, change: impl FnOnce(&mut Script) -> T) -> Result<T> {
+        let mut script = self.script.borrow_mut();
+        let result = change(&mut script);
+        let json = serde_json::to_string_pretty(&*script).context("Failed to serialize script")?;
+        fs::write(&self.path, json).context("Failed to write backend script")?;
+        Ok(result)
+    }
+}
+
+impl WindowManager for ScriptedWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        self.script
+            .borrow()
+            .focused(0)
+            .cloned()
+            .ok_or_else(|| anyhow!("No active window"))
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        self.script
+            .borrow()
+            .focused(1)
+            .cloned()
+            .ok_or_else(|| anyhow!("No previous window"))
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        let script = self.script.borrow();
+        let mut windows: Vec<WindowInfo> = (0..script.focus.len())
+            .filter_map(|back| script.focused(back).cloned())
+            .collect();
+        // Windows that were never focused come last, in the order listed
+        for window in &script.windows {
+            if !windows.contains(window) {
+                windows.push(window.clone());
+            }
+        }
+        Ok(windows)
+    }
+
+    fn focus_window(&self, window: &WindowInfo) -> Result<()> {
+        self.update(|script| script.focus(&window.window_id))?
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        self.update(|script| {
+            let id = script
+                .cursor_window()
+                .map(|w| w.window_id.clone())
+                .ok_or_else(|| anyhow!("Cursor window not found"))?;
+            script.focus(&id)
+        })?
+    }
+
+    fn focus_cursor_window(&self, window: &WindowInfo) -> Result<()> {
+        let known = self
+            .script
+            .borrow()
+            .window(&window.window_id)
+            .is_some_and(|w| w.is_cursor());
+        if known {
+            self.focus_window(window)
+        } else {
+            self.focus_cursor()
+        }
+    }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        self.script.borrow().fullscreen.contains(&window.window_id)
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        self.script.borrow().screen_locked
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        self.script.borrow().idle_ms
+    }
+
+    fn pause_youtube_if_playing(&self, _window_title: &str) -> bool {
+        self.update(Script::pause_youtube).unwrap_or(false)
+    }
+
+    fn pause_youtube_in_window(&self, _window: &WindowInfo) -> bool {
+        self.update(Script::pause_youtube).unwrap_or(false)
+    }
+
+    fn resume_youtube(&self, _window_title: &str) -> bool {
+        self.update(|script| {
+            if !script.youtube_paused {
+                return false;
+            }
+            script.youtube_playing = true;
+            script.youtube_paused = false;
+            true
+        })
+        .unwrap_or(false)
+    }
+
+    fn is_youtube_playing(&self) -> bool {
+        self.script.borrow().youtube_playing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(window_id: &str, app_name: &str) -> WindowInfo {
+        WindowInfo {
+            pid: 1,
+            window_id: window_id.to_string(),
+            app_name: app_name.to_string(),
+            title: String::new(),
+            class: None,
+        }
+    }
+
+    #[test]
+    fn focus_moves_windows_to_the_front() {
+        let mut script = Script {
+            windows: vec![
+                window("cursor-1", "Cursor"),
+                window("chrome-1", "Google Chrome"),
+                window("term-1", "Terminal"),
+            ],
+            focus: vec!["cursor-1".to_string(), "chrome-1".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            script.focused(0).map(|w| w.app_name.as_str()),
+            Some("Google Chrome")
+        );
+        script.focus("term-1").expect("focus");
+        assert_eq!(
+            script.focused(1).map(|w| w.app_name.as_str()),
+            Some("Google Chrome")
+        );
+        assert_eq!(
+            script.cursor_window().map(|w| w.window_id.as_str()),
+            Some("cursor-1")
+        );
+        assert!(script.focus("missing").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Timing window manager
+//!
+//! `TimingWindowManager` wraps another window manager and adds the time each
+//! call takes to a `bench::Stopwatch`: queries (windows, input, media state)
+//! under `WindowQueries`, and focus, media and menu bar changes under `Focus`.
+//! `recursor bench` uses it to tell window manager time from the rest of a hook.
+
+use super::{WindowInfo, WindowManager};
+use crate::bench::{Stage, Stopwatch};
+use crate::doctor::Check;
+use anyhow::Result;
+
+/// Window manager that times every call to another one
+pub struct TimingWindowManager {
+    inner: Box<dyn WindowManager>,
+    stopwatch: Stopwatch,
+}
+
+impl TimingWindowManager {
+    /// Wrap a window manager, adding its call times to `stopwatch`
+    pub fn new(inner: Box<dyn WindowManager>, stopwatch: Stopwatch) -> Self {
+        Self { inner, stopwatch }
+    }
+
+    fn query<T>(&self, f: impl FnOnce(&dyn WindowManager) -> T) -> T {
+        self.stopwatch
+            .time(Stage::WindowQueries, || f(self.inner.as_ref()))
+    }
+
+    fn change<T>(&self, f: impl FnOnce(&dyn WindowManager) -> T) -> T {
+        self.stopwatch.time(Stage::Focus, || f(self.inner.as_ref()))
+    }
+}
+
+impl WindowManager for TimingWindowManager {
+    fn get_active_window(&self) -> Result<WindowInfo> {
+        self.query(|wm| wm.get_active_window())
+    }
+
+    fn get_previous_window(&self) -> Result<WindowInfo> {
+        self.query(|wm| wm.get_previous_window())
+    }
+
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        self.query(|wm| wm.list_windows())
+    }
+
+    fn focus_window(&self, window: &WindowInfo) -> Result<()> {
+        self.change(|wm| wm.focus_window(window))
+    }
+
+    fn focus_cursor(&self) -> Result<()> {
+        self.change(|wm| wm.focus_cursor())
+    }
+
+    fn focus_cursor_window(&self, window: &WindowInfo) -> Result<()> {
+        self.change(|wm| wm.focus_cursor_window(window))
+    }
+
+    fn is_cursor_window(&self, window: &WindowInfo) -> bool {
+        self.query(|wm| wm.is_cursor_window(window))
+    }
+
+    fn is_fullscreen(&self, window: &WindowInfo) -> bool {
+        self.query(|wm| wm.is_fullscreen(window))
+    }
+
+    fn is_screen_locked(&self) -> bool {
+        self.query(|wm| wm.is_screen_locked())
+    }
+
+    fn input_idle_ms(&self) -> Option<u64> {
+        self.query(|wm| wm.input_idle_ms())
+    }
+
+    fn pause_youtube_if_playing(&self, window_title: &str) -> bool {
+        self.change(|wm| wm.pause_youtube_if_playing(window_title))
+    }
+
+    fn pause_youtube_in_window(&self, window: &WindowInfo) -> bool {
+        self.change(|wm| wm.pause_youtube_in_window(window))
+    }
+
+    fn resume_youtube(&self, window_title: &str) -> bool {
+        self.change(|wm| wm.resume_youtube(window_title))
+    }
+
+    fn is_youtube_playing(&self) -> bool {
+        self.query(|wm| wm.is_youtube_playing())
+    }
+
+    fn update_menu_bar_status(&self, status: &str, window_title: Option<&str>) {
+        self.change(|wm| wm.update_menu_bar_status(status, window_title));
+    }
+
+    fn update_menu_bar_status_full(
+        &self,
+        status: &str,
+        cursor_state: Option<&str>,
+        secondary_app: Option<&str>,
+        secondary_title: Option<&str>,
+        media_playing: Option<bool>,
+        stop_status: Option<&str>,
+    ) {
+        self.change(|wm| {
+            wm.update_menu_bar_status_full(
+                status,
+                cursor_state,
+                secondary_app,
+                secondary_title,
+                media_playing,
+                stop_status,
+            )
+        });
+    }
+
+    fn notify(&self, title: &str, message: &str) {
+        self.change(|wm| wm.notify(title, message));
+    }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        self.inner.diagnostics()
+    }
+}
@@ -6,6 +6,7 @@
 //! - GetWindowThreadProcessId to get process information
 
 use super::{WindowInfo, WindowManager};
+use crate::doctor::Check;
 use anyhow::{anyhow, Result};
 use std::ffi::OsString;
 use std::os::windows::ffi::OsStringExt;
@@ -23,6 +24,7 @@ use windows_sys::Win32::UI::WindowsAndMessaging::{
 pub struct WindowsWindowManager;
 
 impl WindowsWindowManager {
+    /// Create a Windows window manager
     pub fn new() -> Self {
         Self
     }
@@ -204,6 +206,7 @@ impl WindowManager for WindowsWindowManager {
                 window_id: format!("{}", hwnd),
                 app_name,
                 title,
+                class: None,
             })
         }
     }
@@ -276,6 +279,24 @@ impl WindowManager for WindowsWindowManager {
         // Strategy 4: Generic Cursor focus
         self.focus_cursor()
     }
+
+    fn diagnostics(&self) -> Vec<Check> {
+        vec![
+            match self.find_cursor_window() {
+                Some(hwnd) => Check::pass("Cursor window", self.get_window_title(hwnd)),
+                None => Check::warn(
+                    "Cursor window",
+                    "no visible Cursor window found",
+                    "Start Cursor and open a project",
+                ),
+            },
+            Check::warn(
+                "media control",
+                "pausing media is not supported on Windows",
+                "Nothing to do; YouTube won't be paused when Recursor pulls you back",
+            ),
+        ]
+    }
 }
 
 impl Default for WindowsWindowManager {
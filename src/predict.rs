@@ -0,0 +1,205 @@
+//! Run time prediction
+//!
+//! Estimates how long an agent run will take from the runs recorded in the
+//! history. Runs are grouped by model, workspace and prompt size; the most
+//! specific group with enough samples gives the estimate, falling back to
+//! broader groups (model and workspace, model, workspace, every run).
+//!
+//! The engine uses the estimate to keep the user in Cursor when a run is
+//! expected to be short, instead of bouncing them away and straight back.
+
+use crate::history::{HistoryEntry, HistoryEvent};
+
+/// Prompts at least this long count as long prompts
+const LONG_PROMPT_CHARS: usize = 500;
+
+/// Only the most recent runs are used, so estimates follow changing habits
+const MAX_SAMPLES: usize = 500;
+
+/// Groups of similar runs, most specific first: what they are called and
+/// whether they share the model, the workspace and the prompt size
+const GROUPS: [(&str, bool, bool, bool); 5] = [
+    ("model, workspace and prompt size", true, true, true),
+    ("model and workspace", true, true, false),
+    ("model", true, false, false),
+    ("workspace", false, true, false),
+    ("history", false, false, false),
+];
+
+/// A finished run from the history
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Model the prompt was sent to
+    pub model: Option<String>,
+    /// First workspace root
+    pub workspace: Option<String>,
+    /// Length of the prompt in characters
+    pub prompt_chars: usize,
+    /// How long the run took
+    pub seconds: f64,
+}
+
+impl Sample {
+    /// The finished run recorded by a history entry, if it is one.
+    /// Stops answered with a follow-up are skipped: their run goes on.
+    pub fn from_entry(entry: &HistoryEntry) -> Option<Self> {
+        let HistoryEvent::Stop {
+            ref workspace_roots,
+            followup: false,
+            run: Some(ref run),
+            ..
+        } = entry.event
+        else {
+            return None;
+        };
+        Some(Self {
+            model: run.model.clone(),
+            workspace: workspace_roots.first().cloned(),
+            prompt_chars: run.prompt_chars,
+            seconds: run.duration_seconds,
+        })
+    }
+
+    fn long_prompt(&self) -> bool {
+        self.prompt_chars >= LONG_PROMPT_CHARS
+    }
+}
+
+/// Estimated run time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    /// Median run time of the similar runs
+    pub median_seconds: f64,
+    /// 75th percentile; most similar runs finished within this
+    pub upper_seconds: f64,
+    /// How many runs the estimate is based on
+    pub samples: usize,
+    /// Which runs were considered similar
+    pub basis: &'static str,
+}
+
+impl Prediction {
+    /// Short description for explanations
+    pub fn describe(&self) -> String {
+        format!(
+            "~{:.0}s, 75% within {:.0}s, from {} runs with the same {}",
+            self.median_seconds, self.upper_seconds, self.samples, self.basis
+        )
+    }
+}
+
+/// Run time estimator over past runs
+pub struct Predictor {
+    samples: Vec<Sample>,
+    min_samples: usize,
+}
+
+impl Predictor {
+    /// Build an estimator from the history; groups with fewer than
+    /// `min_samples` runs are not trusted
+    pub fn from_history(entries: &[HistoryEntry], min_samples: usize) -> Self {
+        let mut samples: Vec<Sample> = entries.iter().filter_map(Sample::from_entry).collect();
+        if samples.len() > MAX_SAMPLES {
+            samples.drain(..samples.len() - MAX_SAMPLES);
+        }
+        Self::new(samples, min_samples)
+    }
+
+    /// Build an estimator from samples
+    pub fn new(samples: Vec<Sample>, min_samples: usize) -> Self {
+        Self {
+            samples,
+            min_samples: min_samples.max(1),
+        }
+    }
+
+    /// Estimate the run time of a prompt, if enough similar runs are known
+    pub fn predict(
+        &self,
+        model: Option<&str>,
+        workspace: Option<&str>,
+        prompt_chars: usize,
+    ) -> Option<Prediction> {
+        let long_prompt = prompt_chars >= LONG_PROMPT_CHARS;
+        GROUPS
+            .iter()
+            .find_map(|&(basis, by_model, by_workspace, by_prompt)| {
+                let similar = |s: &&Sample| {
+                    (!by_model || s.model.as_deref() == model)
+                        && (!by_workspace || s.workspace.as_deref() == workspace)
+                        && (!by_prompt || s.long_prompt() == long_prompt)
+                };
+                let mut seconds: Vec<f64> = self
+                    .samples
+                    .iter()
+                    .filter(similar)
+                    .map(|s| s.seconds)
+                    .collect();
+                if seconds.len() < self.min_samples {
+                    return None;
+                }
+                seconds.sort_by(f64::total_cmp);
+                Some(Prediction {
+                    median_seconds: quantile(&seconds, 0.5),
+                    upper_seconds: quantile(&seconds, 0.75),
+                    samples: seconds.len(),
+                    basis,
+                })
+            })
+    }
+}
+
+/// Nearest-rank quantile of sorted, non-empty values
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(model: &str, workspace: &str, prompt_chars: usize, seconds: f64) -> Sample {
+        Sample {
+            model: Some(model.to_string()),
+            workspace: Some(workspace.to_string()),
+            prompt_chars,
+            seconds,
+        }
+    }
+
+    #[test]
+    fn prediction_uses_the_most_specific_group_with_enough_runs() {
+        let mut samples = Vec::new();
+        for seconds in [3.0, 4.0, 5.0, 6.0] {
+            samples.push(sample("fast", "/api", 40, seconds));
+        }
+        for seconds in [100.0, 200.0, 300.0] {
+            samples.push(sample("fast", "/web", 40, seconds));
+            samples.push(sample("slow", "/api", 2000, seconds));
+        }
+        let predictor = Predictor::new(samples, 3);
+
+        let quick = predictor
+            .predict(Some("fast"), Some("/api"), 10)
+            .expect("quick");
+        assert_eq!(quick.basis, "model, workspace and prompt size");
+        assert_eq!((quick.median_seconds, quick.upper_seconds), (4.0, 5.0));
+        assert_eq!(quick.samples, 4);
+
+        // No long prompts on /api with this model: fall back to model and workspace
+        let long = predictor
+            .predict(Some("fast"), Some("/api"), 900)
+            .expect("long");
+        assert_eq!(long.basis, "model and workspace");
+
+        // Unknown model and workspace: every run
+        let unknown = predictor
+            .predict(Some("new"), Some("/cli"), 10)
+            .expect("unknown");
+        assert_eq!(unknown.basis, "history");
+        assert_eq!(unknown.samples, 10);
+
+        assert_eq!(Predictor::new(Vec::new(), 3).predict(None, None, 0), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! Pull queue
+//!
+//! With agents running in several Cursor windows, approvals and finished runs
+//! of different conversations would each pull the user to their own window and
+//! ping-pong focus between them. Instead every pull asks the queue for the
+//! user's attention first. The first pull happens right away, and the user is
+//! then handling it; while they are, further pulls wait in the queue, approvals
+//! before finished runs, oldest first.
+//!
+//! Once the user has dealt with the pull they are handling (the command was
+//! approved, they went back to the app they were parked in, ...) a background
+//! `recursor pull-queue` process moves on to the next waiting pull.
+
+use crate::hooks::StopStatus;
+use crate::lifecycle::Phase;
+use crate::platform::WindowInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What a conversation needs the user for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PullKind {
+    /// A command was waiting for approval
+    ApprovalNeeded,
+    /// The agent stopped
+    Finished {
+        /// How the run ended
+        status: StopStatus,
+    },
+}
+
+impl PullKind {
+    /// Lower is more urgent: approvals block the agent, errors need a look
+    pub fn urgency(self) -> u8 {
+        match self {
+            Self::ApprovalNeeded => 0,
+            Self::Finished {
+                status: StopStatus::Error,
+            } => 1,
+            Self::Finished { .. } => 2,
+        }
+    }
+
+    /// Short description for status output, e.g. `finished (error)`
+    pub fn describe(self) -> String {
+        match self {
+            Self::ApprovalNeeded => "approval needed".to_string(),
+            Self::Finished { status } => format!("finished ({})", status.as_str()),
+        }
+    }
+
+    /// Whether the event still needs the user, given the conversation's current phase
+    pub fn still_relevant(self, phase: Phase) -> bool {
+        match self {
+            Self::ApprovalNeeded => phase == Phase::ApprovalPending,
+            // A new prompt in the same conversation means the user already saw it
+            Self::Finished { .. } => !matches!(
+                phase,
+                Phase::Working | Phase::ToolPending | Phase::ApprovalPending
+            ),
+        }
+    }
+}
+
+/// A pull to a conversation's Cursor window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedPull {
+    /// When the pull was requested
+    pub at: DateTime<Utc>,
+    /// The conversation that needs the user
+    pub conversation_id: String,
+    /// What it needs them for
+    #[serde(flatten)]
+    pub kind: PullKind,
+    /// The Cursor window of the conversation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor_window: Option<WindowInfo>,
+}
+
+impl QueuedPull {
+    /// Create a pull requested now
+    pub fn new(conversation_id: &str, kind: PullKind, cursor_window: Option<WindowInfo>) -> Self {
+        Self {
+            at: Utc::now(),
+            conversation_id: conversation_id.to_string(),
+            kind,
+            cursor_window,
+        }
+    }
+
+    /// Check if this pull is stale (older than 1 hour)
+    pub fn is_stale(&self) -> bool {
+        (Utc::now() - self.at).num_hours() >= 1
+    }
+}
+
+/// The pull the user is handling and the ones waiting for their turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PullQueue {
+    /// The pull the user was last brought to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handling: Option<QueuedPull>,
+    /// Pulls waiting for their turn, in the order they were requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waiting: Vec<QueuedPull>,
+}
+
+impl PullQueue {
+    /// Whether nothing is handled or waiting
+    pub fn is_empty(&self) -> bool {
+        self.handling.is_none() && self.waiting.is_empty()
+    }
+
+    /// Ask for the user's attention. If they are handling another
+    /// conversation's pull, the new one waits (replacing an earlier pull of the
+    /// same conversation) and the pull it waits behind is returned. Otherwise
+    /// the user is now handling the new pull.
+    pub fn request(&mut self, pull: QueuedPull) -> Option<QueuedPull> {
+        self.waiting
+            .retain(|p| p.conversation_id != pull.conversation_id);
+        match self.handling {
+            Some(ref handling) if handling.conversation_id != pull.conversation_id => {
+                let behind = handling.clone();
+                self.waiting.push(pull);
+                Some(behind)
+            }
+            _ => {
+                self.handling = Some(pull);
+                None
+            }
+        }
+    }
+
+    /// Move on from the pull being handled to the most urgent waiting one,
+    /// oldest first, which the user is then handling
+    pub fn advance(&mut self) -> Option<QueuedPull> {
+        let next = self
+            .waiting
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| (p.kind.urgency(), p.at))
+            .map(|(index, _)| index);
+        self.handling = next.map(|index| self.waiting.remove(index));
+        self.handling.clone()
+    }
+
+    /// Drop pulls older than an hour
+    pub fn cleanup_stale(&mut self) {
+        if self.handling.as_ref().is_some_and(QueuedPull::is_stale) {
+            self.handling = None;
+        }
+        self.waiting.retain(|pull| !pull.is_stale());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn pull(conversation_id: &str, kind: PullKind, minutes_ago: i64) -> QueuedPull {
+        QueuedPull {
+            at: Utc::now() - Duration::minutes(minutes_ago),
+            ..QueuedPull::new(conversation_id, kind, None)
+        }
+    }
+
+    #[test]
+    fn pulls_wait_while_another_is_handled_and_approvals_go_first() {
+        let completed = PullKind::Finished {
+            status: StopStatus::Completed,
+        };
+        let mut queue = PullQueue::default();
+        assert_eq!(queue.request(pull("a", completed, 10)), None);
+
+        let behind = queue.request(pull("b", completed, 9)).expect("queued");
+        assert_eq!(behind.conversation_id, "a");
+        queue.request(pull("c", PullKind::ApprovalNeeded, 8));
+        // A newer pull of the handled conversation goes through
+        assert_eq!(queue.request(pull("a", PullKind::ApprovalNeeded, 7)), None);
+        // A newer pull of a waiting conversation replaces the older one
+        queue.request(pull("b", completed, 6));
+        assert_eq!(queue.waiting.len(), 2);
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.advance())
+            .map(|p| p.conversation_id)
+            .collect();
+        assert_eq!(order, vec!["c", "b"]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+//! Size-rotated JSON lines files
+//!
+//! Backs the audit log and the debug log. Lines are only ever appended; once
+//! the file would grow past its size limit it is renamed to `<name>.1` (older
+//! files move up one number, the oldest is dropped) and a new file is started.
+
+use anyhow::{Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A file of lines and its rotated predecessors
+#[derive(Debug, Clone)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep_files: usize,
+}
+
+impl RotatingFile {
+    /// Rotate `path` once it would grow past `max_bytes`, keeping `keep_files` old files
+    pub fn new(path: PathBuf, max_bytes: u64, keep_files: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            keep_files,
+        }
+    }
+
+    /// The current file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a line, rotating first if the file is full
+    pub fn append(&self, line: &str) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let size = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", line)
+            .with_context(|| format!("Failed to write to {}", self.path.display()))
+    }
+
+    /// Every line of the rotated files and the current one, oldest first
+    pub fn lines(&self) -> Result<Vec<String>> {
+        let mut lines = Vec::new();
+        for number in (0..=self.keep_files).rev() {
+            let path = self.rotated_path(number);
+            if !path.exists() {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            lines.extend(contents.lines().map(str::to_string));
+        }
+        Ok(lines)
+    }
+
+    /// The current file for 0, otherwise the rotated file with that number
+    pub fn rotated_path(&self, number: usize) -> PathBuf {
+        if number == 0 {
+            return self.path.clone();
+        }
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", number));
+        PathBuf::from(name)
+    }
+
+    /// Shift the rotated files up one number, dropping the oldest, and move
+    /// the current file to `.1`
+    fn rotate(&self) -> Result<()> {
+        let keep = self.keep_files;
+        let _ = fs::remove_file(self.rotated_path(keep.max(1)));
+        if keep == 0 {
+            return fs::remove_file(&self.path)
+                .with_context(|| format!("Failed to remove {}", self.path.display()));
+        }
+        for number in (1..keep).rev() {
+            let from = self.rotated_path(number);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(number + 1))
+                    .with_context(|| format!("Failed to rotate {}", from.display()))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+            .with_context(|| format!("Failed to rotate {}", self.path.display()))
+    }
+}
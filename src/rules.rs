@@ -0,0 +1,351 @@
+//! Per-application rules
+//!
+//! Rules in the config file adjust where Recursor sends the user. Each rule
+//! matches windows by app name, window class (WM_CLASS on X11) and/or a title
+//! regex, and applies one action:
+//!
+//! - `never_return`: never bounce back to this window (password managers, the
+//!   terminal running the agent, ...)
+//! - `prefer`: bounce back to this app whenever one of its windows is open
+//! - `do_not_interrupt`: never pull the user out of this window (a video call)
+//!
+//! Every decision comes with human-readable reasons, which the engine stores
+//! for `recursor status`.
+
+use crate::config::{AppRule, RuleAction, WindowMatch};
+use crate::platform::recording::describe_window;
+use crate::platform::WindowInfo;
+use regex::{Regex, RegexBuilder};
+
+impl WindowMatch {
+    /// Short description for explanations, e.g. `app=zoom.us title=/Meeting/`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ref app) = self.app {
+            parts.push(format!("app={}", app));
+        }
+        if let Some(ref class) = self.class {
+            parts.push(format!("class={}", class));
+        }
+        if let Some(ref title) = self.title {
+            parts.push(format!("title=/{}/", title));
+        }
+        if parts.is_empty() {
+            "any window".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+/// A rule with its title pattern compiled
+struct CompiledRule {
+    /// How explanations refer to the rule, e.g. `rule 2`
+    name: String,
+    rule: AppRule,
+    title: Option<Regex>,
+}
+
+impl CompiledRule {
+    /// Whether every condition of the rule holds for the window.
+    /// App and class compare case-insensitively; the title regex is unanchored
+    /// and case-insensitive.
+    fn matches(&self, window: &WindowInfo) -> bool {
+        let conditions = &self.rule.window;
+        let app_matches = conditions
+            .app
+            .as_ref()
+            .is_none_or(|app| app.eq_ignore_ascii_case(&window.app_name));
+        let class_matches = conditions.class.as_ref().is_none_or(|class| {
+            window
+                .class
+                .as_ref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(class))
+        });
+        let title_matches = self
+            .title
+            .as_ref()
+            .is_none_or(|title| title.is_match(&window.title));
+        app_matches && class_matches && title_matches
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} ({} {})",
+            self.name,
+            self.rule.action.as_str(),
+            self.rule.window.describe()
+        )
+    }
+}
+
+/// The configured rules, ready to evaluate
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    errors: Vec<String>,
+}
+
+impl RuleSet {
+    /// Compile the configured rules. Rules with an invalid title regex are
+    /// dropped and reported by `errors`.
+    pub fn new(rules: &[AppRule]) -> Self {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            let title = match rule.window.title {
+                Some(ref pattern) => {
+                    match RegexBuilder::new(pattern).case_insensitive(true).build() {
+                        Ok(regex) => Some(regex),
+                        Err(e) => {
+                            errors.push(format!(
+                                "rule {} ignored: invalid title regex: {}",
+                                index + 1,
+                                e
+                            ));
+                            continue;
+                        }
+                    }
+                }
+                None => None,
+            };
+            compiled.push(CompiledRule {
+                name: format!("rule {}", index + 1),
+                rule: rule.clone(),
+                title,
+            });
+        }
+        Self {
+            rules: compiled,
+            errors,
+        }
+    }
+
+    /// Compile rules that ship with Recursor; explanations call each one
+    /// `built-in rule`. The patterns are known to be valid.
+    pub fn builtin(rules: &[AppRule]) -> Self {
+        let mut set = Self::new(rules);
+        debug_assert!(set.errors.is_empty(), "{:?}", set.errors);
+        for rule in set.rules.iter_mut() {
+            rule.name = "built-in rule".to_string();
+        }
+        set
+    }
+
+    /// Problems found while compiling the rules
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// Whether any rule has the given action
+    pub fn has(&self, action: RuleAction) -> bool {
+        self.rules.iter().any(|r| r.rule.action == action)
+    }
+
+    /// The first rule with `action` that matches the window, described
+    fn first_match(&self, action: RuleAction, window: &WindowInfo) -> Option<String> {
+        self.rules
+            .iter()
+            .filter(|r| r.rule.action == action)
+            .find(|r| r.matches(window))
+            .map(CompiledRule::describe)
+    }
+
+    /// Whether the user may be sent back to this window
+    pub fn allows_return(&self, window: &WindowInfo, why: &mut Vec<String>) -> bool {
+        match self.first_match(RuleAction::NeverReturn, window) {
+            Some(rule) => {
+                why.push(format!("{} skipped {}", rule, describe_window(window)));
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Whether the user must not be pulled out of this window
+    pub fn protects(&self, window: &WindowInfo, why: &mut Vec<String>) -> bool {
+        match self.first_match(RuleAction::DoNotInterrupt, window) {
+            Some(rule) => {
+                why.push(format!("{} kept you in {}", rule, describe_window(window)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Choose the window to bounce back to after a prompt is submitted.
+    ///
+    /// An open window of a preferred app wins (the earliest matching rule, then
+    /// the most recently used window); otherwise the previous window, unless a
+    /// rule excludes it, in which case the most recently used window that is
+    /// neither Cursor nor excluded. `open_windows` is only called when needed.
+    pub fn choose_return_target(
+        &self,
+        previous: Option<&WindowInfo>,
+        open_windows: impl FnOnce() -> Vec<WindowInfo>,
+        why: &mut Vec<String>,
+    ) -> Option<WindowInfo> {
+        why.extend(self.errors.iter().cloned());
+
+        let needs_windows = self.has(RuleAction::Prefer)
+            || previous.is_some_and(|p| self.first_match(RuleAction::NeverReturn, p).is_some());
+        let open = if needs_windows {
+            open_windows()
+        } else {
+            Vec::new()
+        };
+        let candidates = || open.iter().filter(|w| !w.is_cursor());
+
+        for rule in self.rules.iter() {
+            if rule.rule.action != RuleAction::Prefer {
+                continue;
+            }
+            if let Some(window) = candidates().find(|w| rule.matches(w)) {
+                why.push(format!(
+                    "{} picked {}",
+                    rule.describe(),
+                    describe_window(window)
+                ));
+                return Some(window.clone());
+            }
+        }
+
+        match previous {
+            Some(window) if self.allows_return(window, why) => {
+                why.push(format!(
+                    "returning to previous window {}",
+                    describe_window(window)
+                ));
+                return Some(window.clone());
+            }
+            Some(_) => {}
+            None => {
+                why.push("no previous window".to_string());
+                return None;
+            }
+        }
+
+        // The previous window was excluded; fall back to the next most recent one
+        let mut skipped = Vec::new();
+        let fallback = candidates()
+            .find(|w| Some(*w) != previous && self.allows_return(w, &mut skipped))
+            .cloned();
+        why.extend(skipped);
+        match fallback {
+            Some(ref window) => {
+                why.push(format!("falling back to {}", describe_window(window)));
+            }
+            None => why.push("no other window to return to".to_string()),
+        }
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app_name: &str, title: &str, class: Option<&str>) -> WindowInfo {
+        WindowInfo {
+            pid: 1,
+            window_id: format!("{}:{}", app_name, title),
+            app_name: app_name.to_string(),
+            title: title.to_string(),
+            class: class.map(str::to_string),
+        }
+    }
+
+    fn rule(
+        action: RuleAction,
+        app: Option<&str>,
+        class: Option<&str>,
+        title: Option<&str>,
+    ) -> AppRule {
+        AppRule {
+            window: WindowMatch {
+                app: app.map(str::to_string),
+                class: class.map(str::to_string),
+                title: title.map(str::to_string),
+            },
+            action,
+        }
+    }
+
+    #[test]
+    fn excluded_previous_window_falls_back_to_next_recent_window() {
+        let rules = RuleSet::new(&[
+            rule(RuleAction::NeverReturn, Some("1password"), None, None),
+            rule(
+                RuleAction::NeverReturn,
+                None,
+                Some("kitty"),
+                Some("cursor-agent"),
+            ),
+        ]);
+        let vault = window("1Password", "Vault", None);
+        let agent = window("kitty", "cursor-agent: fix tests", Some("kitty"));
+        let shell = window("kitty", "~/code", Some("kitty"));
+        let cursor = window("Cursor", "main.rs - app - Cursor", None);
+
+        let mut why = Vec::new();
+        let target = rules.choose_return_target(
+            Some(&vault),
+            || vec![cursor.clone(), vault.clone(), agent.clone(), shell.clone()],
+            &mut why,
+        );
+        assert_eq!(target, Some(shell));
+        assert_eq!(
+            why,
+            vec![
+                "rule 1 (never_return app=1password) skipped 1Password \"Vault\" (pid 1)",
+                "rule 2 (never_return class=kitty title=/cursor-agent/) skipped kitty \"cursor-agent: fix tests\" (pid 1)",
+                "falling back to kitty \"~/code\" (pid 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn preferred_app_wins_when_open() {
+        let rules = RuleSet::new(&[rule(RuleAction::Prefer, Some("Spotify"), None, None)]);
+        let slack = window("Slack", "general", None);
+        let spotify = window("Spotify", "Daily Mix", None);
+
+        let mut why = Vec::new();
+        let target = rules.choose_return_target(
+            Some(&slack),
+            || vec![slack.clone(), spotify.clone()],
+            &mut why,
+        );
+        assert_eq!(target, Some(spotify));
+        assert_eq!(
+            why,
+            vec!["rule 1 (prefer app=Spotify) picked Spotify \"Daily Mix\" (pid 1)"]
+        );
+
+        // Without a Spotify window, the previous window is used as usual
+        let mut why = Vec::new();
+        assert_eq!(
+            rules.choose_return_target(Some(&slack), || vec![slack.clone()], &mut why),
+            Some(slack)
+        );
+    }
+
+    #[test]
+    fn do_not_interrupt_matches_title_case_insensitively() {
+        let rules = RuleSet::new(&[
+            rule(
+                RuleAction::DoNotInterrupt,
+                None,
+                None,
+                Some("^zoom meeting"),
+            ),
+            rule(RuleAction::Prefer, None, None, Some("(")),
+        ]);
+        assert_eq!(rules.errors().len(), 1);
+        assert!(rules.errors()[0].starts_with("rule 2 ignored: invalid title regex"));
+
+        let mut why = Vec::new();
+        assert!(rules.protects(&window("zoom.us", "Zoom Meeting", None), &mut why));
+        assert!(!rules.protects(&window("zoom.us", "Zoom Workplace", None), &mut why));
+        assert_eq!(why.len(), 1);
+    }
+}
@@ -6,14 +6,23 @@
 //!
 //! Uses conversation_id to track state per Cursor window, so multiple Cursor
 //! windows can each restore to the correct window.
+//!
+//! Hooks and their background processes change the file concurrently, so every
+//! change loads and saves it while holding a lock on `recursor_state.json.lock`.
 
+use crate::away::AwayEvent;
+use crate::bench::{Stage, Stopwatch};
+use crate::history::RunRecord;
+use crate::lifecycle::Lifecycle;
 use crate::platform::WindowInfo;
+use crate::queue::PullQueue;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use fs2::FileExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// State for a single conversation/window
@@ -29,6 +38,13 @@ pub struct ConversationState {
     /// Whether the user has manually switched to a different app
     #[serde(default)]
     pub user_switched: bool,
+    /// Workspace roots of the conversation
+    #[serde(default)]
+    pub workspace_roots: Vec<String>,
+    /// What is known so far about the run started by the prompt
+    #[serde(default)]
+    #[doc(hidden)]
+    pub run: RunRecord,
 }
 
 impl ConversationState {
@@ -39,6 +55,8 @@ impl ConversationState {
             cursor_window,
             saved_at: Utc::now(),
             user_switched: false,
+            workspace_roots: Vec::new(),
+            run: RunRecord::default(),
         }
     }
 
@@ -49,36 +67,107 @@ impl ConversationState {
     }
 }
 
+/// Why Recursor acted the way it did on a conversation's latest decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Explanation {
+    /// When the decision was made
+    pub at: DateTime<Utc>,
+    /// Lifecycle event that led to it
+    pub event: String,
+    /// Human-readable reasons, in the order they were considered
+    pub reasons: Vec<String>,
+}
+
+impl Explanation {
+    /// Check if this explanation is stale (older than 1 hour)
+    pub fn is_stale(&self) -> bool {
+        (Utc::now() - self.at).num_hours() >= 1
+    }
+}
+
 /// Full state file format - maps conversation_id to state
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct RecursorState {
     /// Map of conversation_id -> state
     #[serde(default)]
     pub conversations: HashMap<String, ConversationState>,
+    /// Map of conversation_id -> agent lifecycle
+    #[serde(default)]
+    pub lifecycles: HashMap<String, Lifecycle>,
+    /// Map of conversation_id -> explanation of the latest decision
+    #[serde(default)]
+    pub explanations: HashMap<String, Explanation>,
+    /// What needed the user while they were away, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[doc(hidden)]
+    pub away: Vec<AwayEvent>,
+    /// Pulls to Cursor across conversations
+    #[serde(default, skip_serializing_if = "PullQueue::is_empty")]
+    #[doc(hidden)]
+    pub pulls: PullQueue,
 }
 
 impl RecursorState {
     /// Clean up stale entries
     pub fn cleanup_stale(&mut self) {
         self.conversations.retain(|_, state| !state.is_stale());
+        self.lifecycles.retain(|_, lifecycle| !lifecycle.is_stale());
+        self.explanations
+            .retain(|_, explanation| !explanation.is_stale());
+        self.away.retain(|event| !event.is_stale());
+        self.pulls.cleanup_stale();
+    }
+
+    fn len(&self) -> usize {
+        self.conversations.len()
+            + self.lifecycles.len()
+            + self.explanations.len()
+            + self.away.len()
+            + self.pulls.waiting.len()
+            + usize::from(self.pulls.handling.is_some())
+    }
+}
+
+/// Held while the state file is being changed; released on drop
+struct StateLock(File);
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
     }
 }
 
 /// Manager for state file operations
 pub struct StateManager {
     state_path: PathBuf,
+    stopwatch: Option<Stopwatch>,
 }
 
 impl StateManager {
     /// Create a new state manager
     pub fn new() -> Result<Self> {
         let state_path = Self::get_state_path()?;
-        Ok(Self { state_path })
+        Ok(Self::with_state_path(state_path))
+    }
+
+    /// Create a state manager backed by a specific file
+    pub fn with_state_path(state_path: PathBuf) -> Self {
+        Self {
+            state_path,
+            stopwatch: None,
+        }
+    }
+
+    /// Time state file loads and saves (see `bench`)
+    #[doc(hidden)]
+    pub fn with_stopwatch(mut self, stopwatch: Stopwatch) -> Self {
+        self.stopwatch = Some(stopwatch);
+        self
     }
 
-    #[cfg(test)]
-    fn with_state_path(state_path: PathBuf) -> Self {
-        Self { state_path }
+    /// Path of the state file this manager reads and writes
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
     }
 
     /// Get the path to the state file
@@ -94,30 +183,75 @@ impl StateManager {
         Ok(cursor_dir.join("recursor_state.json"))
     }
 
-    /// Load the full state
+    /// Load the full state for reading. Stale entries are dropped from the
+    /// result and, best-effort, from the file.
     fn load_full(&self) -> Result<RecursorState> {
-        if !self.state_path.exists() {
-            return Ok(RecursorState::default());
+        let (state, cleaned) = self.read_full()?;
+        if cleaned {
+            // Stale entries could otherwise linger indefinitely if no new save occurs
+            let _ = self.update(|_| ());
         }
+        Ok(state)
+    }
 
-        let json = fs::read_to_string(&self.state_path).context("Failed to read state file")?;
-
-        let mut state: RecursorState = serde_json::from_str(&json).unwrap_or_default();
-        let before_cleanup = state.conversations.len();
+    /// Load, change and save the state while holding the state lock, so hooks
+    /// and background processes changing it at the same time don't undo each
+    /// other's changes
+    fn update<T>(&self, change: impl FnOnce(&mut RecursorState) -> T) -> Result<T> {
+        let _lock = self.lock()?;
+        let (mut state, _) = self.read_full()?;
+        let result = change(&mut state);
+        self.save_full(&state)?;
+        Ok(result)
+    }
 
-        // Clean up stale entries
-        state.cleanup_stale();
-        if state.conversations.len() != before_cleanup {
-            // Best-effort persistence of cleanup; stale entries can otherwise
-            // linger indefinitely if no new save operation occurs.
-            let _ = self.save_full(&state);
+    /// Wait for, then hold, the lock on the state file
+    fn lock(&self) -> Result<StateLock> {
+        let path = self.sidecar_path("lock");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create state directory")?;
         }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .context("Failed to open state lock file")?;
+        file.lock_exclusive().context("Failed to lock state file")?;
+        Ok(StateLock(file))
+    }
 
-        Ok(state)
+    /// Read the state file, dropping stale entries; also returns whether any were dropped
+    fn read_full(&self) -> Result<(RecursorState, bool)> {
+        match self.stopwatch {
+            Some(ref stopwatch) => stopwatch.time(Stage::StateLoad, || self.read_file()),
+            None => self.read_file(),
+        }
     }
 
     /// Save the full state
     fn save_full(&self, state: &RecursorState) -> Result<()> {
+        match self.stopwatch {
+            Some(ref stopwatch) => stopwatch.time(Stage::StateSave, || self.write_file(state)),
+            None => self.write_file(state),
+        }
+    }
+
+    fn read_file(&self) -> Result<(RecursorState, bool)> {
+        if !self.state_path.exists() {
+            return Ok((RecursorState::default(), false));
+        }
+
+        let json = fs::read_to_string(&self.state_path).context("Failed to read state file")?;
+
+        let mut state: RecursorState = serde_json::from_str(&json).unwrap_or_default();
+        let before_cleanup = state.len();
+        state.cleanup_stale();
+        let cleaned = state.len() != before_cleanup;
+        Ok((state, cleaned))
+    }
+
+    fn write_file(&self, state: &RecursorState) -> Result<()> {
         let json = serde_json::to_string_pretty(state).context("Failed to serialize state")?;
         if let Some(parent) = self.state_path.parent() {
             fs::create_dir_all(parent).context("Failed to create state directory")?;
@@ -132,6 +266,13 @@ impl StateManager {
         Ok(())
     }
 
+    /// A file next to the state file, e.g. `recursor_state.json.lock`
+    fn sidecar_path(&self, extension: &str) -> PathBuf {
+        let mut name = self.state_path.clone().into_os_string();
+        name.push(format!(".{}", extension));
+        PathBuf::from(name)
+    }
+
     fn temp_state_path(&self) -> PathBuf {
         let parent = self
             .state_path
@@ -162,16 +303,28 @@ impl StateManager {
         conversation_id: &str,
         saved_window: WindowInfo,
         cursor_window: Option<WindowInfo>,
+        workspace_roots: Vec<String>,
+        run: RunRecord,
     ) -> Result<()> {
-        let mut state = self.load_full()?;
-
-        let conv_state = ConversationState::new(saved_window, cursor_window);
-        state
-            .conversations
-            .insert(conversation_id.to_string(), conv_state);
+        let conv_state = ConversationState {
+            workspace_roots,
+            run,
+            ..ConversationState::new(saved_window, cursor_window)
+        };
+        self.update(|state| {
+            state
+                .conversations
+                .insert(conversation_id.to_string(), conv_state);
+        })
+    }
 
-        self.save_full(&state)?;
-        Ok(())
+    /// Count a tool call towards the conversation's current run
+    pub fn count_tool_call(&self, conversation_id: &str) -> Result<()> {
+        self.update(|state| {
+            if let Some(conv_state) = state.conversations.get_mut(conversation_id) {
+                conv_state.run.tool_calls += 1;
+            }
+        })
     }
 
     /// Load state for a specific conversation
@@ -182,20 +335,111 @@ impl StateManager {
 
     /// Clear state for a specific conversation
     pub fn clear_conversation(&self, conversation_id: &str) -> Result<()> {
-        let mut state = self.load_full()?;
-        state.conversations.remove(conversation_id);
-        self.save_full(&state)?;
-        Ok(())
+        self.update(|state| {
+            state.conversations.remove(conversation_id);
+        })
     }
 
     /// Clear all saved state
     pub fn clear(&self) -> Result<()> {
+        let _lock = self.lock()?;
         if self.state_path.exists() {
             fs::remove_file(&self.state_path).context("Failed to remove state file")?;
         }
         Ok(())
     }
 
+    /// Load the lifecycle of a conversation (idle if none is recorded)
+    pub fn load_lifecycle(&self, conversation_id: &str) -> Result<Lifecycle> {
+        let state = self.load_full()?;
+        Ok(state
+            .lifecycles
+            .get(conversation_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Save the lifecycle of a conversation
+    pub fn save_lifecycle(&self, conversation_id: &str, lifecycle: &Lifecycle) -> Result<()> {
+        self.update(|state| {
+            state
+                .lifecycles
+                .insert(conversation_id.to_string(), lifecycle.clone());
+        })
+    }
+
+    /// Get all lifecycles (for status display)
+    pub fn get_all_lifecycles(&self) -> Result<HashMap<String, Lifecycle>> {
+        let state = self.load_full()?;
+        Ok(state.lifecycles)
+    }
+
+    /// Record why the latest decision for a conversation was made
+    pub fn save_explanation(
+        &self,
+        conversation_id: &str,
+        event: &str,
+        reasons: Vec<String>,
+    ) -> Result<()> {
+        let explanation = Explanation {
+            at: Utc::now(),
+            event: event.to_string(),
+            reasons,
+        };
+        self.update(|state| {
+            state
+                .explanations
+                .insert(conversation_id.to_string(), explanation);
+        })
+    }
+
+    /// Get all explanations (for status display)
+    pub fn get_all_explanations(&self) -> Result<HashMap<String, Explanation>> {
+        let state = self.load_full()?;
+        Ok(state.explanations)
+    }
+
+    /// Record an event from while the user was away.
+    /// Returns true if it is the first one since they were last caught up.
+    #[doc(hidden)]
+    pub fn record_away(&self, event: AwayEvent) -> Result<bool> {
+        self.update(|state| {
+            let first = state.away.is_empty();
+            state.away.push(event);
+            first
+        })
+    }
+
+    /// Remove and return the events from while the user was away
+    #[doc(hidden)]
+    pub fn take_away_events(&self) -> Result<Vec<AwayEvent>> {
+        // Polled while the user is away; only write when there is something to take
+        if self.load_full()?.away.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.update(|state| std::mem::take(&mut state.away))
+    }
+
+    /// Get the events from while the user was away (for status display)
+    #[doc(hidden)]
+    pub fn get_away_events(&self) -> Result<Vec<AwayEvent>> {
+        let state = self.load_full()?;
+        Ok(state.away)
+    }
+
+    /// Change the pull queue, returning what `update` returns
+    #[doc(hidden)]
+    pub fn update_pull_queue<T>(&self, update: impl FnOnce(&mut PullQueue) -> T) -> Result<T> {
+        self.update(|state| update(&mut state.pulls))
+    }
+
+    /// Get the pull queue (for status display)
+    #[doc(hidden)]
+    pub fn get_pull_queue(&self) -> Result<PullQueue> {
+        let state = self.load_full()?;
+        Ok(state.pulls)
+    }
+
     /// Get all conversations (for status display)
     pub fn get_all_conversations(&self) -> Result<HashMap<String, ConversationState>> {
         let state = self.load_full()?;
@@ -203,7 +447,6 @@ impl StateManager {
     }
 
     /// Check if we should restore focus to Cursor for a conversation
-    #[allow(dead_code)]
     pub fn should_restore_cursor(
         &self,
         conversation_id: &str,
@@ -240,12 +483,29 @@ impl Default for StateManager {
     }
 }
 
+/// A fresh directory under the system temp dir for one test
+#[cfg(test)]
+pub(crate) fn unique_test_dir(label: &str) -> PathBuf {
+    let nonce = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let dir = std::env::temp_dir().join(format!(
+        "recursor_{}_{}_{}",
+        label,
+        std::process::id(),
+        nonce
+    ));
+    fs::create_dir_all(&dir).expect("failed to create temp test dir");
+    dir
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lifecycle::{LifecycleEvent, Phase};
     use chrono::Duration as ChronoDuration;
     use std::collections::HashMap;
-    use std::time::{SystemTime, UNIX_EPOCH};
 
     fn test_window() -> WindowInfo {
         WindowInfo {
@@ -253,24 +513,10 @@ mod tests {
             window_id: "test:1".to_string(),
             app_name: "Test".to_string(),
             title: "Test Window".to_string(),
+            class: None,
         }
     }
 
-    fn unique_test_dir(label: &str) -> PathBuf {
-        let nonce = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_nanos())
-            .unwrap_or(0);
-        let dir = std::env::temp_dir().join(format!(
-            "recursor_state_{}_{}_{}",
-            label,
-            std::process::id(),
-            nonce
-        ));
-        fs::create_dir_all(&dir).expect("failed to create temp test dir");
-        dir
-    }
-
     #[test]
     fn test_state_staleness() {
         let state = ConversationState::new(test_window(), None);
@@ -279,7 +525,7 @@ mod tests {
 
     #[test]
     fn stale_conversations_are_persistently_cleaned_up() {
-        let dir = unique_test_dir("stale_cleanup");
+        let dir = unique_test_dir("state_stale_cleanup");
         let state_path = dir.join("recursor_state.json");
         let manager = StateManager::with_state_path(state_path.clone());
 
@@ -291,6 +537,8 @@ mod tests {
                 cursor_window: None,
                 saved_at: Utc::now() - ChronoDuration::hours(2),
                 user_switched: false,
+                workspace_roots: Vec::new(),
+                run: RunRecord::default(),
             },
         );
         conversations.insert(
@@ -300,9 +548,14 @@ mod tests {
                 cursor_window: None,
                 saved_at: Utc::now(),
                 user_switched: false,
+                workspace_roots: Vec::new(),
+                run: RunRecord::default(),
             },
         );
-        let state = RecursorState { conversations };
+        let state = RecursorState {
+            conversations,
+            ..Default::default()
+        };
         let json = serde_json::to_string_pretty(&state).expect("serialize state");
         fs::write(&state_path, json).expect("write initial state");
 
@@ -321,12 +574,18 @@ mod tests {
 
     #[test]
     fn save_conversation_does_not_leave_tmp_files() {
-        let dir = unique_test_dir("tmp_cleanup");
+        let dir = unique_test_dir("state_tmp_cleanup");
         let state_path = dir.join("recursor_state.json");
         let manager = StateManager::with_state_path(state_path.clone());
 
         manager
-            .save_conversation("conv-1", test_window(), None)
+            .save_conversation(
+                "conv-1",
+                test_window(),
+                None,
+                Vec::new(),
+                RunRecord::default(),
+            )
             .expect("save state");
 
         assert!(state_path.exists());
@@ -340,9 +599,70 @@ mod tests {
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn lifecycles_persist_alongside_conversations() {
+        let dir = unique_test_dir("state_lifecycle");
+        let manager = StateManager::with_state_path(dir.join("recursor_state.json"));
+
+        assert_eq!(
+            manager.load_lifecycle("conv-1").expect("load").phase,
+            Phase::Idle
+        );
+        let working = Lifecycle::default()
+            .apply(LifecycleEvent::PromptSubmitted)
+            .expect("submit");
+        manager
+            .save_conversation(
+                "conv-1",
+                test_window(),
+                None,
+                Vec::new(),
+                RunRecord::default(),
+            )
+            .expect("save conversation");
+        manager
+            .save_lifecycle("conv-1", &working)
+            .expect("save lifecycle");
+        manager
+            .clear_conversation("conv-1")
+            .expect("clear conversation");
+
+        assert_eq!(manager.load_lifecycle("conv-1").expect("load"), working);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_updates_keep_each_others_changes() {
+        let dir = unique_test_dir("state_concurrent");
+        let state_path = dir.join("recursor_state.json");
+        let working = Lifecycle::default()
+            .apply(LifecycleEvent::PromptSubmitted)
+            .expect("submit");
+
+        std::thread::scope(|scope| {
+            for thread in ["a", "b"] {
+                let manager = StateManager::with_state_path(state_path.clone());
+                let working = working.clone();
+                scope.spawn(move || {
+                    for index in 0..50 {
+                        manager
+                            .save_lifecycle(&format!("{}-{}", thread, index), &working)
+                            .expect("save lifecycle");
+                    }
+                });
+            }
+        });
+
+        let manager = StateManager::with_state_path(state_path);
+        assert_eq!(manager.get_all_lifecycles().expect("load").len(), 100);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn invalid_json_falls_back_to_default_state() {
-        let dir = unique_test_dir("invalid_json");
+        let dir = unique_test_dir("state_invalid_json");
         let state_path = dir.join("recursor_state.json");
         let manager = StateManager::with_state_path(state_path.clone());
 
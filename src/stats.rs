@@ -0,0 +1,483 @@
+//! Run statistics from the history
+//!
+//! Summarizes the history per day, workspace and model: finished runs and
+//! their run times, commands that needed approval and how long they waited for
+//! it, time spent parked in other apps while agents worked, and how often
+//! Recursor moved focus. Also compares the run times predicted at submit (see
+//! `predict`) with the actual ones.
+
+use crate::failsafe::approval_wait_seconds;
+use crate::history::{FocusTarget, HistoryEntry, HistoryEvent};
+use crate::predict::{quantile, Sample};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Totals for a group of history events
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GroupStats {
+    /// Day, workspace or model the events share ("unknown" if not reported)
+    pub name: String,
+    /// Number of finished runs
+    pub runs: usize,
+    /// Median run time
+    pub median_seconds: f64,
+    /// 90th percentile run time
+    pub p90_seconds: f64,
+    /// Tool calls still pending when the failsafe fired
+    pub approvals: usize,
+    /// Seconds tool calls spent waiting for approval
+    pub approval_wait_seconds: f64,
+    /// Seconds spent in other apps while agents worked
+    pub parked_seconds: f64,
+    /// Times Recursor moved focus
+    pub focus_changes: usize,
+}
+
+/// A run whose time was predicted at submit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PredictedRun {
+    /// Conversation of the run
+    pub conversation_id: String,
+    /// Predicted run time
+    pub predicted_seconds: f64,
+    /// Actual run time
+    pub actual_seconds: f64,
+    /// Whether the prediction kept the user in Cursor
+    pub skipped_bounce: bool,
+}
+
+/// How well predictions matched the actual run times
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PredictionStats {
+    /// Every predicted run, oldest first
+    pub runs: Vec<PredictedRun>,
+    /// Median of |predicted - actual|
+    pub median_error_seconds: f64,
+    /// Runs where the user stayed in Cursor
+    pub skipped_bounces: usize,
+    /// Of those, runs that took longer than a short run
+    pub wrong_skips: usize,
+}
+
+/// Summary of the history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// Number of finished runs
+    pub runs: usize,
+    /// Totals over the whole history
+    pub total: GroupStats,
+    /// Totals per day (local time), oldest first
+    pub by_day: Vec<GroupStats>,
+    /// Totals per workspace, most runs first
+    pub by_workspace: Vec<GroupStats>,
+    /// Totals per model, most runs first
+    pub by_model: Vec<GroupStats>,
+    /// Predictions against actual run times, if any were made
+    pub predictions: Option<PredictionStats>,
+}
+
+/// What a history event adds to the totals of its groups
+enum Item {
+    Run(f64),
+    Approval,
+    ApprovalWait(f64),
+    Parked(f64),
+    FocusChange,
+}
+
+/// An item with the groups it counts towards
+struct Row {
+    day: String,
+    workspace: String,
+    model: String,
+    item: Item,
+}
+
+impl Stats {
+    /// Summarize history entries, oldest first; runs longer than
+    /// `short_run_seconds` should not have skipped the bounce
+    pub fn from_entries(entries: &[HistoryEntry], short_run_seconds: f64) -> Self {
+        let rows = rows(entries);
+
+        let mut runs = Vec::new();
+        for entry in entries {
+            let HistoryEvent::Stop {
+                followup: false,
+                run: Some(ref run),
+                ..
+            } = entry.event
+            else {
+                continue;
+            };
+            if let Some(predicted_seconds) = run.predicted_seconds {
+                runs.push(PredictedRun {
+                    conversation_id: entry.conversation_id.clone(),
+                    predicted_seconds,
+                    actual_seconds: run.duration_seconds,
+                    skipped_bounce: run.skipped_bounce,
+                });
+            }
+        }
+        let predictions = (!runs.is_empty()).then(|| {
+            let mut errors: Vec<f64> = runs
+                .iter()
+                .map(|r| (r.predicted_seconds - r.actual_seconds).abs())
+                .collect();
+            errors.sort_by(f64::total_cmp);
+            let skipped: Vec<&PredictedRun> = runs.iter().filter(|r| r.skipped_bounce).collect();
+            PredictionStats {
+                median_error_seconds: quantile(&errors, 0.5),
+                skipped_bounces: skipped.len(),
+                wrong_skips: skipped
+                    .iter()
+                    .filter(|r| r.actual_seconds > short_run_seconds)
+                    .count(),
+                runs,
+            }
+        });
+
+        let total = totals("all", rows.iter());
+        let mut by_day = group(&rows, |r| &r.day);
+        by_day.sort_by(|a, b| a.name.cmp(&b.name));
+        let by_most_runs =
+            |a: &GroupStats, b: &GroupStats| b.runs.cmp(&a.runs).then_with(|| a.name.cmp(&b.name));
+        let mut by_workspace = group(&rows, |r| &r.workspace);
+        by_workspace.sort_by(by_most_runs);
+        let mut by_model = group(&rows, |r| &r.model);
+        by_model.sort_by(by_most_runs);
+
+        Self {
+            runs: total.runs,
+            total,
+            by_day,
+            by_workspace,
+            by_model,
+            predictions,
+        }
+    }
+
+    /// The totals as CSV: a header, then one line for the whole history and one
+    /// per day, workspace and model
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "group,name,runs,median_seconds,p90_seconds,approvals,approval_wait_seconds,parked_seconds,focus_changes\n",
+        );
+        let groups = std::iter::once(("all", &self.total))
+            .chain(self.by_day.iter().map(|g| ("day", g)))
+            .chain(self.by_workspace.iter().map(|g| ("workspace", g)))
+            .chain(self.by_model.iter().map(|g| ("model", g)));
+        for (kind, g) in groups {
+            csv.push_str(&format!(
+                "{},{},{},{:.1},{:.1},{},{:.1},{:.1},{}\n",
+                kind,
+                csv_field(&g.name),
+                g.runs,
+                g.median_seconds,
+                g.p90_seconds,
+                g.approvals,
+                g.approval_wait_seconds,
+                g.parked_seconds,
+                g.focus_changes
+            ));
+        }
+        csv
+    }
+}
+
+/// Parse a `--since` value: a duration back from `now` (`30m`, `12h`, `7d`,
+/// `2w`), a local date (`2026-01-31`) or an RFC 3339 timestamp
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Some(unit) = value.chars().last() {
+        if let Ok(amount) = value[..value.len() - unit.len_utf8()].parse::<i64>() {
+            let duration = match unit {
+                'm' => Some(Duration::minutes(amount)),
+                'h' => Some(Duration::hours(amount)),
+                'd' => Some(Duration::days(amount)),
+                'w' => Some(Duration::weeks(amount)),
+                _ => None,
+            };
+            if let Some(duration) = duration {
+                return Ok(now - duration);
+            }
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        if let Some(midnight) = date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| Local.from_local_datetime(&t).earliest())
+        {
+            return Ok(midnight.with_timezone(&Utc));
+        }
+    }
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    bail!(
+        "Invalid --since value '{}': use a duration like 7d or 12h, or a date like 2026-01-31",
+        value
+    )
+}
+
+/// Turn history entries into items, each with the day, workspace and model it
+/// counts towards. Events other than stops take the workspace and model of
+/// their conversation's latest prompt.
+fn rows(entries: &[HistoryEntry]) -> Vec<Row> {
+    let mut context: HashMap<&str, (Option<String>, Option<String>)> = HashMap::new();
+    // Conversations whose user was sent to another app, and since when
+    let mut parked_since: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    let mut rows = Vec::new();
+
+    for entry in entries {
+        let id = entry.conversation_id.as_str();
+        let mut items = Vec::new();
+        let mut end_parked = |items: &mut Vec<Item>| {
+            if let Some(since) = parked_since.remove(id) {
+                let seconds = (entry.at - since).num_milliseconds() as f64 / 1000.0;
+                items.push(Item::Parked(seconds.max(0.0)));
+            }
+        };
+        match entry.event {
+            HistoryEvent::Submit {
+                ref workspace_roots,
+                ref model,
+            } => {
+                context.insert(id, (workspace_roots.first().cloned(), model.clone()));
+            }
+            HistoryEvent::ApprovalNeeded => items.push(Item::Approval),
+            HistoryEvent::ToolCall {
+                pending_seconds,
+                run_seconds,
+                ..
+            } => {
+                if let Some(wait) = approval_wait_seconds(pending_seconds, run_seconds) {
+                    items.push(Item::ApprovalWait(wait));
+                }
+            }
+            HistoryEvent::Focus { to } => {
+                items.push(Item::FocusChange);
+                match to {
+                    FocusTarget::App => {
+                        parked_since.entry(id).or_insert(entry.at);
+                    }
+                    FocusTarget::Cursor => end_parked(&mut items),
+                }
+            }
+            HistoryEvent::Stop { followup, .. } => {
+                if let Some(sample) = Sample::from_entry(entry) {
+                    context.insert(id, (sample.workspace, sample.model));
+                    items.push(Item::Run(sample.seconds));
+                }
+                // A follow-up keeps the agent working and the user parked
+                if !followup {
+                    end_parked(&mut items);
+                }
+            }
+        }
+
+        let (workspace, model) = context.get(id).cloned().unwrap_or_default();
+        let unknown = || "unknown".to_string();
+        let day = entry
+            .at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d")
+            .to_string();
+        rows.extend(items.into_iter().map(|item| Row {
+            day: day.clone(),
+            workspace: workspace.clone().unwrap_or_else(unknown),
+            model: model.clone().unwrap_or_else(unknown),
+            item,
+        }));
+    }
+    rows
+}
+
+/// Totals per value of `key`, in order of first appearance
+fn group<'a>(rows: &'a [Row], key: impl Fn(&'a Row) -> &'a str) -> Vec<GroupStats> {
+    let mut groups: Vec<(&str, Vec<&Row>)> = Vec::new();
+    for row in rows {
+        let name = key(row);
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, members)) => members.push(row),
+            None => groups.push((name, vec![row])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(name, members)| totals(name, members))
+        .collect()
+}
+
+/// Add up the items of a group
+fn totals<'a>(name: &str, rows: impl IntoIterator<Item = &'a Row>) -> GroupStats {
+    let mut stats = GroupStats {
+        name: name.to_string(),
+        ..GroupStats::default()
+    };
+    let mut seconds = Vec::new();
+    for row in rows {
+        match row.item {
+            Item::Run(run) => seconds.push(run),
+            Item::Approval => stats.approvals += 1,
+            Item::ApprovalWait(wait) => stats.approval_wait_seconds += wait,
+            Item::Parked(parked) => stats.parked_seconds += parked,
+            Item::FocusChange => stats.focus_changes += 1,
+        }
+    }
+    if !seconds.is_empty() {
+        seconds.sort_by(f64::total_cmp);
+        stats.runs = seconds.len();
+        stats.median_seconds = quantile(&seconds, 0.5);
+        stats.p90_seconds = quantile(&seconds, 0.9);
+    }
+    stats
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::RunRecord;
+    use crate::hooks::StopStatus;
+
+    fn stop(model: &str, seconds: f64, predicted: Option<f64>, followup: bool) -> HistoryEntry {
+        HistoryEntry {
+            at: Utc::now(),
+            conversation_id: format!("{}-{}", model, seconds),
+            event: HistoryEvent::Stop {
+                status: StopStatus::Completed,
+                loop_count: 0,
+                workspace_roots: vec!["/app".to_string()],
+                followup,
+                run: Some(RunRecord {
+                    model: Some(model.to_string()),
+                    duration_seconds: seconds,
+                    predicted_seconds: predicted,
+                    skipped_bounce: predicted.is_some_and(|p| p <= 10.0),
+                    ..RunRecord::default()
+                }),
+            },
+        }
+    }
+
+    fn event(at: &str, conversation_id: &str, event: HistoryEvent) -> HistoryEntry {
+        HistoryEntry {
+            at: DateTime::parse_from_rfc3339(at)
+                .expect("timestamp")
+                .with_timezone(&Utc),
+            conversation_id: conversation_id.to_string(),
+            event,
+        }
+    }
+
+    #[test]
+    fn groups_runs_and_scores_predictions() {
+        let entries = vec![
+            stop("fast", 4.0, None, false),
+            stop("fast", 6.0, Some(5.0), false),
+            stop("fast", 30.0, Some(5.0), false),
+            stop("slow", 120.0, Some(100.0), false),
+            // Answered with a follow-up: the run went on
+            stop("slow", 1.0, None, true),
+        ];
+        let stats = Stats::from_entries(&entries, 10.0);
+
+        assert_eq!(stats.runs, 4);
+        assert_eq!(stats.by_model[0].name, "fast");
+        assert_eq!(stats.by_model[0].runs, 3);
+        assert_eq!(stats.by_model[0].median_seconds, 6.0);
+        assert_eq!(stats.by_model[0].p90_seconds, 30.0);
+        assert_eq!(stats.by_workspace.len(), 1);
+
+        let predictions = stats.predictions.expect("predictions");
+        assert_eq!(predictions.runs.len(), 3);
+        assert_eq!(predictions.median_error_seconds, 20.0);
+        assert_eq!(predictions.skipped_bounces, 2);
+        assert_eq!(predictions.wrong_skips, 1);
+    }
+
+    #[test]
+    fn counts_approvals_parked_time_and_focus_changes() {
+        let focus = |to| HistoryEvent::Focus { to };
+        let entries = vec![
+            event(
+                "2026-03-02T12:00:00Z",
+                "c1",
+                HistoryEvent::Submit {
+                    workspace_roots: vec!["/api".to_string()],
+                    model: Some("fast".to_string()),
+                },
+            ),
+            event("2026-03-02T12:00:01Z", "c1", focus(FocusTarget::App)),
+            event("2026-03-02T12:00:21Z", "c1", HistoryEvent::ApprovalNeeded),
+            event("2026-03-02T12:00:21Z", "c1", focus(FocusTarget::Cursor)),
+            event(
+                "2026-03-02T12:00:30Z",
+                "c1",
+                HistoryEvent::ToolCall {
+                    prefix: "cargo test".to_string(),
+                    pending_seconds: 29.0,
+                    run_seconds: Some(4.0),
+                },
+            ),
+            event("2026-03-02T12:00:30Z", "c1", focus(FocusTarget::App)),
+            event(
+                "2026-03-02T12:01:00Z",
+                "c1",
+                HistoryEvent::Stop {
+                    status: StopStatus::Completed,
+                    loop_count: 0,
+                    workspace_roots: vec!["/api".to_string()],
+                    followup: false,
+                    run: Some(RunRecord {
+                        model: Some("fast".to_string()),
+                        duration_seconds: 60.0,
+                        ..RunRecord::default()
+                    }),
+                },
+            ),
+            event("2026-03-02T12:01:00Z", "c1", focus(FocusTarget::Cursor)),
+        ];
+        let stats = Stats::from_entries(&entries, 10.0);
+
+        let expected = GroupStats {
+            name: "/api".to_string(),
+            runs: 1,
+            median_seconds: 60.0,
+            p90_seconds: 60.0,
+            approvals: 1,
+            approval_wait_seconds: 25.0,
+            parked_seconds: 50.0,
+            focus_changes: 4,
+        };
+        assert_eq!(stats.by_workspace, vec![expected.clone()]);
+        assert_eq!(stats.by_model[0].name, "fast");
+        assert_eq!(stats.by_day.len(), 1);
+        assert_eq!(
+            stats.total,
+            GroupStats {
+                name: "all".to_string(),
+                ..expected
+            }
+        );
+        assert_eq!(
+            stats.to_csv().lines().nth(3),
+            Some("workspace,/api,1,60.0,60.0,1,25.0,50.0,4")
+        );
+
+        let now = Utc::now();
+        assert_eq!(parse_since("7d", now).expect("7d"), now - Duration::days(7));
+        assert!(parse_since("2026-03-02", now).is_ok());
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
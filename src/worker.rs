@@ -0,0 +1,305 @@
+//! Hook fast path
+//!
+//! Cursor waits for every hook to answer before it carries on, but the answer
+//! almost never depends on the window work: prompts and commands are always
+//! allowed, and only a `stop` with an error followup says anything more. So a
+//! hook works out its answer from the config and the saved lifecycle (see
+//! `engine::respond`), queues the event with that answer in
+//! `~/.cursor/recursor_jobs.jsonl`, answers, and leaves focus and
+//! media to `recursor hook-worker`. Workers take turns through a lock file and
+//! handle queued events oldest first, so the engine sees them in the order
+//! Cursor sent them. A `stop` takes the worker lock and handles what is
+//! queued before it answers, since its answer depends on the lifecycle.
+//!
+//! How long each hook took to answer is kept in
+//! `~/.cursor/recursor_latency.jsonl`; `recursor doctor` compares it with the
+//! budgets in `fast_path`.
+
+use crate::config::FastPathConfig;
+use crate::rotating::RotatingFile;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Latency samples kept before the file rotates (roughly a few thousand hooks)
+const LATENCY_FILE_BYTES: u64 = 200_000;
+
+/// A hook event waiting for the worker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookJob {
+    /// When the hook answered
+    pub at: DateTime<Utc>,
+    /// Subcommand that received it, e.g. `save --no-focus`
+    pub command: String,
+    /// Raw hook input
+    pub raw: String,
+    /// What Cursor was told
+    #[serde(default)]
+    pub answer: Option<serde_json::Value>,
+}
+
+/// Hook events handed from hooks to the worker
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    path: PathBuf,
+}
+
+/// Held while a worker is handling jobs; released on drop
+#[derive(Debug)]
+pub struct WorkerLock(File);
+
+impl Drop for WorkerLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+impl JobQueue {
+    /// Open the queue at the default location
+    pub fn new() -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self::with_path(
+            home.join(".cursor").join("recursor_jobs.jsonl"),
+        ))
+    }
+
+    /// Open the queue at a specific path
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The queue file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add a job to the end of the queue
+    pub fn push(&self, job: &HookJob) -> Result<()> {
+        let line = serde_json::to_string(job)?;
+        let mut file = self.open_locked()?;
+        file.seek(std::io::SeekFrom::End(0))?;
+        writeln!(file, "{}", line)
+            .with_context(|| format!("Failed to write to {}", self.path.display()))
+    }
+
+    /// Remove and return every queued job, oldest first, skipping lines that
+    /// fail to parse
+    pub fn take_all(&self) -> Result<Vec<HookJob>> {
+        let mut file = self.open_locked()?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        file.set_len(0)
+            .with_context(|| format!("Failed to clear {}", self.path.display()))?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Wait until no other worker is running, then hold the worker lock
+    pub fn lock_worker(&self) -> Result<WorkerLock> {
+        let path = self.path.with_extension("lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(WorkerLock(file))
+    }
+
+    /// Open the queue file, creating it if needed, and lock it against other
+    /// hooks and workers until it is dropped
+    fn open_locked(&self) -> Result<File> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(false)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("Failed to lock {}", self.path.display()))?;
+        Ok(file)
+    }
+}
+
+/// How long one hook took to answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySample {
+    /// When it answered
+    pub at: DateTime<Utc>,
+    /// Hook event name
+    pub event: String,
+    /// Milliseconds from the hook starting to its answer being written
+    pub response_ms: u64,
+}
+
+/// Hook response times
+#[derive(Debug, Clone)]
+pub struct LatencyLog {
+    file: RotatingFile,
+}
+
+impl LatencyLog {
+    /// Open the latency log at the default location
+    pub fn new() -> Result<Self> {
+        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Ok(Self::with_path(
+            home.join(".cursor").join("recursor_latency.jsonl"),
+        ))
+    }
+
+    /// Open the latency log at a specific path
+    pub fn with_path(path: PathBuf) -> Self {
+        Self {
+            file: RotatingFile::new(path, LATENCY_FILE_BYTES, 1),
+        }
+    }
+
+    /// Record how long a hook took to answer
+    pub fn record(&self, event: &str, response_ms: u64) -> Result<()> {
+        let sample = LatencySample {
+            at: Utc::now(),
+            event: event.to_string(),
+            response_ms,
+        };
+        self.file.append(&serde_json::to_string(&sample)?)
+    }
+
+    /// Every recorded sample, oldest first, skipping lines that fail to parse
+    pub fn samples(&self) -> Result<Vec<LatencySample>> {
+        Ok(self
+            .file
+            .lines()?
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Response times of one hook event against its budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatencyReport {
+    /// Hook event name
+    pub event: String,
+    /// Number of samples
+    pub samples: usize,
+    /// Median response time in milliseconds
+    pub p50_ms: u64,
+    /// 90th percentile response time in milliseconds
+    pub p90_ms: u64,
+    /// The event's budget in milliseconds
+    pub budget_ms: u64,
+    /// Samples that took longer than the budget
+    pub over_budget: usize,
+}
+
+impl LatencyReport {
+    /// Whether the event usually answers within its budget
+    pub fn within_budget(&self) -> bool {
+        self.p90_ms <= self.budget_ms
+    }
+}
+
+/// Summarize samples per event, in event name order
+pub fn latency_report(samples: &[LatencySample], config: &FastPathConfig) -> Vec<LatencyReport> {
+    let mut by_event: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    for sample in samples {
+        by_event
+            .entry(sample.event.as_str())
+            .or_default()
+            .push(sample.response_ms);
+    }
+    by_event
+        .into_iter()
+        .map(|(event, mut times)| {
+            times.sort_unstable();
+            let budget_ms = config.budget_for(event);
+            LatencyReport {
+                event: event.to_string(),
+                samples: times.len(),
+                p50_ms: percentile(&times, 50),
+                p90_ms: percentile(&times, 90),
+                budget_ms,
+                over_budget: times.iter().filter(|&&t| t > budget_ms).count(),
+            }
+        })
+        .collect()
+}
+
+/// Nearest-rank percentile of sorted, non-empty values
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::unique_test_dir;
+    use std::collections::HashMap;
+
+    #[test]
+    fn jobs_come_out_in_order_once() {
+        let dir = unique_test_dir("worker_queue");
+        let queue = JobQueue::with_path(dir.join("recursor_jobs.jsonl"));
+        for command in ["save", "before-shell", "restore"] {
+            queue
+                .push(&HookJob {
+                    at: Utc::now(),
+                    command: command.to_string(),
+                    raw: "{}".to_string(),
+                    answer: None,
+                })
+                .expect("push");
+        }
+        let _lock = queue.lock_worker().expect("lock");
+        let commands: Vec<String> = queue
+            .take_all()
+            .expect("take")
+            .into_iter()
+            .map(|job| job.command)
+            .collect();
+        assert_eq!(commands, ["save", "before-shell", "restore"]);
+        assert!(queue.take_all().expect("take").is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn latency_is_compared_with_budgets() {
+        let dir = unique_test_dir("worker_latency");
+        let log = LatencyLog::with_path(dir.join("recursor_latency.jsonl"));
+        for ms in [5, 8, 12, 9, 30] {
+            log.record("beforeSubmitPrompt", ms).expect("record");
+        }
+        log.record("stop", 120).expect("record");
+        let config = FastPathConfig {
+            event_budgets_ms: HashMap::from([("stop".to_string(), 100)]),
+            ..FastPathConfig::default()
+        };
+        let report = latency_report(&log.samples().expect("samples"), &config);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].event, "beforeSubmitPrompt");
+        assert_eq!((report[0].p50_ms, report[0].p90_ms), (9, 30));
+        assert!(report[0].within_budget());
+        assert_eq!(report[1].budget_ms, 100);
+        assert_eq!(report[1].over_budget, 1);
+        assert!(!report[1].within_budget());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}